@@ -0,0 +1,458 @@
+use rocket::http::Status;
+use rocket::request::Request;
+use rocket::response::{self, Responder};
+use rocket::serde::{Deserialize, Serialize};
+use rocket::serde::json::Json;
+use rocket::serde::json::Error as JsonError;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use crate::audit_log::AuditLogError;
+use crate::auth::AuthError;
+use crate::menu_service::MenuServiceError;
+use crate::order_service::OrderServiceError;
+use crate::table_service::TableServiceError;
+use crate::validation::{FieldError, Validator};
+use crate::webhook_service::WebhookServiceError;
+
+// A client may pass its own correlation id in this header, e.g. to tie an error to the request
+// that caused it in its own logs. Otherwise one is generated. Either way, it is sent back in the
+// header and in the body of an error, and logged along with internal errors.
+pub const CORRELATION_HEADER: &str = "X-Correlation-ID";
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct ErrorResponse {
+    pub error: String,
+    pub error_code: String,
+    // The violations of a VALIDATION_FAILED request, or the fields a DUPLICATE_ORDER differs in.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>,
+    pub correlation_id: String,
+}
+
+// ErrorCode is the catalog of error codes the API responds with, served at GET /errors.
+// Every code comes with a single status.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    BadRequest,
+    InvalidBody,
+    InvalidStatus,
+    InvalidTimestamp,
+    InvalidLimit,
+    InvalidSort,
+    InvalidCursor,
+    InvalidEventId,
+    InvalidSeq,
+    InvalidEvent,
+    InvalidOperation,
+    Unauthenticated,
+    Forbidden,
+    NotFound,
+    OrderNotFound,
+    ItemNotFound,
+    TableNotFound,
+    WebhookNotFound,
+    MethodNotAllowed,
+    DuplicateOrder,
+    TableClosed,
+    InvalidTransition,
+    ResyncRequired,
+    UnsupportedMediaType,
+    UnprocessableEntity,
+    ValidationFailed,
+    UnknownItem,
+    ItemUnavailable,
+    UnknownTable,
+    InvalidOrder,
+    InvalidMenuItem,
+    InvalidTable,
+    InvalidWebhook,
+    InternalError,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 34] = [
+        ErrorCode::BadRequest,
+        ErrorCode::InvalidBody,
+        ErrorCode::InvalidStatus,
+        ErrorCode::InvalidTimestamp,
+        ErrorCode::InvalidLimit,
+        ErrorCode::InvalidSort,
+        ErrorCode::InvalidCursor,
+        ErrorCode::InvalidEventId,
+        ErrorCode::InvalidSeq,
+        ErrorCode::InvalidEvent,
+        ErrorCode::InvalidOperation,
+        ErrorCode::Unauthenticated,
+        ErrorCode::Forbidden,
+        ErrorCode::NotFound,
+        ErrorCode::OrderNotFound,
+        ErrorCode::ItemNotFound,
+        ErrorCode::TableNotFound,
+        ErrorCode::WebhookNotFound,
+        ErrorCode::MethodNotAllowed,
+        ErrorCode::DuplicateOrder,
+        ErrorCode::TableClosed,
+        ErrorCode::InvalidTransition,
+        ErrorCode::ResyncRequired,
+        ErrorCode::UnsupportedMediaType,
+        ErrorCode::UnprocessableEntity,
+        ErrorCode::ValidationFailed,
+        ErrorCode::UnknownItem,
+        ErrorCode::ItemUnavailable,
+        ErrorCode::UnknownTable,
+        ErrorCode::InvalidOrder,
+        ErrorCode::InvalidMenuItem,
+        ErrorCode::InvalidTable,
+        ErrorCode::InvalidWebhook,
+        ErrorCode::InternalError,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::BadRequest => "BAD_REQUEST",
+            ErrorCode::InvalidBody => "INVALID_BODY",
+            ErrorCode::InvalidStatus => "INVALID_STATUS",
+            ErrorCode::InvalidTimestamp => "INVALID_TIMESTAMP",
+            ErrorCode::InvalidLimit => "INVALID_LIMIT",
+            ErrorCode::InvalidSort => "INVALID_SORT",
+            ErrorCode::InvalidCursor => "INVALID_CURSOR",
+            ErrorCode::InvalidEventId => "INVALID_EVENT_ID",
+            ErrorCode::InvalidSeq => "INVALID_SEQ",
+            ErrorCode::InvalidEvent => "INVALID_EVENT",
+            ErrorCode::InvalidOperation => "INVALID_OPERATION",
+            ErrorCode::Unauthenticated => "UNAUTHENTICATED",
+            ErrorCode::Forbidden => "FORBIDDEN",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::OrderNotFound => "ORDER_NOT_FOUND",
+            ErrorCode::ItemNotFound => "ITEM_NOT_FOUND",
+            ErrorCode::TableNotFound => "TABLE_NOT_FOUND",
+            ErrorCode::WebhookNotFound => "WEBHOOK_NOT_FOUND",
+            ErrorCode::MethodNotAllowed => "METHOD_NOT_ALLOWED",
+            ErrorCode::DuplicateOrder => "DUPLICATE_ORDER",
+            ErrorCode::TableClosed => "TABLE_CLOSED",
+            ErrorCode::InvalidTransition => "INVALID_TRANSITION",
+            ErrorCode::ResyncRequired => "RESYNC_REQUIRED",
+            ErrorCode::UnsupportedMediaType => "UNSUPPORTED_MEDIA_TYPE",
+            ErrorCode::UnprocessableEntity => "UNPROCESSABLE_ENTITY",
+            ErrorCode::ValidationFailed => "VALIDATION_FAILED",
+            ErrorCode::UnknownItem => "UNKNOWN_ITEM",
+            ErrorCode::ItemUnavailable => "ITEM_UNAVAILABLE",
+            ErrorCode::UnknownTable => "UNKNOWN_TABLE",
+            ErrorCode::InvalidOrder => "INVALID_ORDER",
+            ErrorCode::InvalidMenuItem => "INVALID_MENU_ITEM",
+            ErrorCode::InvalidTable => "INVALID_TABLE",
+            ErrorCode::InvalidWebhook => "INVALID_WEBHOOK",
+            ErrorCode::InternalError => "INTERNAL_ERROR",
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        match self {
+            ErrorCode::BadRequest
+            | ErrorCode::InvalidBody
+            | ErrorCode::InvalidStatus
+            | ErrorCode::InvalidTimestamp
+            | ErrorCode::InvalidLimit
+            | ErrorCode::InvalidSort
+            | ErrorCode::InvalidCursor
+            | ErrorCode::InvalidEventId
+            | ErrorCode::InvalidSeq
+            | ErrorCode::InvalidEvent
+            | ErrorCode::InvalidOperation => Status::BadRequest,
+            ErrorCode::Unauthenticated => Status::Unauthorized,
+            ErrorCode::Forbidden => Status::Forbidden,
+            ErrorCode::NotFound
+            | ErrorCode::OrderNotFound
+            | ErrorCode::ItemNotFound
+            | ErrorCode::TableNotFound
+            | ErrorCode::WebhookNotFound => Status::NotFound,
+            ErrorCode::MethodNotAllowed => Status::MethodNotAllowed,
+            ErrorCode::DuplicateOrder
+            | ErrorCode::TableClosed
+            | ErrorCode::InvalidTransition => Status::Conflict,
+            ErrorCode::ResyncRequired => Status::Gone,
+            ErrorCode::UnsupportedMediaType => Status::UnsupportedMediaType,
+            ErrorCode::UnprocessableEntity
+            | ErrorCode::ValidationFailed
+            | ErrorCode::UnknownItem
+            | ErrorCode::ItemUnavailable
+            | ErrorCode::UnknownTable
+            | ErrorCode::InvalidOrder
+            | ErrorCode::InvalidMenuItem
+            | ErrorCode::InvalidTable
+            | ErrorCode::InvalidWebhook => Status::UnprocessableEntity,
+            ErrorCode::InternalError => Status::InternalServerError,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ErrorCode::BadRequest => "The request was rejected before reaching the API, e.g. because of a malformed header",
+            ErrorCode::InvalidBody => "The request body is not valid JSON or lacks required fields",
+            ErrorCode::InvalidStatus => "Unknown order or delivery status",
+            ErrorCode::InvalidTimestamp => "A timestamp is not in RFC 3339 format",
+            ErrorCode::InvalidLimit => "The page size is out of bounds",
+            ErrorCode::InvalidSort => "Unknown sort key or direction",
+            ErrorCode::InvalidCursor => "The cursor is malformed or was issued for a different sort order",
+            ErrorCode::InvalidEventId => "Last-Event-ID is not an event id",
+            ErrorCode::InvalidSeq => "since is not a sequence number",
+            ErrorCode::InvalidEvent => "Unknown webhook event",
+            ErrorCode::InvalidOperation => "Unknown audit operation",
+            ErrorCode::Unauthenticated => "The bearer token is missing, invalid or expired, or the enrollment key is wrong",
+            ErrorCode::Forbidden => "The device's role does not allow the request",
+            ErrorCode::NotFound => "No route matches the path",
+            ErrorCode::OrderNotFound => "The order does not exist",
+            ErrorCode::ItemNotFound => "The menu item does not exist",
+            ErrorCode::TableNotFound => "The table does not exist",
+            ErrorCode::WebhookNotFound => "The webhook does not exist",
+            ErrorCode::MethodNotAllowed => "The path does not support the method, the Allow header lists the ones it does",
+            ErrorCode::DuplicateOrder => "An order with the id exists with a different body, fields names the differences",
+            ErrorCode::TableClosed => "The table is closed and accepts no orders",
+            ErrorCode::InvalidTransition => "The order's current status does not allow the transition",
+            ErrorCode::ResyncRequired => "The changes after since are no longer available, sync from scratch",
+            ErrorCode::UnsupportedMediaType => "The Content-Type is not the one the route accepts",
+            ErrorCode::UnprocessableEntity => "The request was rejected before reaching the API, e.g. because of a malformed query",
+            ErrorCode::ValidationFailed => "An id or field is malformed, fields describes each violation",
+            ErrorCode::UnknownItem => "The ordered item is not on the menu",
+            ErrorCode::ItemUnavailable => "The ordered item is temporarily unavailable",
+            ErrorCode::UnknownTable => "The order's table is not registered",
+            ErrorCode::InvalidOrder => "Quantity, modifiers or notes are out of bounds",
+            ErrorCode::InvalidMenuItem => "A field of the menu item has an invalid value",
+            ErrorCode::InvalidTable => "A field of the table has an invalid value",
+            ErrorCode::InvalidWebhook => "A field of the webhook has an invalid value",
+            ErrorCode::InternalError => "An unexpected failure, logged with the correlation id",
+        }
+    }
+
+    // The code of an error Rocket responds with on its own, before or instead of a handler.
+    pub fn of_status(status: Status) -> Option<ErrorCode> {
+        match status.code {
+            400 => Some(ErrorCode::BadRequest),
+            404 => Some(ErrorCode::NotFound),
+            405 => Some(ErrorCode::MethodNotAllowed),
+            415 => Some(ErrorCode::UnsupportedMediaType),
+            422 => Some(ErrorCode::UnprocessableEntity),
+            500 => Some(ErrorCode::InternalError),
+            _ => None,
+        }
+    }
+}
+
+// ErrorCodeInfo is an entry of the catalog served at GET /errors.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct ErrorCodeInfo {
+    pub code: String,
+    pub status: u16,
+    pub description: String,
+}
+
+impl From<ErrorCode> for ErrorCodeInfo {
+    fn from(code: ErrorCode) -> ErrorCodeInfo {
+        ErrorCodeInfo {
+            code: code.as_str().to_string(),
+            status: code.status().code,
+            description: code.description().to_string(),
+        }
+    }
+}
+
+pub fn catalog() -> Vec<ErrorCodeInfo> {
+    ErrorCode::ALL.into_iter().map(ErrorCodeInfo::from).collect()
+}
+
+// ApiError is an error response of the API. Handlers return it and let the From impls below
+// map the services' errors, so that every error gets the same status and code everywhere.
+#[derive(Debug)]
+pub enum ApiError {
+    // A client error with its message and, for some codes, the fields at fault.
+    Client { code: ErrorCode, message: String, fields: Vec<FieldError> },
+    // No route has the request's method for its path. Holds the methods the path supports.
+    MethodNotAllowed(Vec<String>),
+    // An unexpected failure. The cause is logged, but not sent to the client.
+    Internal(String),
+    // A status Rocket responded with on its own.
+    Status(Status),
+}
+
+impl ApiError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> ApiError {
+        ApiError::Client { code, message: message.into(), fields: Vec::new() }
+    }
+
+    pub fn invalid_body(e: JsonError<'_>) -> ApiError {
+        error!("Failed to parse JSON body: {}", e);
+        ApiError::new(ErrorCode::InvalidBody, "Invalid request body")
+    }
+
+    pub fn status(&self) -> Status {
+        match self {
+            ApiError::Client { code, .. } => code.status(),
+            ApiError::MethodNotAllowed(_) => Status::MethodNotAllowed,
+            ApiError::Internal(_) => Status::InternalServerError,
+            ApiError::Status(status) => *status,
+        }
+    }
+
+    // Statuses without a code of their own get one derived from the reason phrase,
+    // e.g. IM_A_TEAPOT.
+    pub fn code(&self) -> String {
+        match self {
+            ApiError::Client { code, .. } => code.as_str().to_string(),
+            ApiError::MethodNotAllowed(_) => ErrorCode::MethodNotAllowed.as_str().to_string(),
+            ApiError::Internal(_) => ErrorCode::InternalError.as_str().to_string(),
+            ApiError::Status(status) => match ErrorCode::of_status(*status) {
+                Some(code) => code.as_str().to_string(),
+                None => status.reason().unwrap_or("Error")
+                    .chars()
+                    .filter(|c| c.is_ascii_alphanumeric() || *c == ' ')
+                    .collect::<String>()
+                    .to_uppercase()
+                    .replace(' ', "_"),
+            },
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            ApiError::Client { message, .. } => message.clone(),
+            ApiError::MethodNotAllowed(_) => "Method not allowed".to_string(),
+            ApiError::Internal(_) => "Internal server error".to_string(),
+            ApiError::Status(status) if status.code == 500 => "Internal server error".to_string(),
+            ApiError::Status(status) => status.reason().unwrap_or("Error").to_string(),
+        }
+    }
+}
+
+impl From<Vec<FieldError>> for ApiError {
+    fn from(fields: Vec<FieldError>) -> ApiError {
+        ApiError::Client {
+            code: ErrorCode::ValidationFailed,
+            message: "Request failed validation".to_string(),
+            fields,
+        }
+    }
+}
+
+impl From<OrderServiceError> for ApiError {
+    fn from(e: OrderServiceError) -> ApiError {
+        match e {
+            OrderServiceError::DuplicateOrder { ref fields, .. } => ApiError::Client {
+                code: ErrorCode::DuplicateOrder,
+                message: e.to_string(),
+                fields: fields
+                    .iter()
+                    .map(|field| FieldError::new(field, "CONFLICT", "differs from the stored order".to_string()))
+                    .collect(),
+            },
+            OrderServiceError::OrderNotFound(_) => ApiError::new(ErrorCode::OrderNotFound, "Order not found"),
+            OrderServiceError::InvalidOrder(msg) => ApiError::new(ErrorCode::InvalidOrder, msg),
+            OrderServiceError::InvalidTransition { .. } => ApiError::new(ErrorCode::InvalidTransition, e.to_string()),
+            OrderServiceError::ChangesCompacted(_) => ApiError::new(ErrorCode::ResyncRequired, e.to_string()),
+            OrderServiceError::ConditionFailed(_) => ApiError::new(ErrorCode::Forbidden, e.to_string()),
+            OrderServiceError::AuditFailed(_)
+            | OrderServiceError::MutexPoisoned(_)
+            | OrderServiceError::Storage(_)
+            | OrderServiceError::TaskFailed(_) => ApiError::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<AuthError> for ApiError {
+    fn from(e: AuthError) -> ApiError {
+        match e {
+            AuthError::Forbidden(msg) => ApiError::new(ErrorCode::Forbidden, msg),
+            AuthError::Internal(_) => ApiError::Internal(e.to_string()),
+            _ => ApiError::new(ErrorCode::Unauthenticated, e.to_string()),
+        }
+    }
+}
+
+impl From<MenuServiceError> for ApiError {
+    fn from(e: MenuServiceError) -> ApiError {
+        match e {
+            MenuServiceError::ItemNotFound(_) => ApiError::new(ErrorCode::ItemNotFound, "Menu item not found"),
+            MenuServiceError::InvalidItem(msg) => ApiError::new(ErrorCode::InvalidMenuItem, msg),
+            MenuServiceError::MutexPoisoned(_)
+            | MenuServiceError::Storage(_)
+            | MenuServiceError::TaskFailed(_) => ApiError::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<TableServiceError> for ApiError {
+    fn from(e: TableServiceError) -> ApiError {
+        match e {
+            TableServiceError::TableNotFound(_) => ApiError::new(ErrorCode::TableNotFound, "Table not found"),
+            TableServiceError::InvalidTable(msg) => ApiError::new(ErrorCode::InvalidTable, msg),
+            TableServiceError::MutexPoisoned(_)
+            | TableServiceError::Storage(_)
+            | TableServiceError::TaskFailed(_) => ApiError::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<WebhookServiceError> for ApiError {
+    fn from(e: WebhookServiceError) -> ApiError {
+        match e {
+            WebhookServiceError::WebhookNotFound(_) => ApiError::new(ErrorCode::WebhookNotFound, "Webhook not found"),
+            WebhookServiceError::InvalidWebhook(msg) => ApiError::new(ErrorCode::InvalidWebhook, msg),
+            WebhookServiceError::MutexPoisoned(_) => ApiError::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<AuditLogError> for ApiError {
+    fn from(e: AuditLogError) -> ApiError {
+        ApiError::Internal(e.to_string())
+    }
+}
+
+struct CorrelationId(String);
+
+// The correlation id of the request: the client's, if it passed a well-formed one, otherwise
+// one generated on first use.
+pub fn correlation_id<'r>(req: &'r Request<'_>) -> &'r str {
+    &req.local_cache(|| {
+        let id = req.headers()
+            .get_one(CORRELATION_HEADER)
+            .filter(|id| Validator::new().id(CORRELATION_HEADER, id).finish().is_ok())
+            .map(str::to_string)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        CorrelationId(id)
+    }).0
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let correlation_id = correlation_id(req);
+        if let ApiError::Internal(cause) = &self {
+            error!("{} {}: {} (correlation id {})", req.method(), req.uri(), cause, correlation_id);
+        }
+
+        let status = self.status();
+        let body = ErrorResponse {
+            error: self.message(),
+            error_code: self.code(),
+            fields: match &self {
+                ApiError::Client { fields, .. } => fields.clone(),
+                _ => Vec::new(),
+            },
+            correlation_id: correlation_id.to_string(),
+        };
+
+        let mut response = (status, Json(body)).respond_to(req)?;
+        response.set_raw_header(CORRELATION_HEADER, correlation_id.to_string());
+        match self {
+            ApiError::MethodNotAllowed(methods) => {
+                response.set_raw_header("Allow", methods.join(", "));
+            }
+            ApiError::Client { code: ErrorCode::Unauthenticated, .. } => {
+                response.set_raw_header("WWW-Authenticate", "Bearer");
+            }
+            _ => {}
+        }
+        Ok(response)
+    }
+}
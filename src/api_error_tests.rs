@@ -0,0 +1,89 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use rocket::http::Status;
+    use crate::api_error::{catalog, ApiError, ErrorCode};
+    use crate::menu_service::MenuServiceError;
+    use crate::order_service::{OrderServiceError, OrderStatus};
+    use crate::table_service::TableServiceError;
+    use crate::validation::FieldError;
+    use crate::webhook_service::WebhookServiceError;
+
+    fn status_and_code(error: impl Into<ApiError>) -> (u16, String) {
+        let error = error.into();
+        (error.status().code, error.code())
+    }
+
+    fn expected(status: u16, code: &str) -> (u16, String) {
+        (status, code.to_string())
+    }
+
+    #[test]
+    fn test_catalog_lists_every_code_once() {
+        let codes: HashSet<&str> = ErrorCode::ALL.iter().map(|code| code.as_str()).collect();
+        assert_eq!(codes.len(), ErrorCode::ALL.len());
+
+        for code in codes {
+            assert!(code.chars().all(|c| c.is_ascii_uppercase() || c == '_'), "{}", code);
+        }
+
+        let catalog = catalog();
+        assert_eq!(catalog.len(), ErrorCode::ALL.len());
+        assert!(catalog.windows(2).all(|pair| pair[0].status <= pair[1].status));
+        assert!(catalog.iter().all(|info| !info.description.is_empty()));
+    }
+
+    #[test]
+    fn test_order_service_errors() {
+        let duplicate = OrderServiceError::DuplicateOrder {
+            order_id: "order1".to_string(),
+            fields: vec!["quantity".to_string(), "notes".to_string()],
+        };
+        match ApiError::from(duplicate) {
+            ApiError::Client { code: ErrorCode::DuplicateOrder, message, fields } => {
+                assert!(message.contains("quantity, notes"));
+                assert_eq!(fields, vec![
+                    FieldError::new("quantity", "CONFLICT", "differs from the stored order".to_string()),
+                    FieldError::new("notes", "CONFLICT", "differs from the stored order".to_string()),
+                ]);
+            }
+            other => panic!("Unexpected error {:?}", other),
+        }
+
+        let transition = OrderServiceError::InvalidTransition {
+            order_id: "order1".to_string(),
+            from: OrderStatus::Served,
+            to: OrderStatus::Cooking,
+        };
+        assert_eq!(status_and_code(transition), expected(409, "INVALID_TRANSITION"));
+        assert_eq!(status_and_code(OrderServiceError::OrderNotFound("order1".to_string())), expected(404, "ORDER_NOT_FOUND"));
+        assert_eq!(status_and_code(OrderServiceError::InvalidOrder("quantity".to_string())), expected(422, "INVALID_ORDER"));
+        assert_eq!(status_and_code(OrderServiceError::ChangesCompacted(10)), expected(410, "RESYNC_REQUIRED"));
+        assert_eq!(status_and_code(OrderServiceError::MutexPoisoned("lock".to_string())), expected(500, "INTERNAL_ERROR"));
+        assert_eq!(status_and_code(OrderServiceError::Storage("disk".to_string())), expected(500, "INTERNAL_ERROR"));
+        assert_eq!(status_and_code(OrderServiceError::TaskFailed("join".to_string())), expected(500, "INTERNAL_ERROR"));
+    }
+
+    #[test]
+    fn test_other_service_errors() {
+        assert_eq!(status_and_code(MenuServiceError::ItemNotFound("burger".to_string())), expected(404, "ITEM_NOT_FOUND"));
+        assert_eq!(status_and_code(MenuServiceError::InvalidItem("name".to_string())), expected(422, "INVALID_MENU_ITEM"));
+        assert_eq!(status_and_code(TableServiceError::TableNotFound("1".to_string())), expected(404, "TABLE_NOT_FOUND"));
+        assert_eq!(status_and_code(TableServiceError::InvalidTable("capacity".to_string())), expected(422, "INVALID_TABLE"));
+        assert_eq!(status_and_code(WebhookServiceError::WebhookNotFound("pos".to_string())), expected(404, "WEBHOOK_NOT_FOUND"));
+        assert_eq!(status_and_code(WebhookServiceError::InvalidWebhook("url".to_string())), expected(422, "INVALID_WEBHOOK"));
+        assert_eq!(status_and_code(WebhookServiceError::MutexPoisoned("lock".to_string())), expected(500, "INTERNAL_ERROR"));
+
+        let violations = vec![FieldError::new("id", "REQUIRED", "must not be empty".to_string())];
+        assert_eq!(status_and_code(violations), expected(422, "VALIDATION_FAILED"));
+    }
+
+    #[test]
+    fn test_statuses_without_a_handler() {
+        assert_eq!(status_and_code(ApiError::Status(Status::BadRequest)), expected(400, "BAD_REQUEST"));
+        assert_eq!(status_and_code(ApiError::Status(Status::UnprocessableEntity)), expected(422, "UNPROCESSABLE_ENTITY"));
+        assert_eq!(status_and_code(ApiError::Status(Status::InternalServerError)), expected(500, "INTERNAL_ERROR"));
+        assert_eq!(status_and_code(ApiError::Status(Status::ImATeapot)), expected(418, "IM_A_TEAPOT"));
+        assert_eq!(status_and_code(ApiError::MethodNotAllowed(vec!["GET".to_string()])), expected(405, "METHOD_NOT_ALLOWED"));
+    }
+}
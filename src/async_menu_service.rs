@@ -0,0 +1,47 @@
+use std::sync::Arc;
+use crate::menu_service::{MenuItem, MenuService, MenuServiceError};
+
+// AsyncMenuService is the async facade of a MenuService, the same way AsyncOrderService is the
+// one of an OrderService: calls of a blocking implementation are moved to tokio's blocking
+// thread pool, calls of one that never blocks are made directly.
+#[derive(Clone)]
+pub struct AsyncMenuService {
+    inner: Arc<dyn MenuService>,
+}
+
+impl AsyncMenuService {
+    pub fn new(inner: Arc<dyn MenuService>) -> AsyncMenuService {
+        AsyncMenuService { inner }
+    }
+
+    pub async fn put_item(&self, item: MenuItem) -> Result<MenuItem, MenuServiceError> {
+        self.run(move |service| service.put_item(item)).await
+    }
+
+    pub async fn get_item(&self, item_id: String) -> Result<MenuItem, MenuServiceError> {
+        self.run(move |service| service.get_item(&item_id)).await
+    }
+
+    pub async fn get_items(&self, category: Option<String>) -> Result<Vec<MenuItem>, MenuServiceError> {
+        self.run(move |service| service.get_items(category)).await
+    }
+
+    pub async fn delete_item(&self, item_id: String) -> Result<MenuItem, MenuServiceError> {
+        self.run(move |service| service.delete_item(item_id)).await
+    }
+
+    async fn run<T, F>(&self, f: F) -> Result<T, MenuServiceError>
+    where
+        F: FnOnce(&dyn MenuService) -> Result<T, MenuServiceError> + Send + 'static,
+        T: Send + 'static,
+    {
+        if !self.inner.is_blocking() {
+            return f(self.inner.as_ref());
+        }
+
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || f(inner.as_ref()))
+            .await
+            .map_err(|e| MenuServiceError::TaskFailed(e.to_string()))?
+    }
+}
@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use uuid::Uuid;
+    use crate::async_menu_service::AsyncMenuService;
+    use crate::menu_service::{new_in_memory, new_in_memory_with_journal, MenuItem, MenuService, MenuServiceError};
+    use crate::sqlite_menu_service::new_sqlite;
+
+    fn menu_item(item_id: &str, category: &str) -> MenuItem {
+        MenuItem {
+            item_id: item_id.to_string(),
+            name: item_id.to_string(),
+            price_cents: 1250,
+            category: category.to_string(),
+            preparation_time: 10,
+            available: true,
+        }
+    }
+
+    #[test]
+    fn test_only_menus_on_disk_are_blocking() {
+        let dir = std::env::temp_dir().join(format!("restaurant_api_menu_{}", Uuid::new_v4()));
+        assert!(!new_in_memory().is_blocking());
+        assert!(new_in_memory_with_journal(&dir).unwrap().is_blocking());
+        assert!(new_sqlite(":memory:").unwrap().is_blocking());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_journal_and_sqlite_backends() {
+        let dir = std::env::temp_dir().join(format!("restaurant_api_menu_{}", Uuid::new_v4()));
+        let backends: Vec<Arc<dyn MenuService>> = vec![
+            Arc::new(new_in_memory()),
+            Arc::new(new_in_memory_with_journal(&dir).unwrap()),
+            Arc::new(new_sqlite(":memory:").unwrap()),
+        ];
+
+        for backend in backends {
+            let service = AsyncMenuService::new(backend);
+            service.put_item(menu_item("burger", "mains")).await.unwrap();
+            service.put_item(menu_item("soup", "starters")).await.unwrap();
+
+            assert_eq!(service.get_item("burger".to_string()).await.unwrap(), menu_item("burger", "mains"));
+            let starters = service.get_items(Some("starters".to_string())).await.unwrap();
+            assert_eq!(starters, vec![menu_item("soup", "starters")]);
+
+            assert_eq!(service.delete_item("soup".to_string()).await.unwrap(), menu_item("soup", "starters"));
+            let res = service.get_item("soup".to_string()).await;
+            assert!(matches!(res, Err(MenuServiceError::ItemNotFound(id)) if id == "soup"));
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+use crate::order_service::{
+    Audit, Changes, Order, OrderQuery, OrderResult, OrderService, OrderServiceError, OrderStatus, PutOutcome,
+};
+use crate::pagination::{OrderPage, PageRequest};
+
+// AsyncOrderService is the async facade the API handlers talk to. It wraps any OrderService
+// and makes sure a blocking implementation (e.g. one doing disk or database I/O) never
+// stalls Rocket's async workers: such calls are moved to tokio's blocking thread pool.
+// Implementations that never block, like a plain InMemoryOrderService, are called directly,
+// which avoids the overhead of a thread hop. A mutation is recorded in the same step as it is
+// made, so it is moved to the blocking thread pool if recording it may block as well.
+#[derive(Clone)]
+pub struct AsyncOrderService {
+    inner: Arc<dyn OrderService>,
+}
+
+impl AsyncOrderService {
+    pub fn new(inner: Arc<dyn OrderService>) -> AsyncOrderService {
+        AsyncOrderService { inner }
+    }
+
+    pub async fn put_order<A>(&self, id: String, order: Order, audit: A) -> Result<PutOutcome, OrderServiceError>
+    where
+        A: Audit + 'static,
+    {
+        self.run(audit.is_blocking(), move |service| service.put_order_audited(id, order, &audit)).await
+    }
+
+    pub async fn delete_order<A>(&self, order_id: String, audit: A) -> Result<OrderResult, OrderServiceError>
+    where
+        A: Audit + 'static,
+    {
+        self.delete_order_if(order_id, |_| true, audit).await
+    }
+
+    pub async fn delete_order_if<F, A>(&self, order_id: String, condition: F, audit: A) -> Result<OrderResult, OrderServiceError>
+    where
+        F: Fn(&OrderResult) -> bool + Send + 'static,
+        A: Audit + 'static,
+    {
+        self.run(audit.is_blocking(), move |service| service.delete_order_audited(order_id, &condition, &audit)).await
+    }
+
+    pub async fn update_status<A>(&self, order_id: String, status: OrderStatus, audit: A) -> Result<OrderResult, OrderServiceError>
+    where
+        A: Audit + 'static,
+    {
+        self.run(audit.is_blocking(), move |service| service.update_status_audited(order_id, status, &audit)).await
+    }
+
+    pub async fn get_order_by_id(&self, order_id: String) -> Result<OrderResult, OrderServiceError> {
+        self.run(false, move |service| service.get_order_by_id(order_id)).await
+    }
+
+    pub async fn get_orders_page(&self, query: OrderQuery, page: PageRequest) -> Result<OrderPage, OrderServiceError> {
+        self.run(false, move |service| service.get_orders_page(&query, &page)).await
+    }
+
+    pub async fn get_changes(&self, since: Option<u64>) -> Result<Changes, OrderServiceError> {
+        self.run(false, move |service| service.get_changes(since)).await
+    }
+
+    // Runs f on the blocking thread pool if the service or, as `blocking` tells, anything else
+    // f does may block.
+    async fn run<T, F>(&self, blocking: bool, f: F) -> Result<T, OrderServiceError>
+    where
+        F: FnOnce(&dyn OrderService) -> Result<T, OrderServiceError> + Send + 'static,
+        T: Send + 'static,
+    {
+        if !blocking && !self.inner.is_blocking() {
+            return f(self.inner.as_ref());
+        }
+
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || f(inner.as_ref()))
+            .await
+            .map_err(|e| OrderServiceError::TaskFailed(e.to_string()))?
+    }
+}
@@ -0,0 +1,135 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::thread::{self, ThreadId};
+    use crate::async_order_service::AsyncOrderService;
+    use crate::order_service::{
+        new_in_memory, Audit, Changes, Order, OrderQuery, OrderResult, OrderService, OrderServiceError, OrderStatus,
+        PutOutcome,
+    };
+    use crate::order_service_tests::Unaudited;
+    use crate::pagination::{OrderPage, PageRequest};
+    use crate::sqlite_order_service::new_sqlite;
+
+    // RecordingOrderService remembers on which thread it was last called.
+    struct RecordingOrderService {
+        blocking: bool,
+        called_on: Mutex<Option<ThreadId>>,
+    }
+
+    impl RecordingOrderService {
+        fn new(blocking: bool) -> Arc<RecordingOrderService> {
+            Arc::new(RecordingOrderService {
+                blocking,
+                called_on: Mutex::new(None),
+            })
+        }
+
+        fn record(&self) {
+            *self.called_on.lock().unwrap() = Some(thread::current().id());
+        }
+
+        fn called_on(&self) -> Option<ThreadId> {
+            *self.called_on.lock().unwrap()
+        }
+    }
+
+    impl OrderService for RecordingOrderService {
+        fn put_order_audited(&self, id: String, order: Order, _: &dyn Audit) -> Result<PutOutcome, OrderServiceError> {
+            self.record();
+            Ok(PutOutcome::Created(OrderResult::placed(id, order, 10)))
+        }
+
+        fn delete_order_audited(
+            &self,
+            order_id: String,
+            _: &dyn Fn(&OrderResult) -> bool,
+            _: &dyn Audit,
+        ) -> Result<OrderResult, OrderServiceError> {
+            self.record();
+            Err(OrderServiceError::OrderNotFound(order_id))
+        }
+
+        fn update_status_audited(&self, order_id: String, _: OrderStatus, _: &dyn Audit) -> Result<OrderResult, OrderServiceError> {
+            self.record();
+            Err(OrderServiceError::OrderNotFound(order_id))
+        }
+
+        fn get_order_by_id(&self, order_id: String) -> Result<OrderResult, OrderServiceError> {
+            self.record();
+            Err(OrderServiceError::OrderNotFound(order_id))
+        }
+
+        fn get_orders_page(&self, _: &OrderQuery, _: &PageRequest) -> Result<OrderPage, OrderServiceError> {
+            self.record();
+            Ok(OrderPage { orders: Vec::new(), next: None })
+        }
+
+        fn get_changes(&self, _: Option<u64>) -> Result<Changes, OrderServiceError> {
+            self.record();
+            Ok(Changes { changes: Vec::new(), last_seq: 0 })
+        }
+
+        fn is_blocking(&self) -> bool {
+            self.blocking
+        }
+    }
+
+    fn order() -> Order {
+        Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blocking_service_runs_on_blocking_pool() {
+        let recording = RecordingOrderService::new(true);
+        let service = AsyncOrderService::new(recording.clone());
+
+        service.put_order("order1".to_string(), order(), Unaudited).await.unwrap();
+        assert_ne!(recording.called_on(), Some(thread::current().id()));
+    }
+
+    #[tokio::test]
+    async fn test_non_blocking_service_runs_inline() {
+        let recording = RecordingOrderService::new(false);
+        let service = AsyncOrderService::new(recording.clone());
+
+        service.get_orders_page(OrderQuery::default(), PageRequest::all()).await.unwrap();
+        assert_eq!(recording.called_on(), Some(thread::current().id()));
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_passed_through() {
+        let service = AsyncOrderService::new(RecordingOrderService::new(true));
+
+        let res = service.delete_order("order1".to_string(), Unaudited).await;
+        assert!(matches!(res, Err(OrderServiceError::OrderNotFound(id)) if id == "order1"));
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_and_sqlite_backends() {
+        let backends: Vec<Arc<dyn OrderService>> = vec![
+            Arc::new(new_in_memory()),
+            Arc::new(new_sqlite(":memory:").unwrap()),
+        ];
+
+        for backend in backends {
+            let service = AsyncOrderService::new(backend);
+            service.put_order("order1".to_string(), order(), Unaudited).await.unwrap();
+
+            let page = service.get_orders_page(OrderQuery {
+                table_ids: vec!["table1".to_string()],
+                ..Default::default()
+            }, PageRequest::all())
+                .await
+                .unwrap();
+            assert_eq!(page.orders.len(), 1);
+
+            let deleted = service.delete_order("order1".to_string(), Unaudited).await.unwrap();
+            assert_eq!(deleted.order_id, "order1");
+        }
+    }
+}
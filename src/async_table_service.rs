@@ -0,0 +1,51 @@
+use std::sync::Arc;
+use crate::table_service::{Table, TableService, TableServiceError, TableStatus};
+
+// AsyncTableService is the async facade of a TableService, the same way AsyncOrderService is the
+// one of an OrderService: calls of a blocking implementation are moved to tokio's blocking
+// thread pool, calls of one that never blocks are made directly.
+#[derive(Clone)]
+pub struct AsyncTableService {
+    inner: Arc<dyn TableService>,
+}
+
+impl AsyncTableService {
+    pub fn new(inner: Arc<dyn TableService>) -> AsyncTableService {
+        AsyncTableService { inner }
+    }
+
+    pub async fn put_table(&self, table: Table) -> Result<Table, TableServiceError> {
+        self.run(move |service| service.put_table(table)).await
+    }
+
+    pub async fn get_table(&self, table_id: String) -> Result<Table, TableServiceError> {
+        self.run(move |service| service.get_table(&table_id)).await
+    }
+
+    pub async fn get_tables(&self, section: Option<String>) -> Result<Vec<Table>, TableServiceError> {
+        self.run(move |service| service.get_tables(section)).await
+    }
+
+    pub async fn set_status(&self, table_id: String, status: TableStatus) -> Result<Table, TableServiceError> {
+        self.run(move |service| service.set_status(&table_id, status)).await
+    }
+
+    pub async fn delete_table(&self, table_id: String) -> Result<Table, TableServiceError> {
+        self.run(move |service| service.delete_table(table_id)).await
+    }
+
+    async fn run<T, F>(&self, f: F) -> Result<T, TableServiceError>
+    where
+        F: FnOnce(&dyn TableService) -> Result<T, TableServiceError> + Send + 'static,
+        T: Send + 'static,
+    {
+        if !self.inner.is_blocking() {
+            return f(self.inner.as_ref());
+        }
+
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || f(inner.as_ref()))
+            .await
+            .map_err(|e| TableServiceError::TaskFailed(e.to_string()))?
+    }
+}
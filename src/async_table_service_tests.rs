@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use uuid::Uuid;
+    use crate::async_table_service::AsyncTableService;
+    use crate::sqlite_table_service::new_sqlite;
+    use crate::table_service::{new_in_memory, new_in_memory_with_journal, Table, TableService, TableServiceError, TableStatus};
+
+    fn table(table_id: &str, section: &str) -> Table {
+        Table {
+            table_id: table_id.to_string(),
+            capacity: 4,
+            section: section.to_string(),
+            status: TableStatus::Open,
+        }
+    }
+
+    #[test]
+    fn test_only_tables_on_disk_are_blocking() {
+        let dir = std::env::temp_dir().join(format!("restaurant_api_tables_{}", Uuid::new_v4()));
+        assert!(!new_in_memory().is_blocking());
+        assert!(new_in_memory_with_journal(&dir).unwrap().is_blocking());
+        assert!(new_sqlite(":memory:").unwrap().is_blocking());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_journal_and_sqlite_backends() {
+        let dir = std::env::temp_dir().join(format!("restaurant_api_tables_{}", Uuid::new_v4()));
+        let backends: Vec<Arc<dyn TableService>> = vec![
+            Arc::new(new_in_memory()),
+            Arc::new(new_in_memory_with_journal(&dir).unwrap()),
+            Arc::new(new_sqlite(":memory:").unwrap()),
+        ];
+
+        for backend in backends {
+            let service = AsyncTableService::new(backend);
+            service.put_table(table("1", "main")).await.unwrap();
+            service.put_table(table("2", "terrace")).await.unwrap();
+
+            assert_eq!(service.get_table("1".to_string()).await.unwrap(), table("1", "main"));
+            let terrace = service.get_tables(Some("terrace".to_string())).await.unwrap();
+            assert_eq!(terrace, vec![table("2", "terrace")]);
+
+            let closed = service.set_status("1".to_string(), TableStatus::Closed).await.unwrap();
+            assert_eq!(closed.status, TableStatus::Closed);
+
+            assert_eq!(service.delete_table("1".to_string()).await.unwrap(), closed);
+            let res = service.get_table("1".to_string()).await;
+            assert!(matches!(res, Err(TableServiceError::TableNotFound(id)) if id == "1"));
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
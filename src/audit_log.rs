@@ -0,0 +1,312 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use chrono::{DateTime, Utc};
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::serde::{Deserialize, Serialize};
+use rocket::serde::json::serde_json;
+use utoipa::ToSchema;
+use crate::api_error;
+use crate::auth::Claims;
+use crate::journal::LineLog;
+use crate::order_service::{Audit, OrderResult, OrderServiceError};
+use crate::policy::Role;
+use crate::validation::{FieldError, Validator};
+
+// Staff may say why they made a change, e.g. why an order was cancelled, in this header.
+pub const REASON_HEADER: &str = "X-Audit-Reason";
+pub const MAX_REASON_LEN: usize = 200;
+
+#[derive(Serialize, Clone, Copy, Deserialize, Debug, PartialEq, Eq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub enum AuditOperation {
+    #[serde(rename = "create_order")]
+    Create,
+    #[serde(rename = "delete_order")]
+    Delete,
+    #[serde(rename = "start_order")]
+    Start,
+    #[serde(rename = "ready_order")]
+    Ready,
+    #[serde(rename = "serve_order")]
+    Serve,
+    #[serde(rename = "cancel_order")]
+    Cancel,
+}
+
+impl AuditOperation {
+    pub const ALL: [AuditOperation; 6] = [
+        AuditOperation::Create,
+        AuditOperation::Delete,
+        AuditOperation::Start,
+        AuditOperation::Ready,
+        AuditOperation::Serve,
+        AuditOperation::Cancel,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditOperation::Create => "create_order",
+            AuditOperation::Delete => "delete_order",
+            AuditOperation::Start => "start_order",
+            AuditOperation::Ready => "ready_order",
+            AuditOperation::Serve => "serve_order",
+            AuditOperation::Cancel => "cancel_order",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<AuditOperation> {
+        AuditOperation::ALL.into_iter().find(|operation| operation.as_str() == s)
+    }
+}
+
+// AuditEntry records a single mutation of an order: who made it, in which role and why, along
+// with the order before and after it. Staff authenticate per device, so the one who made the
+// change is the device the request was authenticated as, the subject of its bearer token.
+// before is None for a created order, after is None for a deleted one.
+// seq is assigned by the log and grows with every entry.
+#[derive(Serialize, Clone, Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub device: String,
+    pub role: Role,
+    pub operation: AuditOperation,
+    pub order_id: String,
+    pub before: Option<OrderResult>,
+    pub after: Option<OrderResult>,
+    pub reason: Option<String>,
+    pub correlation_id: String,
+}
+
+// AuditQuery narrows down the entries of get_entries. Every filter that is set must match.
+// since and until bound the entries' timestamps, since inclusive and until exclusive.
+#[derive(Clone, Debug, Default)]
+pub struct AuditQuery {
+    pub order_id: Option<String>,
+    pub device: Option<String>,
+    pub operation: Option<AuditOperation>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl AuditQuery {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.order_id.as_ref().is_none_or(|id| &entry.order_id == id)
+            && self.device.as_ref().is_none_or(|device| &entry.device == device)
+            && self.operation.is_none_or(|operation| entry.operation == operation)
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp < until)
+    }
+}
+
+#[derive(Debug)]
+pub enum AuditLogError {
+    Storage(String),
+    MutexPoisoned(String),
+}
+
+impl fmt::Display for AuditLogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditLogError::Storage(msg) => write!(f, "Storage error: {}", msg),
+            AuditLogError::MutexPoisoned(msg) => write!(f, "Mutex poisoned: {}", msg),
+        }
+    }
+}
+
+impl Error for AuditLogError {}
+
+// AuditLog is the append-only trail of order mutations. Entries can be recorded and queried,
+// but never changed or removed. get_entries returns them in the order they were recorded in.
+pub trait AuditLog: Sync + Send {
+    fn record(&self, entry: AuditEntry) -> Result<AuditEntry, AuditLogError>;
+    fn get_entries(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>, AuditLogError>;
+
+    // Whether calls may block the current thread, see OrderService::is_blocking.
+    fn is_blocking(&self) -> bool {
+        true
+    }
+}
+
+struct Entries {
+    entries: Vec<AuditEntry>,
+    // The JSON Lines file entries are appended to, if any.
+    file: Option<LineLog>,
+}
+
+// InMemoryAuditLog keeps the entries in a Vec wrapped in RwLock for thread safety. Opened with
+// a file, it also appends every entry to it as a JSON line and reads them back on startup, so
+// that the trail survives a restart. An entry that fails to be written is cut off the file
+// again, see LineLog.
+pub struct InMemoryAuditLog {
+    entries: RwLock<Entries>,
+}
+
+pub fn new_in_memory() -> InMemoryAuditLog {
+    InMemoryAuditLog {
+        entries: RwLock::new(Entries { entries: Vec::new(), file: None }),
+    }
+}
+
+// Opens the audit log kept in the file at `path`, creating it if needed. A last line left
+// incomplete by a crash mid-write was never acknowledged and is dropped.
+pub fn open(path: impl AsRef<Path>) -> Result<InMemoryAuditLog, AuditLogError> {
+    let path = path.as_ref();
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(io_error(e)),
+    };
+
+    let complete = contents.rfind('\n').map_or(0, |i| i + 1);
+    let entries = contents[..complete]
+        .lines()
+        .map(|line| serde_json::from_str::<AuditEntry>(line).map_err(json_error))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(io_error)?;
+    if complete < contents.len() {
+        warn!("Dropping incomplete last audit log entry");
+        file.set_len(complete as u64).map_err(io_error)?;
+    }
+
+    Ok(InMemoryAuditLog {
+        entries: RwLock::new(Entries { entries, file: Some(LineLog::new(file, complete as u64)) }),
+    })
+}
+
+impl AuditLog for InMemoryAuditLog {
+    fn record(&self, mut entry: AuditEntry) -> Result<AuditEntry, AuditLogError> {
+        let mut log = self.entries.write()
+            .map_err(|_| AuditLogError::MutexPoisoned("Failed to obtain write mutex".into()))?;
+
+        entry.seq = log.entries.last().map_or(1, |last| last.seq + 1);
+        if let Some(file) = &mut log.file {
+            let mut line = serde_json::to_vec(&entry).map_err(json_error)?;
+            line.push(b'\n');
+            file.append(&line).map_err(io_error)?;
+        }
+        log.entries.push(entry.clone());
+
+        Ok(entry)
+    }
+
+    fn get_entries(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>, AuditLogError> {
+        let log = self.entries.read()
+            .map_err(|_| AuditLogError::MutexPoisoned("Failed to obtain read mutex".into()))?;
+
+        Ok(log.entries
+            .iter()
+            .filter(|entry| query.matches(entry))
+            .cloned()
+            .collect())
+    }
+
+    // Only a log kept in a file writes to disk.
+    fn is_blocking(&self) -> bool {
+        self.entries.read().map_or(true, |log| log.file.is_some())
+    }
+}
+
+fn io_error(e: std::io::Error) -> AuditLogError {
+    AuditLogError::Storage(format!("audit log I/O failed: {}", e))
+}
+
+fn json_error(e: serde_json::Error) -> AuditLogError {
+    AuditLogError::Storage(format!("invalid audit log data: {}", e))
+}
+
+// Auditor is a request guard that records the mutations of a request in the audit log, along
+// with the reason given in the X-Audit-Reason header and the request's correlation id.
+pub struct Auditor {
+    log: Arc<dyn AuditLog>,
+    reason: Option<String>,
+    correlation_id: String,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Auditor {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, ()> {
+        let Some(log) = req.rocket().state::<Arc<dyn AuditLog>>() else {
+            error!("No audit log is managed");
+            return Outcome::Error((Status::InternalServerError, ()));
+        };
+        Outcome::Success(Auditor {
+            log: log.clone(),
+            reason: req.headers().get_one(REASON_HEADER).map(str::to_string),
+            correlation_id: api_error::correlation_id(req).to_string(),
+        })
+    }
+}
+
+impl Auditor {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut validator = Validator::new();
+        if let Some(reason) = &self.reason {
+            validator.text(REASON_HEADER, reason, MAX_REASON_LEN);
+        }
+        validator.finish()
+    }
+
+    // Returns the Audit that records a change the device makes to an order with the given
+    // operation. It is handed to the OrderService, which records the change in the same step as
+    // it makes it: a change that cannot be recorded is not made, and the request fails with
+    // INTERNAL_ERROR.
+    pub fn audit(&self, device: &Claims, operation: AuditOperation) -> OrderAudit {
+        OrderAudit {
+            log: self.log.clone(),
+            device: device.sub.clone(),
+            role: device.role,
+            operation,
+            reason: self.reason.clone(),
+            correlation_id: self.correlation_id.clone(),
+        }
+    }
+}
+
+pub struct OrderAudit {
+    log: Arc<dyn AuditLog>,
+    device: String,
+    role: Role,
+    operation: AuditOperation,
+    reason: Option<String>,
+    correlation_id: String,
+}
+
+impl Audit for OrderAudit {
+    fn record(&self, before: Option<&OrderResult>, after: Option<&OrderResult>) -> Result<(), OrderServiceError> {
+        let order_id = after.or(before).map(|order| order.order_id.clone()).unwrap_or_default();
+        let entry = AuditEntry {
+            seq: 0,
+            timestamp: Utc::now(),
+            device: self.device.clone(),
+            role: self.role,
+            operation: self.operation,
+            order_id: order_id.clone(),
+            before: before.cloned(),
+            after: after.cloned(),
+            reason: self.reason.clone(),
+            correlation_id: self.correlation_id.clone(),
+        };
+
+        self.log.record(entry).map(|_| ()).map_err(|e| {
+            OrderServiceError::AuditFailed(format!("Failed to record {} of order {}: {}", self.operation.as_str(), order_id, e))
+        })
+    }
+
+    fn is_blocking(&self) -> bool {
+        self.log.is_blocking()
+    }
+}
@@ -0,0 +1,126 @@
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+    use std::path::PathBuf;
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+    use crate::audit_log::{new_in_memory, open, AuditEntry, AuditLog, AuditOperation, AuditQuery};
+    use crate::order_service::{Order, OrderResult};
+    use crate::policy::Role;
+
+    fn temp_audit_file() -> PathBuf {
+        std::env::temp_dir().join(format!("restaurant_api_audit_{}.jsonl", Uuid::new_v4()))
+    }
+
+    fn order(order_id: &str) -> OrderResult {
+        OrderResult::placed(order_id.to_string(), Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Order::default()
+        }, 10)
+    }
+
+    fn entry(device: &str, operation: AuditOperation, order_id: &str) -> AuditEntry {
+        AuditEntry {
+            seq: 0,
+            timestamp: Utc::now(),
+            device: device.to_string(),
+            role: Role::Waiter,
+            operation,
+            order_id: order_id.to_string(),
+            before: None,
+            after: Some(order(order_id)),
+            reason: None,
+            correlation_id: Uuid::new_v4().to_string(),
+        }
+    }
+
+    fn seqs(entries: Vec<AuditEntry>) -> Vec<u64> {
+        entries.into_iter().map(|entry| entry.seq).collect()
+    }
+
+    #[test]
+    fn test_record_assigns_sequence_numbers() {
+        let log = new_in_memory();
+        assert_eq!(log.record(entry("watch-17", AuditOperation::Create, "order1")).unwrap().seq, 1);
+        assert_eq!(log.record(entry("watch-17", AuditOperation::Create, "order2")).unwrap().seq, 2);
+
+        let entries = log.get_entries(&AuditQuery::default()).unwrap();
+        assert_eq!(seqs(entries.clone()), vec![1, 2]);
+        assert_eq!(entries[1].order_id, "order2");
+        assert_eq!(entries[1].after.as_ref().unwrap().order_id, "order2");
+    }
+
+    #[test]
+    fn test_get_entries_filters() {
+        let log = new_in_memory();
+        log.record(entry("watch-17", AuditOperation::Create, "order1")).unwrap();
+        log.record(entry("watch-18", AuditOperation::Create, "order2")).unwrap();
+        log.record(entry("watch-18", AuditOperation::Delete, "order1")).unwrap();
+
+        let by_order = AuditQuery { order_id: Some("order1".to_string()), ..Default::default() };
+        assert_eq!(seqs(log.get_entries(&by_order).unwrap()), vec![1, 3]);
+
+        let by_device = AuditQuery { device: Some("watch-18".to_string()), ..Default::default() };
+        assert_eq!(seqs(log.get_entries(&by_device).unwrap()), vec![2, 3]);
+
+        let deletions = AuditQuery { operation: Some(AuditOperation::Delete), ..by_device };
+        assert_eq!(seqs(log.get_entries(&deletions).unwrap()), vec![3]);
+
+        let future = AuditQuery { since: Some(Utc::now() + Duration::hours(1)), ..Default::default() };
+        assert!(log.get_entries(&future).unwrap().is_empty());
+        let past = AuditQuery { until: Some(Utc::now() + Duration::hours(1)), ..Default::default() };
+        assert_eq!(seqs(log.get_entries(&past).unwrap()), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_parse_operation() {
+        for operation in AuditOperation::ALL {
+            assert_eq!(AuditOperation::parse(operation.as_str()), Some(operation));
+        }
+        assert_eq!(AuditOperation::parse("update_order"), None);
+    }
+
+    #[test]
+    fn test_entries_are_recovered_from_the_file() {
+        let path = temp_audit_file();
+        {
+            let log = open(&path).unwrap();
+            log.record(entry("watch-17", AuditOperation::Create, "order1")).unwrap();
+            log.record(entry("watch-17", AuditOperation::Delete, "order1")).unwrap();
+        }
+
+        let log = open(&path).unwrap();
+        let entries = log.get_entries(&AuditQuery::default()).unwrap();
+        assert_eq!(seqs(entries.clone()), vec![1, 2]);
+        assert_eq!(entries[1].operation, AuditOperation::Delete);
+
+        // Sequence numbers carry on where they left off.
+        assert_eq!(log.record(entry("watch-17", AuditOperation::Create, "order2")).unwrap().seq, 3);
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_incomplete_last_line_is_dropped() {
+        let path = temp_audit_file();
+        {
+            let log = open(&path).unwrap();
+            log.record(entry("watch-17", AuditOperation::Create, "order1")).unwrap();
+        }
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"seq\": 2, \"timest").unwrap();
+        drop(file);
+
+        let log = open(&path).unwrap();
+        assert_eq!(seqs(log.get_entries(&AuditQuery::default()).unwrap()), vec![1]);
+        log.record(entry("watch-17", AuditOperation::Delete, "order1")).unwrap();
+
+        let log = open(&path).unwrap();
+        assert_eq!(seqs(log.get_entries(&AuditQuery::default()).unwrap()), vec![1, 2]);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::errors::ErrorKind;
+use rocket::http::Status;
+use rocket::outcome::try_outcome;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::serde::{Deserialize, Serialize};
+use rocket::State;
+use utoipa::ToSchema;
+use crate::policy::Role;
+
+// Claims of the bearer tokens issued to staff devices. sub is the device id, section the
+// waiter's section of the restaurant, if any.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "rocket::serde")]
+pub struct Claims {
+    pub sub: String,
+    pub role: Role,
+    #[serde(default)]
+    pub section: Option<String>,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct IssuedToken {
+    pub token: String,
+    // Always "Bearer".
+    pub token_type: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub enum AuthError {
+    MissingToken,
+    InvalidToken(String),
+    ExpiredToken,
+    InvalidEnrollmentKey,
+    Forbidden(String),
+    Internal(String),
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::MissingToken => write!(f, "Missing bearer token"),
+            AuthError::InvalidToken(msg) => write!(f, "Invalid bearer token: {}", msg),
+            AuthError::ExpiredToken => write!(f, "Bearer token has expired"),
+            AuthError::InvalidEnrollmentKey => write!(f, "Invalid enrollment key"),
+            AuthError::Forbidden(msg) => write!(f, "{}", msg),
+            AuthError::Internal(msg) => write!(f, "Authentication failed: {}", msg),
+        }
+    }
+}
+
+impl Error for AuthError {}
+
+// HS256 keys shorter than the hash output weaken the signature, so shorter secrets are refused.
+pub const MIN_SECRET_LEN: usize = 32;
+
+// Auth issues and verifies the bearer tokens of staff devices, JWTs signed with HS256.
+// A device obtains its token by enrolling with the enrollment key of its role, which is
+// never put in a token.
+pub struct Auth {
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    validation: Validation,
+    enrollment_keys: HashMap<Role, String>,
+    ttl: Duration,
+}
+
+impl Auth {
+    pub fn new(secret: &[u8], enrollment_keys: HashMap<Role, String>, ttl: Duration) -> Auth {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_required_spec_claims(&["exp", "sub"]);
+        Auth {
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+            validation,
+            enrollment_keys,
+            ttl,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn enrollment_key(&self, role: Role) -> &str {
+        &self.enrollment_keys[&role]
+    }
+
+    pub fn enroll(
+        &self,
+        device_id: &str,
+        role: Role,
+        section: Option<String>,
+        enrollment_key: &str,
+    ) -> Result<IssuedToken, AuthError> {
+        let valid = self.enrollment_keys
+            .get(&role)
+            .is_some_and(|key| constant_time_eq(enrollment_key.as_bytes(), key.as_bytes()));
+        if !valid {
+            return Err(AuthError::InvalidEnrollmentKey);
+        }
+        self.issue(device_id, role, section)
+    }
+
+    pub fn issue(&self, device_id: &str, role: Role, section: Option<String>) -> Result<IssuedToken, AuthError> {
+        let now = Utc::now();
+        let expires_at = now + chrono::Duration::from_std(self.ttl)
+            .map_err(|e| AuthError::Internal(e.to_string()))?;
+        let claims = Claims {
+            sub: device_id.to_string(),
+            role,
+            section,
+            iat: now.timestamp(),
+            exp: expires_at.timestamp(),
+        };
+
+        let token = jsonwebtoken::encode(&Header::new(Algorithm::HS256), &claims, &self.encoding_key)
+            .map_err(|e| AuthError::Internal(e.to_string()))?;
+        Ok(IssuedToken { token, token_type: "Bearer".to_string(), expires_at })
+    }
+
+    pub fn verify(&self, token: &str) -> Result<Claims, AuthError> {
+        jsonwebtoken::decode::<Claims>(token, &self.decoding_key, &self.validation)
+            .map(|data| data.claims)
+            .map_err(|e| match e.kind() {
+                ErrorKind::ExpiredSignature => AuthError::ExpiredToken,
+                _ => AuthError::InvalidToken(e.to_string()),
+            })
+    }
+}
+
+// Compares the keys without revealing how long a prefix of them matches.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// Why the request failed authentication or authorization, for the 401 and 403 catchers.
+struct AuthFailure(String);
+
+pub fn set_failure(req: &Request<'_>, e: &AuthError) {
+    req.local_cache(|| AuthFailure(e.to_string()));
+}
+
+pub fn failure<'r>(req: &'r Request<'_>) -> &'r str {
+    &req.local_cache(|| AuthFailure(AuthError::MissingToken.to_string())).0
+}
+
+// Authenticated is a request guard that requires an "Authorization: Bearer <token>" header with
+// a valid token. Requests without one fail with 401.
+pub struct Authenticated {
+    pub claims: Claims,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Authenticated {
+    type Error = AuthError;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, AuthError> {
+        let auth = try_outcome!(req.guard::<&State<Auth>>().await
+            .map_error(|(status, _)| (status, AuthError::Internal("Auth is not managed".to_string()))));
+
+        let result = match req.headers().get_one("Authorization") {
+            None => Err(AuthError::MissingToken),
+            // The scheme is case-insensitive.
+            Some(header) => match header.split_once(' ') {
+                Some((scheme, token)) if scheme.eq_ignore_ascii_case("Bearer") => auth.verify(token.trim()),
+                _ => Err(AuthError::InvalidToken("Authorization must use the Bearer scheme".to_string())),
+            },
+        };
+
+        match result {
+            Ok(claims) => Outcome::Success(Authenticated { claims }),
+            Err(e) => {
+                set_failure(req, &e);
+                Outcome::Error((Status::Unauthorized, e))
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use chrono::Utc;
+    use jsonwebtoken::{Algorithm, EncodingKey, Header};
+    use crate::auth::{Auth, AuthError, Claims};
+    use crate::policy::Role;
+
+    const SECRET: &[u8] = b"test secret";
+
+    fn new_auth() -> Auth {
+        let enrollment_keys = HashMap::from([
+            (Role::Waiter, "enroll-me".to_string()),
+            (Role::Manager, "enroll-the-boss".to_string()),
+        ]);
+        Auth::new(SECRET, enrollment_keys, Duration::from_secs(60 * 60))
+    }
+
+    fn sign(claims: &Claims, algorithm: Algorithm, secret: &[u8]) -> String {
+        jsonwebtoken::encode(&Header::new(algorithm), claims, &EncodingKey::from_secret(secret)).unwrap()
+    }
+
+    #[test]
+    fn test_issue_and_verify() {
+        let auth = new_auth();
+        let issued = auth.issue("watch-17", Role::Waiter, Some("terrace".to_string())).unwrap();
+        assert_eq!(issued.token_type, "Bearer");
+        assert!(issued.expires_at > Utc::now() + chrono::Duration::minutes(59));
+
+        let claims = auth.verify(&issued.token).unwrap();
+        assert_eq!(claims.sub, "watch-17");
+        assert_eq!(claims.role, Role::Waiter);
+        assert_eq!(claims.section.as_deref(), Some("terrace"));
+        assert_eq!(claims.exp, issued.expires_at.timestamp());
+    }
+
+    #[test]
+    fn test_enroll_checks_the_key() {
+        let auth = new_auth();
+        assert!(auth.enroll("watch-17", Role::Waiter, None, "enroll-me").is_ok());
+        assert!(matches!(auth.enroll("watch-17", Role::Waiter, None, "enroll-you"), Err(AuthError::InvalidEnrollmentKey)));
+        assert!(matches!(auth.enroll("watch-17", Role::Waiter, None, "enroll-me-too"), Err(AuthError::InvalidEnrollmentKey)));
+        assert!(matches!(auth.enroll("watch-17", Role::Waiter, None, ""), Err(AuthError::InvalidEnrollmentKey)));
+    }
+
+    #[test]
+    fn test_enroll_checks_the_key_of_the_role() {
+        let auth = new_auth();
+        let issued = auth.enroll("tablet-1", Role::Manager, None, "enroll-the-boss").unwrap();
+        assert_eq!(auth.verify(&issued.token).unwrap().role, Role::Manager);
+
+        // A waiter's key does not make a manager, and roles without a key cannot enroll.
+        assert!(matches!(auth.enroll("watch-17", Role::Manager, None, "enroll-me"), Err(AuthError::InvalidEnrollmentKey)));
+        assert!(matches!(auth.enroll("pass-1", Role::Kitchen, None, "enroll-me"), Err(AuthError::InvalidEnrollmentKey)));
+    }
+
+    #[test]
+    fn test_rejects_foreign_and_tampered_tokens() {
+        let auth = new_auth();
+        let now = Utc::now().timestamp();
+        let claims = Claims { sub: "watch-17".to_string(), role: Role::Waiter, section: None, iat: now, exp: now + 60 };
+
+        let foreign = sign(&claims, Algorithm::HS256, b"another secret");
+        assert!(matches!(auth.verify(&foreign), Err(AuthError::InvalidToken(_))));
+
+        let other_algorithm = sign(&claims, Algorithm::HS512, SECRET);
+        assert!(matches!(auth.verify(&other_algorithm), Err(AuthError::InvalidToken(_))));
+
+        let token = auth.issue("watch-17", Role::Waiter, None).unwrap().token;
+        let mut parts: Vec<&str> = token.split('.').collect();
+        let forged = sign(&Claims { role: Role::Manager, ..claims }, Algorithm::HS256, b"forged");
+        parts[1] = forged.split('.').nth(1).unwrap();
+        assert!(matches!(auth.verify(&parts.join(".")), Err(AuthError::InvalidToken(_))));
+
+        assert!(matches!(auth.verify("not a token"), Err(AuthError::InvalidToken(_))));
+    }
+
+    #[test]
+    fn test_rejects_expired_tokens() {
+        let auth = new_auth();
+        let now = Utc::now().timestamp();
+        let claims = Claims { sub: "watch-17".to_string(), role: Role::Waiter, section: None, iat: now - 7200, exp: now - 3600 };
+        let token = sign(&claims, Algorithm::HS256, SECRET);
+        assert!(matches!(auth.verify(&token), Err(AuthError::ExpiredToken)));
+    }
+}
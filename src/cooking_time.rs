@@ -0,0 +1,101 @@
+use std::sync::Arc;
+use rand::Rng;
+use crate::menu_service::MenuService;
+use crate::order_service::{Doneness, Modifier, Order};
+
+// Preparation time assumed for items that are (no longer) on the menu.
+const DEFAULT_PREPARATION_TIME: i32 = 10;
+
+// Extra minutes for every portion after the first one.
+const EXTRA_PORTION_MINUTES: i32 = 2;
+
+// Extra minutes for every added or removed ingredient.
+const INGREDIENT_MODIFIER_MINUTES: i32 = 1;
+
+// CookingTimeEstimator decides the cooking time (in minutes) assigned to a newly accepted order,
+// in two steps. base_time is the time the order takes on its own. It may look things up, e.g.
+// on the menu, so it is called before the order store is locked. estimate then adds the time
+// the kitchen load takes. It is called with the store locked and must not block.
+// orders_in_progress is the number of orders the kitchen is currently working on,
+// not counting the new one.
+pub trait CookingTimeEstimator: Sync + Send {
+    fn base_time(&self, order: &Order) -> i32;
+    fn estimate(&self, base_time: i32, orders_in_progress: usize) -> i32;
+}
+
+// RandomEstimator assigns a random cooking time between 5 and 15 minutes, regardless of the
+// item and the kitchen load.
+pub struct RandomEstimator;
+
+impl CookingTimeEstimator for RandomEstimator {
+    fn base_time(&self, _: &Order) -> i32 {
+        rand::thread_rng().gen_range(5..16)
+    }
+
+    fn estimate(&self, base_time: i32, _: usize) -> i32 {
+        base_time
+    }
+}
+
+// KitchenLoadEstimator models the kitchen as working on `capacity` orders in parallel.
+// An order takes the item's base preparation time, plus the time needed for its extra portions
+// and modifiers, plus `minutes_per_batch` for every full batch of `capacity` orders ahead of it:
+//
+// cooking_time = preparation_time + customization_minutes(order)
+//     + (orders_in_progress / capacity) * minutes_per_batch
+pub struct KitchenLoadEstimator {
+    menu_service: Arc<dyn MenuService>,
+    capacity: usize,
+    minutes_per_batch: i32,
+}
+
+impl KitchenLoadEstimator {
+    pub fn new(menu_service: Arc<dyn MenuService>, capacity: usize, minutes_per_batch: i32) -> KitchenLoadEstimator {
+        KitchenLoadEstimator {
+            menu_service,
+            capacity: capacity.max(1),
+            minutes_per_batch,
+        }
+    }
+}
+
+impl CookingTimeEstimator for KitchenLoadEstimator {
+    fn base_time(&self, order: &Order) -> i32 {
+        let preparation_time = match self.menu_service.get_item(&order.item_id) {
+            Ok(item) => item.preparation_time,
+            Err(e) => {
+                warn!("Using default preparation time for item '{}': {}", order.item_id, e);
+                DEFAULT_PREPARATION_TIME
+            }
+        };
+        preparation_time.saturating_add(customization_minutes(order))
+    }
+
+    fn estimate(&self, base_time: i32, orders_in_progress: usize) -> i32 {
+        let batches_ahead = i32::try_from(orders_in_progress / self.capacity).unwrap_or(i32::MAX);
+        base_time.saturating_add(batches_ahead.saturating_mul(self.minutes_per_batch))
+    }
+}
+
+// Time the order takes on top of a single portion of the item as listed on the menu.
+fn customization_minutes(order: &Order) -> i32 {
+    let extra_portions = i32::try_from(order.quantity.saturating_sub(1)).unwrap_or(i32::MAX);
+    let modifiers: i32 = order.modifiers.iter()
+        .map(|modifier| match modifier {
+            Modifier::Add { .. } | Modifier::Remove { .. } => INGREDIENT_MODIFIER_MINUTES,
+            Modifier::Doneness { level } => doneness_minutes(*level),
+        })
+        .sum();
+
+    extra_portions.saturating_mul(EXTRA_PORTION_MINUTES).saturating_add(modifiers)
+}
+
+// The menu's preparation time assumes a medium result. Rarer is not faster, as the item
+// still has to rest.
+fn doneness_minutes(level: Doneness) -> i32 {
+    match level {
+        Doneness::Rare | Doneness::MediumRare | Doneness::Medium => 0,
+        Doneness::MediumWell => 2,
+        Doneness::WellDone => 4,
+    }
+}
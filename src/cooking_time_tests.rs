@@ -0,0 +1,126 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use crate::cooking_time::{CookingTimeEstimator, KitchenLoadEstimator, RandomEstimator};
+    use crate::menu_service::{self, MenuItem, MenuService};
+    use crate::order_service::{new_in_memory, Doneness, Modifier, Order, OrderService, OrderStatus};
+    use crate::order_service_tests::OrderServiceExt;
+    use crate::sqlite_order_service::new_sqlite;
+
+    // RecordingEstimator returns a fixed cooking time and remembers the loads it was asked about.
+    struct RecordingEstimator {
+        loads: Mutex<Vec<usize>>,
+    }
+
+    impl CookingTimeEstimator for RecordingEstimator {
+        fn base_time(&self, _: &Order) -> i32 {
+            7
+        }
+
+        fn estimate(&self, base_time: i32, orders_in_progress: usize) -> i32 {
+            self.loads.lock().unwrap().push(orders_in_progress);
+            base_time
+        }
+    }
+
+    fn estimate(estimator: &dyn CookingTimeEstimator, order: &Order, orders_in_progress: usize) -> i32 {
+        estimator.estimate(estimator.base_time(order), orders_in_progress)
+    }
+
+    fn menu_with_burger() -> Arc<dyn MenuService> {
+        let menu = menu_service::new_in_memory();
+        menu.put_item(MenuItem {
+            item_id: "burger".to_string(),
+            name: "Burger".to_string(),
+            price_cents: 1250,
+            category: "mains".to_string(),
+            preparation_time: 12,
+            available: true,
+        }).unwrap();
+        Arc::new(menu)
+    }
+
+    fn order(item_id: &str) -> Order {
+        Order {
+            item_id: item_id.to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_kitchen_load_estimate() {
+        let estimator = KitchenLoadEstimator::new(menu_with_burger(), 4, 5);
+
+        assert_eq!(estimate(&estimator, &order("burger"), 0), 12);
+        assert_eq!(estimate(&estimator, &order("burger"), 3), 12);
+        assert_eq!(estimate(&estimator, &order("burger"), 4), 17);
+        assert_eq!(estimate(&estimator, &order("burger"), 9), 22);
+    }
+
+    #[test]
+    fn test_kitchen_load_estimate_for_unknown_item() {
+        let estimator = KitchenLoadEstimator::new(menu_with_burger(), 4, 5);
+
+        assert_eq!(estimate(&estimator, &order("soup"), 0), 10);
+    }
+
+    #[test]
+    fn test_random_estimate_in_range() {
+        for _ in 0..100 {
+            assert!((5..=15).contains(&estimate(&RandomEstimator, &order("burger"), 0)));
+        }
+    }
+
+    #[test]
+    fn test_services_pass_current_load_to_estimator() {
+        let services: Vec<(Arc<RecordingEstimator>, Box<dyn OrderService>)> = {
+            let in_memory = Arc::new(RecordingEstimator { loads: Mutex::new(Vec::new()) });
+            let sqlite = Arc::new(RecordingEstimator { loads: Mutex::new(Vec::new()) });
+            vec![
+                (in_memory.clone(), Box::new(new_in_memory().with_estimator(in_memory))),
+                (sqlite.clone(), Box::new(new_sqlite(":memory:").unwrap().with_estimator(sqlite))),
+            ]
+        };
+
+        for (estimator, service) in services {
+            for i in 0..3 {
+                let order_result = service.put_order(format!("order{}", i), order("burger")).unwrap().into_order();
+                assert_eq!(order_result.cooking_time, 7);
+            }
+            service.delete_order("order0".to_string()).unwrap();
+            service.put_order("order3".to_string(), order("burger")).unwrap();
+
+            // Orders that are ready are no longer in progress.
+            service.update_status("order1".to_string(), OrderStatus::Cooking).unwrap();
+            service.update_status("order1".to_string(), OrderStatus::Ready).unwrap();
+            service.put_order("order4".to_string(), order("burger")).unwrap();
+
+            assert_eq!(*estimator.loads.lock().unwrap(), vec![0, 1, 2, 2, 2]);
+        }
+    }
+
+    #[test]
+    fn test_customizations_add_to_estimate() {
+        let estimator = KitchenLoadEstimator::new(menu_with_burger(), 4, 5);
+
+        let customized = Order {
+            quantity: 3,
+            modifiers: vec![
+                Modifier::Add { ingredient: "bacon".to_string() },
+                Modifier::Remove { ingredient: "onion".to_string() },
+                Modifier::Doneness { level: Doneness::WellDone },
+            ],
+            ..order("burger")
+        };
+        // 12 minutes + 2 extra portions * 2 + 2 ingredients * 1 + 4 for well done.
+        assert_eq!(estimate(&estimator, &customized, 0), 22);
+        assert_eq!(estimate(&estimator, &customized, 4), 27);
+
+        let medium = Order {
+            modifiers: vec![Modifier::Doneness { level: Doneness::Medium }],
+            ..order("burger")
+        };
+        assert_eq!(estimate(&estimator, &medium, 0), 12);
+    }
+}
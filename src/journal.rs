@@ -0,0 +1,283 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use rocket::serde::{Deserialize, DeserializeOwned, Serialize};
+use rocket::serde::json::serde_json;
+use crate::order_service::{OrderResult, OrderServiceError, OrderStatus};
+
+const JOURNAL_FILE: &str = "journal.log";
+const SNAPSHOT_FILE: &str = "snapshot.json";
+const SNAPSHOT_TMP_FILE: &str = "snapshot.json.tmp";
+
+// A single journaled mutation, stored as one JSON line in the journal file.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "rocket::serde", tag = "op", rename_all = "snake_case")]
+pub enum JournalEntry {
+    Put { order: OrderResult },
+    Delete {
+        order_id: String,
+        #[serde(default)]
+        seq: u64,
+    },
+    UpdateStatus {
+        order_id: String,
+        status: OrderStatus,
+        #[serde(default)]
+        updated_at: DateTime<Utc>,
+        #[serde(default)]
+        seq: u64,
+    },
+}
+
+// The snapshot file holds the orders and the sequence number of the last mutation, which must
+// not be handed out again even if the mutation was a deletion. Snapshots written before
+// mutations got sequence numbers are a plain list of orders.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde", untagged)]
+enum Snapshot<O> {
+    Sequenced { last_seq: u64, orders: Vec<O> },
+    Orders(Vec<O>),
+}
+
+// The state recovered from the snapshot and the journal.
+pub struct Recovered {
+    pub orders: Vec<OrderResult>,
+    pub last_seq: u64,
+}
+
+// Journal is an append-only log of mutations plus a snapshot of the state at the time
+// of the last compaction. Together they allow InMemoryOrderService to rebuild its
+// state after a crash: load the snapshot, then re-apply the journal on top of it.
+//
+// Replay is idempotent (a put or status update overwrites, a delete of a missing order is a no-op), so
+// a crash between writing a new snapshot and truncating the journal is harmless.
+pub struct Journal {
+    dir: PathBuf,
+    file: LineLog,
+    entries: usize,
+    compact_every: usize,
+}
+
+impl Journal {
+    // Opens the journal in `dir`, creating the directory if needed, and returns it together
+    // with the recovered state. The recovered state is compacted right away, which also
+    // drops a torn last line left behind by a crash mid-write.
+    pub fn open(dir: impl AsRef<Path>, compact_every: usize) -> Result<(Journal, Recovered), OrderServiceError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(io_error)?;
+
+        let (mut orders, mut last_seq) = read_snapshot(&dir.join(SNAPSHOT_FILE))?;
+        replay(&dir.join(JOURNAL_FILE), &mut orders, &mut last_seq)?;
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(JOURNAL_FILE))
+            .map_err(io_error)?;
+        let len = file.metadata().map_err(io_error)?.len();
+
+        let mut journal = Journal {
+            dir,
+            file: LineLog::new(file, len),
+            entries: 0,
+            compact_every,
+        };
+        journal.compact(orders.values(), last_seq)?;
+
+        Ok((journal, Recovered { orders: orders.into_values().collect(), last_seq }))
+    }
+
+    // Appends the entry and syncs it to disk, so that once this returns the mutation
+    // survives a crash and can be acknowledged to the client. If it fails, the entry is not
+    // in the journal and is not replayed after a restart.
+    pub fn append(&mut self, entry: &JournalEntry) -> Result<(), OrderServiceError> {
+        let mut line = serde_json::to_vec(entry).map_err(json_error)?;
+        line.push(b'\n');
+        self.file.append(&line).map_err(io_error)?;
+        self.entries += 1;
+        Ok(())
+    }
+
+    pub fn should_compact(&self) -> bool {
+        self.entries >= self.compact_every
+    }
+
+    // Writes the given orders and last sequence number as the new snapshot and truncates
+    // the journal. The caller must make sure no mutation happens concurrently.
+    pub fn compact<'a>(&mut self, orders: impl Iterator<Item = &'a OrderResult>, last_seq: u64) -> Result<(), OrderServiceError> {
+        let tmp_path = self.dir.join(SNAPSHOT_TMP_FILE);
+        {
+            let tmp = File::create(&tmp_path).map_err(io_error)?;
+            let mut writer = BufWriter::new(&tmp);
+            let snapshot = Snapshot::Sequenced { last_seq, orders: orders.collect() };
+            serde_json::to_writer(&mut writer, &snapshot).map_err(json_error)?;
+            writer.flush().map_err(io_error)?;
+            tmp.sync_all().map_err(io_error)?;
+        }
+        fs::rename(&tmp_path, self.dir.join(SNAPSHOT_FILE)).map_err(io_error)?;
+        // The rename is only durable once the directory is synced. Until then a crash could bring
+        // back the old snapshot, which must not happen after the journal is truncated.
+        sync_dir(&self.dir).map_err(io_error)?;
+
+        self.file.truncate().map_err(io_error)?;
+        self.entries = 0;
+        Ok(())
+    }
+}
+
+// LogFile is what LineLog needs of a file. Tests implement it to make writes fail.
+pub trait LogFile: Write {
+    fn sync_data(&self) -> io::Result<()>;
+    fn sync_all(&self) -> io::Result<()>;
+    fn set_len(&self, len: u64) -> io::Result<()>;
+}
+
+impl LogFile for File {
+    fn sync_data(&self) -> io::Result<()> {
+        File::sync_data(self)
+    }
+
+    fn sync_all(&self) -> io::Result<()> {
+        File::sync_all(self)
+    }
+
+    fn set_len(&self, len: u64) -> io::Result<()> {
+        File::set_len(self, len)
+    }
+}
+
+// LineLog appends lines to a file opened for appending, `len` bytes long. A line that could not
+// be written in full or synced is cut off again: only a torn last line can be told from a
+// corrupted file on replay, so the next line must not end up behind it. If cutting it off fails
+// as well, the next append tries again before it writes anything.
+pub struct LineLog<F: LogFile = File> {
+    file: F,
+    len: u64,
+    torn: bool,
+}
+
+impl<F: LogFile> LineLog<F> {
+    pub fn new(file: F, len: u64) -> LineLog<F> {
+        LineLog { file, len, torn: false }
+    }
+
+    // Appends the line, which must end with a newline, and syncs it to disk.
+    pub fn append(&mut self, line: &[u8]) -> io::Result<()> {
+        if self.torn {
+            self.file.set_len(self.len)?;
+            self.torn = false;
+        }
+
+        if let Err(e) = self.file.write_all(line).and_then(|_| self.file.sync_data()) {
+            self.torn = self.file.set_len(self.len).is_err();
+            return Err(e);
+        }
+        self.len += line.len() as u64;
+        Ok(())
+    }
+
+    // Empties the file.
+    pub fn truncate(&mut self) -> io::Result<()> {
+        self.file.set_len(0)?;
+        self.file.sync_all()?;
+        self.len = 0;
+        self.torn = false;
+        Ok(())
+    }
+}
+
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+// Registries that are small and change rarely, like the menu and the tables, are kept in the
+// journal directory as a whole: every change rewrites their file atomically, the same way as
+// the snapshot. A missing file is an empty registry.
+pub fn read_registry<T: DeserializeOwned>(dir: &Path, file_name: &str) -> io::Result<Vec<T>> {
+    let file = match File::open(dir.join(file_name)) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    serde_json::from_reader(BufReader::new(file)).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+pub fn write_registry<T: Serialize>(dir: &Path, file_name: &str, entries: &[T]) -> io::Result<()> {
+    let tmp_path = dir.join(format!("{}.tmp", file_name));
+    {
+        let tmp = File::create(&tmp_path)?;
+        let mut writer = BufWriter::new(&tmp);
+        serde_json::to_writer(&mut writer, entries).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        writer.flush()?;
+        tmp.sync_all()?;
+    }
+    fs::rename(&tmp_path, dir.join(file_name))?;
+    sync_dir(dir)
+}
+
+fn read_snapshot(path: &Path) -> Result<(HashMap<String, OrderResult>, u64), OrderServiceError> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok((HashMap::new(), 0)),
+        Err(e) => return Err(io_error(e)),
+    };
+
+    let (orders, last_seq) = match serde_json::from_reader(BufReader::new(file)).map_err(json_error)? {
+        Snapshot::Sequenced { last_seq, orders } => (orders, last_seq),
+        Snapshot::Orders(orders) => (orders, 0),
+    };
+    Ok((orders.into_iter().map(|order: OrderResult| (order.order_id.clone(), order)).collect(), last_seq))
+}
+
+fn replay(path: &Path, orders: &mut HashMap<String, OrderResult>, last_seq: &mut u64) -> Result<(), OrderServiceError> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(io_error(e)),
+    };
+
+    let mut lines = BufReader::new(file).lines().peekable();
+    while let Some(line) = lines.next() {
+        let line = line.map_err(io_error)?;
+        let entry = match serde_json::from_str::<JournalEntry>(&line) {
+            Ok(entry) => entry,
+            // Only the last line may be incomplete: it was being written when we crashed,
+            // so it was never acknowledged and can be dropped.
+            Err(_) if lines.peek().is_none() => {
+                warn!("Dropping incomplete last journal entry");
+                break;
+            }
+            Err(e) => return Err(json_error(e)),
+        };
+
+        match entry {
+            JournalEntry::Put { order } => {
+                *last_seq = (*last_seq).max(order.seq);
+                orders.insert(order.order_id.clone(), order);
+            }
+            JournalEntry::Delete { order_id, seq } => {
+                *last_seq = (*last_seq).max(seq);
+                orders.remove(&order_id);
+            }
+            JournalEntry::UpdateStatus { order_id, status, updated_at, seq } => {
+                *last_seq = (*last_seq).max(seq);
+                if let Some(order) = orders.get_mut(&order_id) {
+                    order.status = status;
+                    order.updated_at = updated_at;
+                    order.seq = seq;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn io_error(e: std::io::Error) -> OrderServiceError {
+    OrderServiceError::Storage(format!("journal I/O failed: {}", e))
+}
+
+fn json_error(e: serde_json::Error) -> OrderServiceError {
+    OrderServiceError::Storage(format!("invalid journal data: {}", e))
+}
@@ -0,0 +1,277 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::{self, ErrorKind, Write};
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use uuid::Uuid;
+    use crate::journal::{LineLog, LogFile};
+    use crate::order_service_tests::OrderServiceExt;
+    use crate::order_service::{
+        new_in_memory_with_journal, Change, Order, OrderQuery, OrderService, OrderServiceError, OrderStatus,
+    };
+
+    fn temp_journal_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("restaurant_api_journal_{}", Uuid::new_v4()))
+    }
+
+    fn order(item_id: &str, table_id: &str) -> Order {
+        Order {
+            item_id: item_id.to_string(),
+            table_id: table_id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_orders_are_recovered_after_restart() {
+        let dir = temp_journal_dir();
+        let created = {
+            let service = new_in_memory_with_journal(&dir, 1000).unwrap();
+            service.put_order("order1".to_string(), order("item1", "table1")).unwrap();
+            service.put_order("order2".to_string(), order("item2", "table1")).unwrap();
+            service.put_order("order3".to_string(), order("item1", "table2")).unwrap();
+            service.delete_order("order2".to_string()).unwrap();
+            service.get_orders(&OrderQuery { table_ids: vec!["table1".to_string()], ..Default::default() }).unwrap()
+        };
+
+        let service = new_in_memory_with_journal(&dir, 1000).unwrap();
+        let recovered = service.get_orders(&OrderQuery {
+            table_ids: vec!["table1".to_string()],
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].order_id, "order1");
+        assert_eq!(recovered[0].cooking_time, created[0].cooking_time);
+        assert_eq!(service.get_orders(&OrderQuery::default()).unwrap().len(), 2);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_compaction_truncates_journal() {
+        let dir = temp_journal_dir();
+        {
+            let service = new_in_memory_with_journal(&dir, 3).unwrap();
+            for i in 0..5 {
+                service.put_order(format!("order{}", i), order("item1", "table1")).unwrap();
+            }
+        }
+
+        // 3 entries were compacted into the snapshot, 2 are still in the journal.
+        let journal = fs::read_to_string(dir.join("journal.log")).unwrap();
+        assert_eq!(journal.lines().count(), 2);
+
+        let service = new_in_memory_with_journal(&dir, 3).unwrap();
+        assert_eq!(service.get_orders(&OrderQuery::default()).unwrap().len(), 5);
+
+        // Opening the journal compacts the recovered state.
+        let journal = fs::read_to_string(dir.join("journal.log")).unwrap();
+        assert!(journal.is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_torn_last_entry_is_dropped() {
+        let dir = temp_journal_dir();
+        {
+            let service = new_in_memory_with_journal(&dir, 1000).unwrap();
+            service.put_order("order1".to_string(), order("item1", "table1")).unwrap();
+        }
+
+        let mut journal = fs::OpenOptions::new().append(true).open(dir.join("journal.log")).unwrap();
+        journal.write_all(b"{\"op\":\"put\",\"order\":{\"order_id\":\"ord").unwrap();
+
+        let service = new_in_memory_with_journal(&dir, 1000).unwrap();
+        let orders = service.get_orders(&OrderQuery::default()).unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].order_id, "order1");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_corrupted_journal_is_rejected() {
+        let dir = temp_journal_dir();
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("journal.log"), "garbage\n{\"op\":\"delete\",\"order_id\":\"order1\"}\n").unwrap();
+
+        assert!(new_in_memory_with_journal(&dir, 1000).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_status_updates_are_recovered() {
+        let dir = temp_journal_dir();
+        {
+            let service = new_in_memory_with_journal(&dir, 1000).unwrap();
+            service.put_order("order1".to_string(), order("item1", "table1")).unwrap();
+            service.update_status("order1".to_string(), OrderStatus::Cooking).unwrap();
+            service.update_status("order1".to_string(), OrderStatus::Ready).unwrap();
+        }
+
+        let service = new_in_memory_with_journal(&dir, 1000).unwrap();
+        let orders = service.get_orders(&OrderQuery {
+            statuses: vec![OrderStatus::Ready],
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].order_id, "order1");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_sequence_numbers_survive_restart() {
+        let dir = temp_journal_dir();
+        {
+            let service = new_in_memory_with_journal(&dir, 2).unwrap();
+            service.put_order("order1".to_string(), order("item1", "table1")).unwrap();
+            service.put_order("order2".to_string(), order("item1", "table1")).unwrap();
+            service.update_status("order1".to_string(), OrderStatus::Cooking).unwrap();
+            service.delete_order("order2".to_string()).unwrap();
+        }
+
+        let service = new_in_memory_with_journal(&dir, 2).unwrap();
+        let changes = service.get_changes(None).unwrap();
+        assert_eq!(changes.last_seq, 4);
+        assert!(matches!(&changes.changes[..], [Change::Put(order)] if order.seq == 3));
+
+        // The deletion is no longer known, but later changes are.
+        assert!(matches!(service.get_changes(Some(3)), Err(OrderServiceError::ChangesCompacted(3))));
+        service.put_order("order3".to_string(), order("item1", "table1")).unwrap();
+        let changes = service.get_changes(Some(4)).unwrap();
+        assert!(matches!(&changes.changes[..], [Change::Put(order)] if order.seq == 5));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_without_sequence_numbers_is_recovered() {
+        let dir = temp_journal_dir();
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("snapshot.json"), r#"[
+            {"order_id": "order1", "item_id": "item1", "table_id": "table1", "cooking_time": 5},
+            {"order_id": "order2", "item_id": "item1", "table_id": "table1", "cooking_time": 5}
+        ]"#).unwrap();
+
+        let service = new_in_memory_with_journal(&dir, 1000).unwrap();
+        let changes = service.get_changes(None).unwrap();
+        let mut seqs: Vec<u64> = changes.changes.iter().map(Change::seq).collect();
+        seqs.sort();
+        assert_eq!(seqs, vec![1, 2]);
+        assert_eq!(changes.last_seq, 2);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // FlakyFile keeps its contents in memory and fails the operations the test tells it to. A
+    // write stores at most write_budget bytes, if set, and fails once the budget is used up.
+    #[derive(Default)]
+    struct Flaky {
+        data: Vec<u8>,
+        write_budget: Option<usize>,
+        fail_sync: bool,
+        fail_set_len: bool,
+    }
+
+    #[derive(Clone, Default)]
+    struct FlakyFile(Arc<Mutex<Flaky>>);
+
+    impl FlakyFile {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().data.clone()).unwrap()
+        }
+    }
+
+    impl Write for FlakyFile {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let mut file = self.0.lock().unwrap();
+            let n = file.write_budget.map_or(buf.len(), |budget| budget.min(buf.len()));
+            if n == 0 {
+                return Err(io::Error::new(ErrorKind::StorageFull, "no space left"));
+            }
+            file.write_budget = file.write_budget.map(|budget| budget - n);
+            file.data.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl LogFile for FlakyFile {
+        fn sync_data(&self) -> io::Result<()> {
+            if self.0.lock().unwrap().fail_sync {
+                return Err(io::Error::other("sync failed"));
+            }
+            Ok(())
+        }
+
+        fn sync_all(&self) -> io::Result<()> {
+            self.sync_data()
+        }
+
+        fn set_len(&self, len: u64) -> io::Result<()> {
+            let mut file = self.0.lock().unwrap();
+            if file.fail_set_len {
+                return Err(io::Error::other("truncate failed"));
+            }
+            file.data.truncate(len as usize);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_partially_written_line_is_cut_off() {
+        let file = FlakyFile::default();
+        let mut log = LineLog::new(file.clone(), 0);
+        log.append(b"first\n").unwrap();
+
+        file.0.lock().unwrap().write_budget = Some(3);
+        assert!(log.append(b"second\n").is_err());
+        assert_eq!(file.contents(), "first\n");
+
+        file.0.lock().unwrap().write_budget = None;
+        log.append(b"third\n").unwrap();
+        assert_eq!(file.contents(), "first\nthird\n");
+    }
+
+    #[test]
+    fn test_unsynced_line_is_cut_off() {
+        let file = FlakyFile::default();
+        let mut log = LineLog::new(file.clone(), 0);
+        log.append(b"first\n").unwrap();
+
+        file.0.lock().unwrap().fail_sync = true;
+        assert!(log.append(b"second\n").is_err());
+        assert_eq!(file.contents(), "first\n");
+    }
+
+    #[test]
+    fn test_torn_line_is_cut_off_before_the_next_append() {
+        let file = FlakyFile::default();
+        let mut log = LineLog::new(file.clone(), 0);
+        log.append(b"first\n").unwrap();
+
+        {
+            let mut flaky = file.0.lock().unwrap();
+            flaky.write_budget = Some(3);
+            flaky.fail_set_len = true;
+        }
+        assert!(log.append(b"second\n").is_err());
+        assert_eq!(file.contents(), "first\nsec");
+
+        // As long as the torn line cannot be cut off, nothing is written behind it.
+        file.0.lock().unwrap().write_budget = None;
+        assert!(log.append(b"third\n").is_err());
+        assert_eq!(file.contents(), "first\nsec");
+
+        file.0.lock().unwrap().fail_set_len = false;
+        log.append(b"third\n").unwrap();
+        assert_eq!(file.contents(), "first\nthird\n");
+    }
+}
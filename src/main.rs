@@ -1,6 +1,44 @@
 mod tests;
 mod order_service;
 mod order_service_tests;
+mod journal;
+mod journal_tests;
+mod sqlite_order_service;
+mod sqlite_order_service_tests;
+mod async_order_service;
+mod async_order_service_tests;
+mod async_menu_service;
+mod async_menu_service_tests;
+mod async_table_service;
+mod async_table_service_tests;
+mod menu_service;
+mod menu_service_tests;
+mod sqlite_menu_service;
+mod sqlite_menu_service_tests;
+mod table_service;
+mod table_service_tests;
+mod sqlite_table_service;
+mod sqlite_table_service_tests;
+mod cooking_time;
+mod cooking_time_tests;
+mod pagination;
+mod pagination_tests;
+mod order_events;
+mod order_events_tests;
+mod webhook_service;
+mod webhook_service_tests;
+mod webhook_dispatcher;
+mod webhook_dispatcher_tests;
+mod validation;
+mod validation_tests;
+mod api_error;
+mod api_error_tests;
+mod auth;
+mod auth_tests;
+mod policy;
+mod policy_tests;
+mod audit_log;
+mod audit_log_tests;
 
 extern crate rocket;
 
@@ -10,42 +48,143 @@ extern crate log;
 
 extern crate chrono;
 
-use std::collections::HashMap;
-use std::sync::{Arc, Once, RwLock};
-use std::error::Error;
-use std::fmt;
+use std::sync::{Arc, Once};
 use std::env; // Added to read environment variables
-use rocket::{delete, get, launch, put, routes, Build, State};
-use rocket::http::Status;
+use std::time::Duration;
+use std::io::Write;
+use chrono::{DateTime, Utc};
+use rocket::{catch, catchers, delete, get, launch, post, put, routes, Build, FromForm, Route, Shutdown, State};
+use rocket::fairing::AdHoc;
+use rocket::http::{ContentType, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::stream::{Event, EventStream};
+use rocket::tokio::select;
+use rocket::tokio::sync::broadcast::error::RecvError;
 use rocket::serde::{Deserialize, Serialize};
-use rocket::serde::json::{json, Json};
+use rocket::serde::json::{serde_json, Json};
 use rocket::serde::json::Error as JsonError;
-use rand::Rng;
 use rocket::figment::Figment;
-use order_service::{InMemoryOrderService, OrderService, OrderServiceError};
-use crate::order_service::{Order, OrderResult};
+use utoipa::{IntoParams, IntoResponses, Modify, OpenApi, ToSchema};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use order_service::OrderService;
+use crate::api_error::{ApiError, ErrorCode, ErrorCodeInfo, ErrorResponse};
+use crate::async_menu_service::AsyncMenuService;
+use crate::async_order_service::AsyncOrderService;
+use crate::async_table_service::AsyncTableService;
+use crate::audit_log::{AuditEntry, AuditLog, AuditOperation, AuditQuery, Auditor};
+use crate::auth::{Auth, IssuedToken, MIN_SECRET_LEN};
+use crate::cooking_time::{CookingTimeEstimator, KitchenLoadEstimator, RandomEstimator};
+use crate::order_events::{EventId, OrderEvent, OrderEvents, DEFAULT_HISTORY};
+use crate::order_service::{Change, Modifier, Order, OrderQuery, OrderResult, OrderServiceError, OrderStatus};
+use crate::menu_service::{MenuItem, MenuService, MenuServiceError};
+use crate::policy::{Authorized, Role};
+use crate::pagination::{Cursor, PageRequest, SortDirection, SortKey, DEFAULT_LIMIT, MAX_LIMIT};
+use crate::table_service::{Table, TableService, TableServiceError, TableStatus};
+use crate::validation::{IdFormat, Validator, MAX_NAME_LEN, MAX_SECRET_LEN, MAX_URL_LEN};
+use crate::webhook_dispatcher::{RetryPolicy, WebhookDispatcher};
+use crate::webhook_service::{Delivery, DeliveryStatus, Webhook, WebhookEvent, WebhookService};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 #[serde(crate = "rocket::serde")]
 pub struct CreateOrder {
     item_id: String,
     table_id: String,
+    #[serde(default = "default_quantity")]
+    quantity: u32,
+    #[serde(default)]
+    modifiers: Vec<Modifier>,
+    #[serde(default)]
+    notes: Option<String>,
 }
 
-#[derive(Serialize, Clone, Deserialize, Debug)]
+fn default_quantity() -> u32 {
+    1
+}
+
+#[derive(Serialize, Clone, Deserialize, Debug, ToSchema)]
 #[serde(crate = "rocket::serde")]
 pub struct OrderDetails {
     order_id: String,
     item_id: String,
     table_id: String,
     cooking_time: i32,
+    quantity: u32,
+    modifiers: Vec<Modifier>,
+    notes: Option<String>,
+    // The device that placed the order.
+    placed_by: Option<String>,
+    status: OrderStatus,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    estimated_ready_at: DateTime<Utc>,
+    seq: u64,
+}
+
+impl From<OrderResult> for OrderDetails {
+    fn from(order: OrderResult) -> OrderDetails {
+        OrderDetails {
+            order_id: order.order_id,
+            item_id: order.item_id,
+            table_id: order.table_id,
+            cooking_time: order.cooking_time,
+            quantity: order.quantity,
+            modifiers: order.modifiers,
+            notes: order.notes,
+            placed_by: order.placed_by,
+            status: order.status,
+            created_at: order.created_at,
+            updated_at: order.updated_at,
+            estimated_ready_at: order.estimated_ready_at,
+            seq: order.seq,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct CreateMenuItem {
+    name: String,
+    price_cents: u32,
+    category: String,
+    preparation_time: i32,
+    #[serde(default = "default_available")]
+    available: bool,
+}
+
+fn default_available() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct CreateTable {
+    capacity: u32,
+    section: String,
+    #[serde(default = "default_table_status")]
+    status: TableStatus,
+}
+
+fn default_table_status() -> TableStatus {
+    TableStatus::Open
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 #[serde(crate = "rocket::serde")]
-pub struct ErrorResponse {
-    error: String,
-    error_code: String,
+pub struct CreateWebhook {
+    url: String,
+    events: Vec<WebhookEvent>,
+    secret: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct EnrollDevice {
+    device_id: String,
+    role: Role,
+    // The section of the restaurant a waiter serves.
+    #[serde(default)]
+    section: Option<String>,
+    enrollment_key: String,
 }
 
 // PUT /order/<id> is a simple idempotent way of adding new Orders into the system.
@@ -57,141 +196,1527 @@ pub struct ErrorResponse {
 // we don't want the same order to be submitted twice or an existing order to be overwritten
 // by its own copy with a different cooking time.
 //
+// A device that did not get the response can safely retry: a PUT with the same body as the one
+// that created the order returns the stored order, including the cooking time assigned back then.
+// Only a PUT with a different body for an existing id is rejected.
+//
 // There is chance that two waiters' devices would generate the same UUID4, but
 // the chance is practically negligible. Considering the "cost" of such error, this is no-issue.
+// With APP_ORDER_ID_FORMAT=uuid4 ids that are not UUID4 are rejected, otherwise any id made of
+// ASCII letters, digits, '-', '_' and '.' is accepted.
+//
+// Besides item_id and table_id, the body may carry a quantity (1 by default), modifiers
+// (added or removed ingredients, doneness) and free-text notes for the kitchen, e.g.
+// {"item_id": "burger", "table_id": "1", "quantity": 2,
+//  "modifiers": [{"type": "remove", "ingredient": "onion"}, {"type": "doneness", "level": "medium_rare"}],
+//  "notes": "sauce on the side"}
+// Modifiers make the order take longer to cook.
+//
+// Every request that changes an order (PUT, DELETE and the status transitions) is recorded in the
+// audit log, see GET /audit. The X-Audit-Reason header may say why the change was made.
 //
 // PUT /order/<id> may return:
-// Status: 200, Body: details of newly accepted order
+// Status: 200, Body: details of newly accepted order (or of the stored order, for a retry)
 // Status: 400, Body: error description, error code (in case of malformed request body)
-// Status: 409, Body: error description, error code (in case Order with such id already exists)
+// Status: 401, Body: error description, error code (UNAUTHENTICATED without a valid bearer token)
+// Status: 403, Body: error description, error code (FORBIDDEN unless a waiter's or a manager's device)
+// Status: 415, Body: error description, error code (UNSUPPORTED_MEDIA_TYPE unless Content-Type is application/json)
+// Status: 409, Body: error description, error code
+//   (DUPLICATE_ORDER if Order with such id already exists with a different body, the error
+//   names the fields that differ; TABLE_CLOSED if the table is closed)
+// Status: 422, Body: error description, error code
+//   (VALIDATION_FAILED if the id, item_id, table_id or X-Audit-Reason is malformed,
+//   UNKNOWN_ITEM if the item is not on the menu, ITEM_UNAVAILABLE if it is disabled,
+//   UNKNOWN_TABLE if the table is not registered,
+//   INVALID_ORDER if quantity, modifiers or notes are out of bounds)
 // Status: 500, Body: error description, error code
+#[utoipa::path(
+    context_path = "/order",
+    tag = "orders",
+    security(("bearer" = [])),
+    request_body = CreateOrder,
+    params(
+        ("X-Audit-Reason" = Option<String>, Header, description = "Why the change is made, recorded in the audit log"),
+    ),
+    responses(
+        (status = 200, description = "The newly accepted order, or the stored order for a retry", body = OrderDetails),
+        (status = 400, description = "INVALID_BODY: malformed request body", body = ErrorResponse),
+        (status = 401, description = "UNAUTHENTICATED: the bearer token is missing, invalid or expired", body = ErrorResponse),
+        (status = 403, description = "FORBIDDEN: the device's role may not place orders", body = ErrorResponse),
+        (status = 415, description = "UNSUPPORTED_MEDIA_TYPE: Content-Type is not application/json", body = ErrorResponse),
+        (status = 409, description = "DUPLICATE_ORDER: an order with this id exists with a different body, the error names the fields that differ; TABLE_CLOSED: the table is closed", body = ErrorResponse),
+        (status = 422, description = "VALIDATION_FAILED: the id, a field or the reason is malformed, the fields array describes each violation; UNKNOWN_ITEM: the item is not on the menu; ITEM_UNAVAILABLE: the item is disabled; UNKNOWN_TABLE: the table is not registered; INVALID_ORDER: quantity, modifiers or notes are out of bounds", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
 #[put("/<id>", format = "json", data = "<order>")]
-fn put_order(
+#[allow(clippy::too_many_arguments)] // Rocket passes every state and guard as an argument.
+async fn put_order(
     id: String,
     order: Result<Json<CreateOrder>, JsonError<'_>>,
-    order_service: &State<Arc<dyn OrderService>>,
-) -> Result<Json<OrderDetails>, (Status, Json<ErrorResponse>)> {
-    let order = match order {
-        Ok(order) => order.into_inner(),
-        Err(e) => {
-            error!("Failed to parse JSON body: {}", e);
-            return Err((
-                Status::BadRequest,
-                Json(ErrorResponse {
-                    error: "Invalid request body".to_string(),
-                    error_code: "INVALID_BODY".to_string(),
-                }),
-            ));
-        }
-    };
+    order_service: &State<AsyncOrderService>,
+    menu_service: &State<AsyncMenuService>,
+    table_service: &State<AsyncTableService>,
+    id_format: &State<IdFormat>,
+    device: Authorized,
+    auditor: Auditor,
+) -> Result<Json<OrderDetails>, ApiError> {
+    let order = order.map_err(ApiError::invalid_body)?.into_inner();
+
+    Validator::new()
+        .order_id("id", &id, *id_format.inner())
+        .id("item_id", &order.item_id)
+        .id("table_id", &order.table_id)
+        .finish()?;
+    auditor.validate()?;
 
-    let res = order_service.put_order(id.clone(), Order {
+    match menu_service.get_item(order.item_id.clone()).await {
+        Ok(item) if item.available => {}
+        Ok(_) => return Err(ApiError::new(ErrorCode::ItemUnavailable, "Item is currently unavailable")),
+        Err(MenuServiceError::ItemNotFound(_)) => return Err(ApiError::new(ErrorCode::UnknownItem, "Item is not on the menu")),
+        Err(e) => return Err(e.into()),
+    }
+
+    match table_service.get_table(order.table_id.clone()).await {
+        Ok(table) if table.status == TableStatus::Open => {}
+        Ok(_) => return Err(ApiError::new(ErrorCode::TableClosed, "Table is closed")),
+        Err(TableServiceError::TableNotFound(_)) => return Err(ApiError::new(ErrorCode::UnknownTable, "Table is not registered")),
+        Err(e) => return Err(e.into()),
+    }
+
+    let outcome = order_service.put_order(id.clone(), Order {
         item_id: order.item_id,
         table_id: order.table_id,
-    });
+        quantity: order.quantity,
+        modifiers: order.modifiers,
+        notes: order.notes,
+        placed_by: Some(device.claims.sub.clone()),
+    }, auditor.audit(&device.claims, AuditOperation::Create)).await?;
 
-    match res {
-        Ok(order_result) => Ok(Json(OrderDetails {
-            order_id: order_result.order_id,
-            item_id: order_result.item_id,
-            table_id: order_result.table_id,
-            cooking_time: order_result.cooking_time,
-        })),
-        Err(OrderServiceError::DuplicateOrder(_)) => Err((
-            Status::Conflict,
-            Json(ErrorResponse {
-                error: "Order already exists".to_string(),
-                error_code: "DUPLICATE_ORDER".to_string(),
-            }),
-        )),
-        Err(e) => {
-            error!("{}", e);
-            Err((
-                Status::InternalServerError,
-                Json(ErrorResponse {
-                    error: "Internal server error".to_string(),
-                    error_code: "INTERNAL_ERROR".to_string(),
-                }),
-            ))
-        }
+    if !outcome.is_created() {
+        info!("Order '{}' was already accepted, returning the stored order", id);
     }
+    Ok(Json(outcome.into_order().into()))
 }
 
-// GET /orders?table_id=...&item_id=... provides a filtered list of orders.
+// GET /orders?table_id=...&item_id=...&placed_by=...&status=... provides a filtered list of orders.
 // The filtering logic:
 // table_id + item_id: all orders with given item for the specified table
 // table_id: all orders for the given table
 // item_id: all orders with the given item
 // <no params>: all orders in the system
-// (Note: for production system we would implement pagination with reasonable default params)
-
-// GET /orders?table_id=...&item_id=... may return:
-// Status: 200, Body: array of orders' details
-// Status: 500, Body: error description, error code
-#[get("/?<table_id>&<item_id>")]
-fn get_order(
-    table_id: Option<String>,
-    item_id: Option<String>,
-    order_service: &State<Arc<dyn OrderService>>,
-) -> Result<Json<Vec<OrderDetails>>, (Status, Json<ErrorResponse>)> {
-    let res = order_service
-        .inner()
-        .get_orders(table_id.clone(), item_id.clone());
-
-    match res {
-        Ok(orders) => {
-            let order_details: Vec<OrderDetails> = orders.into_iter().map(|order| OrderDetails {
-                order_id: order.order_id,
-                item_id: order.item_id,
-                table_id: order.table_id,
-                cooking_time: order.cooking_time,
-            }).collect();
-
-            Ok(Json(order_details))
-        },
-        Err(e) => {
-            error!("{}", e);
-            Err((
-                Status::InternalServerError,
-                Json(ErrorResponse {
-                    error: "Internal server error".to_string(),
-                    error_code: "INTERNAL_ERROR".to_string(),
-                }),
-            ))
+// status (placed, cooking, ready, served or cancelled) further restricts any of the above
+// placed_by (a device id) keeps only orders placed by that device, e.g. a waiter's own orders
+// since/until (RFC 3339 timestamps) keep only orders created at or after since and before until
+// table_id, item_id, placed_by and status may be repeated to match any of the given values, e.g.
+// /orders?table_id=1&table_id=2&status=placed&status=cooking
+//
+// The list is paginated. Orders are sorted by sort (created, table or item; created by default)
+// in direction (asc or desc; asc by default), ties are broken by order id. A page holds up to
+// limit orders (100 by default, at most 1000). If there are more orders, next_cursor is set:
+// passing it as cursor, together with the same filters, returns the next page. Orders created or
+// deleted in the meantime never cause an order to be returned twice or to be skipped.
+#[derive(FromForm, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ListOrdersParams<'r> {
+    #[param(required = false)]
+    table_id: Vec<String>,
+    #[param(required = false)]
+    item_id: Vec<String>,
+    #[param(required = false)]
+    placed_by: Vec<String>,
+    #[param(value_type = Vec<OrderStatus>, required = false)]
+    status: Vec<&'r str>,
+    #[param(value_type = Option<DateTime<Utc>>)]
+    since: Option<&'r str>,
+    #[param(value_type = Option<DateTime<Utc>>)]
+    until: Option<&'r str>,
+    #[param(value_type = Option<usize>, minimum = 1, maximum = 1000)]
+    limit: Option<&'r str>,
+    #[param(value_type = Option<String>)]
+    cursor: Option<&'r str>,
+    #[param(value_type = Option<String>, pattern = "^(created|table|item)$")]
+    sort: Option<&'r str>,
+    #[param(value_type = Option<String>, pattern = "^(asc|desc)$")]
+    direction: Option<&'r str>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct OrderList {
+    orders: Vec<OrderDetails>,
+    next_cursor: Option<String>,
+}
+
+// OrderChange is an entry of the change feed: either the current details of a created or
+// updated order, or a tombstone of a deleted one (deleted is true and order is null).
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct OrderChange {
+    seq: u64,
+    order_id: String,
+    deleted: bool,
+    order: Option<OrderDetails>,
+}
+
+impl From<Change> for OrderChange {
+    fn from(change: Change) -> OrderChange {
+        match change {
+            Change::Put(order) => OrderChange {
+                seq: order.seq,
+                order_id: order.order_id.clone(),
+                deleted: false,
+                order: Some(order.into()),
+            },
+            Change::Deleted(tombstone) => OrderChange {
+                seq: tombstone.seq,
+                order_id: tombstone.order_id,
+                deleted: true,
+                order: None,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct ChangeList {
+    changes: Vec<OrderChange>,
+    last_seq: u64,
+}
+
+fn parse_timestamp(value: Option<&str>) -> Result<Option<DateTime<Utc>>, chrono::ParseError> {
+    value
+        .map(|v| DateTime::parse_from_rfc3339(v).map(|ts| ts.with_timezone(&Utc)))
+        .transpose()
+}
+
+// Checks the id of a menu item, table or webhook given in the path.
+fn validate_id(id: &str) -> Result<(), ApiError> {
+    Ok(Validator::new().id("id", id).finish()?)
+}
+
+fn validate_order_id(id: &str, format: IdFormat) -> Result<(), ApiError> {
+    Ok(Validator::new().order_id("id", id, format).finish()?)
+}
+
+// A cursor carries the sort order of the listing it was issued for. Sort and direction may be
+// left out when following a cursor, but must not contradict it.
+fn parse_page_request(params: &ListOrdersParams<'_>) -> Result<PageRequest, ApiError> {
+    let limit = match params.limit.map(str::parse::<usize>) {
+        None => DEFAULT_LIMIT,
+        Some(Ok(limit)) if (1..=MAX_LIMIT).contains(&limit) => limit,
+        Some(_) => return Err(ApiError::new(ErrorCode::InvalidLimit, format!("limit must be between 1 and {}", MAX_LIMIT))),
+    };
+    let sort = match params.sort.map(SortKey::parse) {
+        None => None,
+        Some(Some(sort)) => Some(sort),
+        Some(None) => return Err(ApiError::new(ErrorCode::InvalidSort, "sort must be one of created, table or item")),
+    };
+    let direction = match params.direction.map(SortDirection::parse) {
+        None => None,
+        Some(Some(direction)) => Some(direction),
+        Some(None) => return Err(ApiError::new(ErrorCode::InvalidSort, "direction must be asc or desc")),
+    };
+
+    let after = match params.cursor.map(Cursor::decode) {
+        None => None,
+        Some(Some(cursor)) => Some(cursor),
+        Some(None) => return Err(ApiError::new(ErrorCode::InvalidCursor, "Malformed cursor")),
+    };
+    if let Some(cursor) = &after {
+        if sort.is_some_and(|sort| sort != cursor.sort) || direction.is_some_and(|direction| direction != cursor.direction) {
+            return Err(ApiError::new(ErrorCode::InvalidCursor, "Cursor was issued for a different sort order"));
         }
     }
+
+    Ok(PageRequest {
+        sort: after.as_ref().map(|cursor| cursor.sort).or(sort).unwrap_or_default(),
+        direction: after.as_ref().map(|cursor| cursor.direction).or(direction).unwrap_or_default(),
+        limit,
+        after,
+    })
+}
+
+// GET /orders?table_id=...&item_id=...&placed_by=...&status=...&since=...&until=...&limit=...&cursor=...&sort=...&direction=...
+// may return:
+// Status: 200, Body: a page of orders' details and the cursor of the next page (null on the last page)
+// Status: 400, Body: error description, error code (INVALID_STATUS in case of unknown status,
+//   INVALID_TIMESTAMP in case of malformed since/until timestamp, INVALID_LIMIT, INVALID_SORT,
+//   INVALID_CURSOR in case of a malformed cursor or one issued for a different sort order)
+// Status: 401, Body: error description, error code (UNAUTHENTICATED without a valid bearer token)
+// Status: 500, Body: error description, error code
+#[utoipa::path(
+    path = "/orders",
+    tag = "orders",
+    security(("bearer" = [])),
+    params(ListOrdersParams),
+    responses(
+        (status = 200, description = "A page of orders and the cursor of the next page, null on the last page", body = OrderList),
+        (status = 400, description = "INVALID_STATUS: unknown status; INVALID_TIMESTAMP: malformed since or until; INVALID_LIMIT: limit out of bounds; INVALID_SORT: unknown sort or direction; INVALID_CURSOR: malformed cursor or one issued for a different sort order", body = ErrorResponse),
+        (status = 401, description = "UNAUTHENTICATED: the bearer token is missing, invalid or expired", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[get("/?<params..>")]
+async fn get_order(
+    params: ListOrdersParams<'_>,
+    order_service: &State<AsyncOrderService>,
+    _device: Authorized,
+) -> Result<Json<OrderList>, ApiError> {
+    let statuses = match params.status.iter().map(|status| OrderStatus::parse(status)).collect() {
+        Some(statuses) => statuses,
+        None => return Err(ApiError::new(ErrorCode::InvalidStatus, "Unknown order status")),
+    };
+
+    let (since, until) = match (parse_timestamp(params.since), parse_timestamp(params.until)) {
+        (Ok(since), Ok(until)) => (since, until),
+        _ => return Err(ApiError::new(ErrorCode::InvalidTimestamp, "Timestamps must be in RFC 3339 format")),
+    };
+
+    let page = parse_page_request(&params)?;
+    let query = OrderQuery {
+        table_ids: params.table_id,
+        item_ids: params.item_id,
+        placed_by: params.placed_by,
+        statuses,
+        created_after: since,
+        created_before: until,
+    };
+
+    let page = order_service
+        .get_orders_page(query, page)
+        .await?;
+
+    let order_details: Vec<OrderDetails> = page.orders.into_iter().map(OrderDetails::from).collect();
+
+    Ok(Json(OrderList {
+        orders: order_details,
+        next_cursor: page.next.map(|cursor| cursor.encode()),
+    }))
+}
+
+// GET /order/<id> returns the current state of a single order, e.g. for a device that has just
+// reconnected and wants to know whether its order is ready.
+// Status: 200, Body: details of the order
+// Status: 401, Body: error description, error code (UNAUTHENTICATED without a valid bearer token)
+// Status: 404, Body: error description, error code
+// Status: 422, Body: error description, error code (VALIDATION_FAILED if the id is malformed)
+// Status: 500, Body: error description, error code
+#[utoipa::path(
+    context_path = "/order",
+    tag = "orders",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The order", body = OrderDetails),
+        (status = 401, description = "UNAUTHENTICATED: the bearer token is missing, invalid or expired", body = ErrorResponse),
+        (status = 404, description = "ORDER_NOT_FOUND", body = ErrorResponse),
+        (status = 422, description = "VALIDATION_FAILED: the id is malformed, the fields array describes each violation", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[get("/<id>")]
+async fn get_order_by_id(
+    id: String,
+    order_service: &State<AsyncOrderService>,
+    id_format: &State<IdFormat>,
+    _device: Authorized,
+) -> Result<Json<OrderDetails>, ApiError> {
+    validate_order_id(&id, *id_format.inner())?;
+
+    let order_result = order_service.get_order_by_id(id).await?;
+    Ok(Json(order_result.into()))
+}
+
+// The Last-Event-ID header, sent by SSE clients when they reconnect.
+struct LastEventId<'r>(Option<&'r str>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LastEventId<'r> {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, ()> {
+        Outcome::Success(LastEventId(request.headers().get_one("Last-Event-ID")))
+    }
+}
+
+fn order_event(epoch: u32, event: OrderEvent) -> Event {
+    Event::json(&OrderDetails::from(event.order))
+        .event(event.kind.as_str())
+        .id(EventId { epoch, id: event.id }.to_string())
+}
+
+// Tells the client that it missed events which can no longer be replayed, so it has to refetch
+// the orders with GET /orders. The id lets it resume from here on its next reconnect.
+fn resync_event(epoch: u32, last_id: u64) -> Event {
+    Event::data("Missed events are no longer available, refetch the orders")
+        .event("resync")
+        .id(EventId { epoch, id: last_id }.to_string())
+}
+
+// GET /orders/events?table_id=...&item_id=... is a Server-Sent Events stream of order changes,
+// so that kitchen screens and waiters' devices don't have to poll GET /orders.
+// Every event carries the full details of the order as data and one of the following types:
+// created: a new order was accepted
+// updated: the order's status changed
+// deleted: the order was deleted (data holds the order as it was)
+// table_id and item_id filter events the same way they filter GET /orders, and may be repeated.
+//
+// Event ids are made of the server's epoch, which changes on every restart, and a number that
+// grows by one with every event, e.g. 2718281828-17. A client reconnecting with the Last-Event-ID
+// header first gets the events it missed. If they are no longer kept (only the latest
+// APP_EVENTS_HISTORY events are, 1000 by default), or its id has another epoch because the server
+// was restarted in the meantime, it gets a resync event instead and should refetch the orders.
+// Status: 200, Body: the event stream
+// Status: 400, Body: error description, error code (INVALID_EVENT_ID in case of a malformed Last-Event-ID)
+// Status: 401, Body: error description, error code (UNAUTHENTICATED without a valid bearer token)
+#[utoipa::path(
+    context_path = "/orders",
+    tag = "orders",
+    security(("bearer" = [])),
+    params(
+        ("table_id" = Option<Vec<String>>, Query, description = "Only events of orders for these tables"),
+        ("item_id" = Option<Vec<String>>, Query, description = "Only events of orders for these items"),
+        ("Last-Event-ID" = Option<String>, Header, description = "The id of the last event the client has seen, e.g. 2718281828-17"),
+    ),
+    responses(
+        (status = 200, description = "Server-Sent Events of type created, updated or deleted with the order as data, or resync", content_type = "text/event-stream", body = String),
+        (status = 400, description = "INVALID_EVENT_ID: malformed Last-Event-ID", body = ErrorResponse),
+        (status = 401, description = "UNAUTHENTICATED: the bearer token is missing, invalid or expired", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[get("/events?<table_id>&<item_id>")]
+async fn stream_order_events(
+    table_id: Vec<String>,
+    item_id: Vec<String>,
+    last_event_id: LastEventId<'_>,
+    events: &State<Arc<OrderEvents>>,
+    mut shutdown: Shutdown,
+    _device: Authorized,
+) -> Result<EventStream![], ApiError> {
+    let last_event_id = match last_event_id.0.map(str::parse::<EventId>) {
+        None => None,
+        Some(Ok(id)) => Some(id),
+        Some(Err(_)) => return Err(ApiError::new(ErrorCode::InvalidEventId, "Last-Event-ID must be an event id")),
+    };
+
+    let query = OrderQuery {
+        table_ids: table_id,
+        item_ids: item_id,
+        ..Default::default()
+    };
+    let events = events.inner().clone();
+    let epoch = events.epoch();
+    let mut subscription = events.resume(last_event_id);
+
+    Ok(EventStream! {
+        loop {
+            let mut last_id = subscription.last_id;
+            match subscription.missed.take() {
+                Some(missed) => for event in missed {
+                    if query.matches(&event.order) {
+                        yield order_event(epoch, event);
+                    }
+                },
+                None => yield resync_event(epoch, last_id),
+            }
+
+            // Events that were sent while the client was catching up are in the receiver.
+            loop {
+                let event = select! {
+                    biased;
+                    event = subscription.receiver.recv() => event,
+                    _ = &mut shutdown => return,
+                };
+                match event {
+                    Ok(event) => {
+                        last_id = event.id;
+                        if query.matches(&event.order) {
+                            yield order_event(epoch, event);
+                        }
+                    }
+                    // The client fell too far behind: continue from the history, if possible.
+                    Err(RecvError::Lagged(_)) => break,
+                    Err(RecvError::Closed) => return,
+                }
+            }
+            subscription = events.subscribe(Some(last_id));
+        }
+    })
+}
+
+// GET /orders/changes?since=<seq> is a sync primitive for devices that go offline for minutes at
+// a time. Every mutation of an order gets a sequence number, one higher than the previous one.
+// The response lists what changed after the mutation with sequence number since, in the order
+// the changes happened: created or updated orders with their current details, and tombstones
+// of deleted ones. last_seq is the since to pass next time.
+// Without since, the response lists all orders, to start syncing from scratch.
+//
+// Deletions are only remembered for so long (and not across restarts of the in-memory storage),
+// so a device that was offline for too long gets a 410 RESYNC_REQUIRED response. It then has to
+// start from scratch.
+// Status: 200, Body: the changes and the sequence number of the latest one
+// Status: 400, Body: error description, error code (INVALID_SEQ in case since is not a number)
+// Status: 401, Body: error description, error code (UNAUTHENTICATED without a valid bearer token)
+// Status: 410, Body: error description, error code (RESYNC_REQUIRED)
+// Status: 500, Body: error description, error code
+#[utoipa::path(
+    context_path = "/orders",
+    tag = "orders",
+    security(("bearer" = [])),
+    params(
+        ("since" = Option<u64>, Query, description = "The last_seq of the previous sync, left out to list all orders"),
+    ),
+    responses(
+        (status = 200, description = "The changes after since and the sequence number of the latest one", body = ChangeList),
+        (status = 400, description = "INVALID_SEQ: since is not a sequence number", body = ErrorResponse),
+        (status = 401, description = "UNAUTHENTICATED: the bearer token is missing, invalid or expired", body = ErrorResponse),
+        (status = 410, description = "RESYNC_REQUIRED: the changes after since are no longer available", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[get("/changes?<since>")]
+async fn get_order_changes(
+    since: Option<&str>,
+    order_service: &State<AsyncOrderService>,
+    _device: Authorized,
+) -> Result<Json<ChangeList>, ApiError> {
+    let since = match since.map(str::parse::<u64>) {
+        None => None,
+        Some(Ok(since)) => Some(since),
+        Some(Err(_)) => return Err(ApiError::new(ErrorCode::InvalidSeq, "since must be a sequence number")),
+    };
+
+    let changes = order_service.get_changes(since).await?;
+    Ok(Json(ChangeList {
+        changes: changes.changes.into_iter().map(OrderChange::from).collect(),
+        last_seq: changes.last_seq,
+    }))
 }
 
 // DELETE /order/<id> either deletes an order or returns a 404 if such order does not exist.
 // Status: 200
+// Status: 401, Body: error description, error code (UNAUTHENTICATED without a valid bearer token)
+// Status: 403, Body: error description, error code (FORBIDDEN unless a manager's device, or a waiter's
+//   device that placed the order or serves the section of its table)
 // Status: 404, Body:error description, error code
+// Status: 422, Body: error description, error code (VALIDATION_FAILED if the id or X-Audit-Reason is malformed)
 // Status: 500, Body: error description, error code
+#[utoipa::path(
+    context_path = "/order",
+    tag = "orders",
+    security(("bearer" = [])),
+    params(
+        ("X-Audit-Reason" = Option<String>, Header, description = "Why the order is deleted, recorded in the audit log"),
+    ),
+    responses(
+        (status = 200, description = "The order was deleted"),
+        (status = 401, description = "UNAUTHENTICATED: the bearer token is missing, invalid or expired", body = ErrorResponse),
+        (status = 403, description = "FORBIDDEN: the device's role may not delete orders, or a waiter's device neither placed the order nor serves its table's section", body = ErrorResponse),
+        (status = 404, description = "ORDER_NOT_FOUND", body = ErrorResponse),
+        (status = 422, description = "VALIDATION_FAILED: the id is malformed, the fields array describes each violation", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
 #[delete("/<id>")]
-fn delete_order(
-    id: String,
-    order_service: &State<Arc<dyn OrderService>>,
-) -> Result<Status, (Status, Json<ErrorResponse>)> {
-    let res = order_service.inner().delete_order(id.clone());
-    match res {
-        Ok(_) => Ok(Status::Ok),
-        Err(OrderServiceError::OrderNotFound(_)) => Err((
-            Status::NotFound,
-            Json(ErrorResponse {
-                error: "Order not found".to_string(),
-                error_code: "ORDER_NOT_FOUND".to_string(),
-            }),
-        )),
-        Err(e) => {
-            error!("{}", e);
-            Err((
-                Status::InternalServerError,
-                Json(ErrorResponse {
-                    error: "Internal server error".to_string(),
-                    error_code: "INTERNAL_ERROR".to_string(),
-                }),
-            ))
+async fn delete_order(
+    id: String,
+    order_service: &State<AsyncOrderService>,
+    table_service: &State<AsyncTableService>,
+    id_format: &State<IdFormat>,
+    device: Authorized,
+    auditor: Auditor,
+) -> Result<Status, ApiError> {
+    validate_order_id(&id, *id_format.inner())?;
+    auditor.validate()?;
+
+    let audit = auditor.audit(&device.claims, AuditOperation::Delete);
+    if device.claims.role == Role::Manager {
+        order_service.delete_order(id.clone(), audit).await?;
+    } else {
+        // The section of the order's table is looked up first, so that the tables are not read
+        // while the orders are locked. The table may have been deleted since, in which case the
+        // order has no section.
+        let order = order_service.get_order_by_id(id.clone()).await?;
+        let section = table_service.get_table(order.table_id.clone()).await.ok().map(|table| table.section);
+
+        // The order is then checked and deleted at once, so that it cannot be replaced in between
+        // by an order the device may not delete. An order replaced by one for another table is
+        // not deleted either, as the section looked up is not its section.
+        let claims = device.claims.clone();
+        order_service
+            .delete_order_if(id.clone(), move |current| {
+                current.table_id == order.table_id && claims.may_delete(current, section.as_deref())
+            }, audit)
+            .await
+            .map_err(|e| match e {
+                OrderServiceError::ConditionFailed(_) =>
+                    ApiError::new(ErrorCode::Forbidden, "Waiters may only delete their own orders or their section's"),
+                e => e.into(),
+            })?;
+    }
+    info!("Order {} deleted by device {}", id, device.claims.sub);
+    Ok(Status::Ok)
+}
+
+// POST /order/<id>/start, /ready, /served and /cancel move an order along its lifecycle:
+// placed -> cooking (start) -> ready -> served, with placed or cooking orders being cancellable.
+// Status: 200, Body: details of the updated order
+// Status: 401, Body: error description, error code (UNAUTHENTICATED without a valid bearer token)
+// Status: 403, Body: error description, error code (FORBIDDEN unless a kitchen's or a manager's device)
+// Status: 404, Body: error description, error code
+// Status: 409, Body: error description, error code (in case the order's current status does not
+//   allow the transition, e.g. serving an order that is still cooking)
+// Status: 422, Body: error description, error code (VALIDATION_FAILED if the id or X-Audit-Reason is malformed)
+// Status: 500, Body: error description, error code
+//
+// The routes only differ in the status they move the order to, see transition.
+#[derive(IntoResponses)]
+#[allow(dead_code)] // Never built, it only describes the responses of the routes in the OpenAPI document.
+enum TransitionResponses {
+    #[response(status = 200, description = "The updated order")]
+    Updated(OrderDetails),
+    #[response(status = 401, description = "UNAUTHENTICATED: the bearer token is missing, invalid or expired")]
+    Unauthenticated(ErrorResponse),
+    #[response(status = 403, description = "FORBIDDEN: the device's role may not change the status of orders")]
+    Forbidden(ErrorResponse),
+    #[response(status = 404, description = "ORDER_NOT_FOUND")]
+    NotFound(ErrorResponse),
+    #[response(status = 409, description = "INVALID_TRANSITION: the order's current status does not allow the transition")]
+    InvalidTransition(ErrorResponse),
+    #[response(status = 422, description = "VALIDATION_FAILED: the id or the reason is malformed, the fields array describes each violation")]
+    ValidationFailed(ErrorResponse),
+    #[response(status = 500, description = "INTERNAL_ERROR")]
+    Internal(ErrorResponse),
+}
+
+async fn transition(
+    id: String,
+    status: OrderStatus,
+    operation: AuditOperation,
+    order_service: &AsyncOrderService,
+    id_format: IdFormat,
+    device: Authorized,
+    auditor: Auditor,
+) -> Result<Json<OrderDetails>, ApiError> {
+    validate_order_id(&id, id_format)?;
+    auditor.validate()?;
+    let order = order_service.update_status(id, status, auditor.audit(&device.claims, operation)).await?;
+    Ok(Json(order.into()))
+}
+
+#[utoipa::path(
+    context_path = "/order",
+    tag = "orders",
+    security(("bearer" = [])),
+    params(
+        ("X-Audit-Reason" = Option<String>, Header, description = "Why the order is started, recorded in the audit log"),
+    ),
+    responses(TransitionResponses)
+)]
+#[post("/<id>/start")]
+async fn start_order(
+    id: String,
+    order_service: &State<AsyncOrderService>,
+    id_format: &State<IdFormat>,
+    device: Authorized,
+    auditor: Auditor,
+) -> Result<Json<OrderDetails>, ApiError> {
+    transition(id, OrderStatus::Cooking, AuditOperation::Start, order_service, *id_format.inner(), device, auditor).await
+}
+
+#[utoipa::path(
+    context_path = "/order",
+    tag = "orders",
+    security(("bearer" = [])),
+    params(
+        ("X-Audit-Reason" = Option<String>, Header, description = "Why the order is marked ready, recorded in the audit log"),
+    ),
+    responses(TransitionResponses)
+)]
+#[post("/<id>/ready")]
+async fn ready_order(
+    id: String,
+    order_service: &State<AsyncOrderService>,
+    id_format: &State<IdFormat>,
+    device: Authorized,
+    auditor: Auditor,
+) -> Result<Json<OrderDetails>, ApiError> {
+    transition(id, OrderStatus::Ready, AuditOperation::Ready, order_service, *id_format.inner(), device, auditor).await
+}
+
+#[utoipa::path(
+    context_path = "/order",
+    tag = "orders",
+    security(("bearer" = [])),
+    params(
+        ("X-Audit-Reason" = Option<String>, Header, description = "Why the order is marked served, recorded in the audit log"),
+    ),
+    responses(TransitionResponses)
+)]
+#[post("/<id>/served")]
+async fn serve_order(
+    id: String,
+    order_service: &State<AsyncOrderService>,
+    id_format: &State<IdFormat>,
+    device: Authorized,
+    auditor: Auditor,
+) -> Result<Json<OrderDetails>, ApiError> {
+    transition(id, OrderStatus::Served, AuditOperation::Serve, order_service, *id_format.inner(), device, auditor).await
+}
+
+#[utoipa::path(
+    context_path = "/order",
+    tag = "orders",
+    security(("bearer" = [])),
+    params(
+        ("X-Audit-Reason" = Option<String>, Header, description = "Why the order is cancelled, recorded in the audit log"),
+    ),
+    responses(TransitionResponses)
+)]
+#[post("/<id>/cancel")]
+async fn cancel_order(
+    id: String,
+    order_service: &State<AsyncOrderService>,
+    id_format: &State<IdFormat>,
+    device: Authorized,
+    auditor: Auditor,
+) -> Result<Json<OrderDetails>, ApiError> {
+    transition(id, OrderStatus::Cancelled, AuditOperation::Cancel, order_service, *id_format.inner(), device, auditor).await
+}
+
+// GET /menu?category=... lists menu items, optionally only those of the given category.
+// Items are sorted by category and name. Unavailable items are listed too, so that
+// devices can show them as such.
+// Status: 200, Body: array of menu items
+// Status: 500, Body: error description, error code
+#[utoipa::path(
+    path = "/menu",
+    tag = "menu",
+    params(
+        ("category" = Option<String>, Query, description = "Only items of this category"),
+    ),
+    responses(
+        (status = 200, description = "Menu items sorted by category and name", body = Vec<MenuItem>),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[get("/?<category>")]
+async fn get_menu(
+    category: Option<String>,
+    menu_service: &State<AsyncMenuService>,
+) -> Result<Json<Vec<MenuItem>>, ApiError> {
+    Ok(Json(menu_service.get_items(category).await?))
+}
+
+// GET /menu/<id> returns a single menu item.
+// Status: 200, Body: menu item
+// Status: 404, Body: error description, error code
+// Status: 422, Body: error description, error code (VALIDATION_FAILED if the id is malformed)
+// Status: 500, Body: error description, error code
+#[utoipa::path(
+    context_path = "/menu",
+    tag = "menu",
+    responses(
+        (status = 200, description = "The menu item", body = MenuItem),
+        (status = 404, description = "ITEM_NOT_FOUND", body = ErrorResponse),
+        (status = 422, description = "VALIDATION_FAILED: the id is malformed, the fields array describes each violation", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[get("/<id>")]
+async fn get_menu_item(
+    id: String,
+    menu_service: &State<AsyncMenuService>,
+) -> Result<Json<MenuItem>, ApiError> {
+    validate_id(&id)?;
+
+    Ok(Json(menu_service.get_item(id).await?))
+}
+
+// PUT /menu/<id> creates a menu item or replaces the existing one with the same id.
+// To take an item off the menu temporarily, PUT it with "available": false.
+// Status: 200, Body: the stored menu item
+// Status: 400, Body: error description, error code (in case of malformed request body)
+// Status: 401, Body: error description, error code (UNAUTHENTICATED without a valid bearer token)
+// Status: 403, Body: error description, error code (FORBIDDEN unless a manager's device)
+// Status: 415, Body: error description, error code (UNSUPPORTED_MEDIA_TYPE unless Content-Type is application/json)
+// Status: 422, Body: error description, error code
+//   (VALIDATION_FAILED if the id or a field is malformed, otherwise in case of invalid field values)
+// Status: 500, Body: error description, error code
+#[utoipa::path(
+    context_path = "/menu",
+    tag = "menu",
+    security(("bearer" = [])),
+    request_body = CreateMenuItem,
+    responses(
+        (status = 200, description = "The stored menu item", body = MenuItem),
+        (status = 400, description = "INVALID_BODY: malformed request body", body = ErrorResponse),
+        (status = 401, description = "UNAUTHENTICATED: the bearer token is missing, invalid or expired", body = ErrorResponse),
+        (status = 403, description = "FORBIDDEN: only managers may change the menu", body = ErrorResponse),
+        (status = 415, description = "UNSUPPORTED_MEDIA_TYPE: Content-Type is not application/json", body = ErrorResponse),
+        (status = 422, description = "VALIDATION_FAILED: the id or a field is malformed, the fields array describes each violation; INVALID_MENU_ITEM: invalid field values", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[put("/<id>", format = "json", data = "<item>")]
+async fn put_menu_item(
+    id: String,
+    item: Result<Json<CreateMenuItem>, JsonError<'_>>,
+    menu_service: &State<AsyncMenuService>,
+    _device: Authorized,
+) -> Result<Json<MenuItem>, ApiError> {
+    let item = item.map_err(ApiError::invalid_body)?.into_inner();
+
+    Validator::new()
+        .id("id", &id)
+        .text("name", &item.name, MAX_NAME_LEN)
+        .text("category", &item.category, MAX_NAME_LEN)
+        .finish()?;
+
+    let item = menu_service.put_item(MenuItem {
+        item_id: id,
+        name: item.name,
+        price_cents: item.price_cents,
+        category: item.category,
+        preparation_time: item.preparation_time,
+        available: item.available,
+    }).await?;
+
+    Ok(Json(item))
+}
+
+// DELETE /menu/<id> removes an item from the menu. Existing orders for it are not affected.
+// Status: 200
+// Status: 401, Body: error description, error code (UNAUTHENTICATED without a valid bearer token)
+// Status: 403, Body: error description, error code (FORBIDDEN unless a manager's device)
+// Status: 404, Body: error description, error code
+// Status: 422, Body: error description, error code (VALIDATION_FAILED if the id is malformed)
+// Status: 500, Body: error description, error code
+#[utoipa::path(
+    context_path = "/menu",
+    tag = "menu",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The item was removed from the menu"),
+        (status = 401, description = "UNAUTHENTICATED: the bearer token is missing, invalid or expired", body = ErrorResponse),
+        (status = 403, description = "FORBIDDEN: only managers may change the menu", body = ErrorResponse),
+        (status = 404, description = "ITEM_NOT_FOUND", body = ErrorResponse),
+        (status = 422, description = "VALIDATION_FAILED: the id is malformed, the fields array describes each violation", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[delete("/<id>")]
+async fn delete_menu_item(
+    id: String,
+    menu_service: &State<AsyncMenuService>,
+    _device: Authorized,
+) -> Result<Status, ApiError> {
+    validate_id(&id)?;
+
+    menu_service.delete_item(id).await?;
+    Ok(Status::Ok)
+}
+
+// GET /tables?section=... lists registered tables, optionally only those of the given section.
+// Tables are sorted by section and id.
+// Status: 200, Body: array of tables
+// Status: 500, Body: error description, error code
+#[utoipa::path(
+    path = "/tables",
+    tag = "tables",
+    params(
+        ("section" = Option<String>, Query, description = "Only tables of this section"),
+    ),
+    responses(
+        (status = 200, description = "Tables sorted by section and id", body = Vec<Table>),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[get("/?<section>")]
+async fn get_tables(
+    section: Option<String>,
+    table_service: &State<AsyncTableService>,
+) -> Result<Json<Vec<Table>>, ApiError> {
+    Ok(Json(table_service.get_tables(section).await?))
+}
+
+// GET /tables/<id> returns a single table.
+// Status: 200, Body: table
+// Status: 404, Body: error description, error code
+// Status: 422, Body: error description, error code (VALIDATION_FAILED if the id is malformed)
+// Status: 500, Body: error description, error code
+#[utoipa::path(
+    context_path = "/tables",
+    tag = "tables",
+    responses(
+        (status = 200, description = "The table", body = Table),
+        (status = 404, description = "TABLE_NOT_FOUND", body = ErrorResponse),
+        (status = 422, description = "VALIDATION_FAILED: the id is malformed, the fields array describes each violation", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[get("/<id>")]
+async fn get_table(
+    id: String,
+    table_service: &State<AsyncTableService>,
+) -> Result<Json<Table>, ApiError> {
+    validate_id(&id)?;
+    Ok(Json(table_service.get_table(id).await?))
+}
+
+// PUT /tables/<id> registers a table or replaces the existing one with the same id.
+// Tables are open unless "status": "closed" is given.
+// Status: 200, Body: the stored table
+// Status: 400, Body: error description, error code (in case of malformed request body)
+// Status: 401, Body: error description, error code (UNAUTHENTICATED without a valid bearer token)
+// Status: 403, Body: error description, error code (FORBIDDEN unless a manager's device)
+// Status: 415, Body: error description, error code (UNSUPPORTED_MEDIA_TYPE unless Content-Type is application/json)
+// Status: 422, Body: error description, error code
+//   (VALIDATION_FAILED if the id or a field is malformed, otherwise in case of invalid field values)
+// Status: 500, Body: error description, error code
+#[utoipa::path(
+    context_path = "/tables",
+    tag = "tables",
+    security(("bearer" = [])),
+    request_body = CreateTable,
+    responses(
+        (status = 200, description = "The stored table", body = Table),
+        (status = 400, description = "INVALID_BODY: malformed request body", body = ErrorResponse),
+        (status = 401, description = "UNAUTHENTICATED: the bearer token is missing, invalid or expired", body = ErrorResponse),
+        (status = 403, description = "FORBIDDEN: only managers may change the tables", body = ErrorResponse),
+        (status = 415, description = "UNSUPPORTED_MEDIA_TYPE: Content-Type is not application/json", body = ErrorResponse),
+        (status = 422, description = "VALIDATION_FAILED: the id or a field is malformed, the fields array describes each violation; INVALID_TABLE: invalid field values", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[put("/<id>", format = "json", data = "<table>")]
+async fn put_table(
+    id: String,
+    table: Result<Json<CreateTable>, JsonError<'_>>,
+    table_service: &State<AsyncTableService>,
+    _device: Authorized,
+) -> Result<Json<Table>, ApiError> {
+    let table = table.map_err(ApiError::invalid_body)?.into_inner();
+
+    Validator::new()
+        .id("id", &id)
+        .text("section", &table.section, MAX_NAME_LEN)
+        .finish()?;
+
+    let table = table_service.put_table(Table {
+        table_id: id,
+        capacity: table.capacity,
+        section: table.section,
+        status: table.status,
+    }).await?;
+
+    Ok(Json(table))
+}
+
+// POST /tables/<id>/open and POST /tables/<id>/close change whether orders are accepted
+// for the table. Both are idempotent.
+// Status: 200, Body: the updated table
+// Status: 401, Body: error description, error code (UNAUTHENTICATED without a valid bearer token)
+// Status: 403, Body: error description, error code (FORBIDDEN unless a manager's device)
+// Status: 404, Body: error description, error code
+// Status: 422, Body: error description, error code (VALIDATION_FAILED if the id is malformed)
+// Status: 500, Body: error description, error code
+#[utoipa::path(
+    context_path = "/tables",
+    tag = "tables",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The updated table", body = Table),
+        (status = 401, description = "UNAUTHENTICATED: the bearer token is missing, invalid or expired", body = ErrorResponse),
+        (status = 403, description = "FORBIDDEN: only managers may change the tables", body = ErrorResponse),
+        (status = 404, description = "TABLE_NOT_FOUND", body = ErrorResponse),
+        (status = 422, description = "VALIDATION_FAILED: the id is malformed, the fields array describes each violation", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[post("/<id>/open")]
+async fn open_table(
+    id: String,
+    table_service: &State<AsyncTableService>,
+    _device: Authorized,
+) -> Result<Json<Table>, ApiError> {
+    validate_id(&id)?;
+    Ok(Json(table_service.set_status(id, TableStatus::Open).await?))
+}
+
+#[utoipa::path(
+    context_path = "/tables",
+    tag = "tables",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The updated table", body = Table),
+        (status = 401, description = "UNAUTHENTICATED: the bearer token is missing, invalid or expired", body = ErrorResponse),
+        (status = 403, description = "FORBIDDEN: only managers may change the tables", body = ErrorResponse),
+        (status = 404, description = "TABLE_NOT_FOUND", body = ErrorResponse),
+        (status = 422, description = "VALIDATION_FAILED: the id is malformed, the fields array describes each violation", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[post("/<id>/close")]
+async fn close_table(
+    id: String,
+    table_service: &State<AsyncTableService>,
+    _device: Authorized,
+) -> Result<Json<Table>, ApiError> {
+    validate_id(&id)?;
+    Ok(Json(table_service.set_status(id, TableStatus::Closed).await?))
+}
+
+// DELETE /tables/<id> removes a table from the registry. Existing orders are not affected.
+// Status: 200
+// Status: 401, Body: error description, error code (UNAUTHENTICATED without a valid bearer token)
+// Status: 403, Body: error description, error code (FORBIDDEN unless a manager's device)
+// Status: 404, Body: error description, error code
+// Status: 422, Body: error description, error code (VALIDATION_FAILED if the id is malformed)
+// Status: 500, Body: error description, error code
+#[utoipa::path(
+    context_path = "/tables",
+    tag = "tables",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The table was removed"),
+        (status = 401, description = "UNAUTHENTICATED: the bearer token is missing, invalid or expired", body = ErrorResponse),
+        (status = 403, description = "FORBIDDEN: only managers may change the tables", body = ErrorResponse),
+        (status = 404, description = "TABLE_NOT_FOUND", body = ErrorResponse),
+        (status = 422, description = "VALIDATION_FAILED: the id is malformed, the fields array describes each violation", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[delete("/<id>")]
+async fn delete_table(
+    id: String,
+    table_service: &State<AsyncTableService>,
+    _device: Authorized,
+) -> Result<Status, ApiError> {
+    validate_id(&id)?;
+    table_service.delete_table(id).await?;
+    Ok(Status::Ok)
+}
+
+// GET /webhooks?event=... lists registered webhooks, optionally only those subscribed to the
+// given event (order_created, order_ready or order_deleted). Webhooks are sorted by id and
+// listed without their secrets.
+// Status: 200, Body: array of webhooks
+// Status: 400, Body: error description, error code (INVALID_EVENT in case of unknown event)
+// Status: 401, Body: error description, error code (UNAUTHENTICATED without a valid bearer token)
+// Status: 403, Body: error description, error code (FORBIDDEN unless a manager's device)
+// Status: 500, Body: error description, error code
+#[utoipa::path(
+    path = "/webhooks",
+    tag = "webhooks",
+    security(("bearer" = [])),
+    params(
+        ("event" = Option<WebhookEvent>, Query, description = "Only webhooks subscribed to this event"),
+    ),
+    responses(
+        (status = 200, description = "Webhooks sorted by id, without their secrets", body = Vec<Webhook>),
+        (status = 400, description = "INVALID_EVENT: unknown event", body = ErrorResponse),
+        (status = 401, description = "UNAUTHENTICATED: the bearer token is missing, invalid or expired", body = ErrorResponse),
+        (status = 403, description = "FORBIDDEN: only managers may manage webhooks", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[get("/?<event>")]
+fn get_webhooks(
+    event: Option<&str>,
+    webhook_service: &State<Arc<dyn WebhookService>>,
+    _device: Authorized,
+) -> Result<Json<Vec<Webhook>>, ApiError> {
+    let event = match event.map(WebhookEvent::parse) {
+        None => None,
+        Some(Some(event)) => Some(event),
+        Some(None) => return Err(ApiError::new(ErrorCode::InvalidEvent, "Unknown webhook event")),
+    };
+
+    Ok(Json(webhook_service.get_webhooks(event)?))
+}
+
+// GET /webhooks/<id> returns a single webhook, without its secret.
+// Status: 200, Body: webhook
+// Status: 401, Body: error description, error code (UNAUTHENTICATED without a valid bearer token)
+// Status: 403, Body: error description, error code (FORBIDDEN unless a manager's device)
+// Status: 404, Body: error description, error code
+// Status: 422, Body: error description, error code (VALIDATION_FAILED if the id is malformed)
+// Status: 500, Body: error description, error code
+#[utoipa::path(
+    context_path = "/webhooks",
+    tag = "webhooks",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The webhook, without its secret", body = Webhook),
+        (status = 401, description = "UNAUTHENTICATED: the bearer token is missing, invalid or expired", body = ErrorResponse),
+        (status = 403, description = "FORBIDDEN: only managers may manage webhooks", body = ErrorResponse),
+        (status = 404, description = "WEBHOOK_NOT_FOUND", body = ErrorResponse),
+        (status = 422, description = "VALIDATION_FAILED: the id is malformed, the fields array describes each violation", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[get("/<id>")]
+fn get_webhook(
+    id: String,
+    webhook_service: &State<Arc<dyn WebhookService>>,
+    _device: Authorized,
+) -> Result<Json<Webhook>, ApiError> {
+    validate_id(&id)?;
+    Ok(Json(webhook_service.get_webhook(&id)?))
+}
+
+// PUT /webhooks/<id> subscribes the url to the given order events, or replaces the existing
+// webhook with the same id:
+// order_created: an order was accepted
+// order_ready: an order is ready to be served
+// order_deleted: an order was deleted
+// Every event is POSTed to the url as JSON with delivery_id, webhook_id, event and order.
+// The X-Webhook-Signature header carries "sha256=" and the hex encoded HMAC-SHA256 of the body,
+// keyed with the secret. Failed deliveries are retried with exponential backoff.
+// Status: 200, Body: the stored webhook, without its secret
+// Status: 400, Body: error description, error code (in case of malformed request body)
+// Status: 401, Body: error description, error code (UNAUTHENTICATED without a valid bearer token)
+// Status: 403, Body: error description, error code (FORBIDDEN unless a manager's device)
+// Status: 415, Body: error description, error code (UNSUPPORTED_MEDIA_TYPE unless Content-Type is application/json)
+// Status: 422, Body: error description, error code
+//   (VALIDATION_FAILED if the id or a field is malformed, otherwise in case of invalid field values)
+// Status: 500, Body: error description, error code
+#[utoipa::path(
+    context_path = "/webhooks",
+    tag = "webhooks",
+    security(("bearer" = [])),
+    request_body = CreateWebhook,
+    responses(
+        (status = 200, description = "The stored webhook, without its secret", body = Webhook),
+        (status = 400, description = "INVALID_BODY: malformed request body", body = ErrorResponse),
+        (status = 401, description = "UNAUTHENTICATED: the bearer token is missing, invalid or expired", body = ErrorResponse),
+        (status = 403, description = "FORBIDDEN: only managers may manage webhooks", body = ErrorResponse),
+        (status = 415, description = "UNSUPPORTED_MEDIA_TYPE: Content-Type is not application/json", body = ErrorResponse),
+        (status = 422, description = "VALIDATION_FAILED: the id or a field is malformed, the fields array describes each violation; INVALID_WEBHOOK: invalid field values", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[put("/<id>", format = "json", data = "<webhook>")]
+fn put_webhook(
+    id: String,
+    webhook: Result<Json<CreateWebhook>, JsonError<'_>>,
+    webhook_service: &State<Arc<dyn WebhookService>>,
+    _device: Authorized,
+) -> Result<Json<Webhook>, ApiError> {
+    let webhook = webhook.map_err(ApiError::invalid_body)?.into_inner();
+
+    Validator::new()
+        .id("id", &id)
+        .text("url", &webhook.url, MAX_URL_LEN)
+        .text("secret", &webhook.secret, MAX_SECRET_LEN)
+        .finish()?;
+
+    let webhook = webhook_service.put_webhook(Webhook {
+        webhook_id: id,
+        url: webhook.url,
+        events: webhook.events,
+        secret: webhook.secret,
+    })?;
+
+    Ok(Json(webhook))
+}
+
+// DELETE /webhooks/<id> unsubscribes a webhook. Pending deliveries to it are given up.
+// Status: 200
+// Status: 401, Body: error description, error code (UNAUTHENTICATED without a valid bearer token)
+// Status: 403, Body: error description, error code (FORBIDDEN unless a manager's device)
+// Status: 404, Body: error description, error code
+// Status: 422, Body: error description, error code (VALIDATION_FAILED if the id is malformed)
+// Status: 500, Body: error description, error code
+#[utoipa::path(
+    context_path = "/webhooks",
+    tag = "webhooks",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The webhook was removed"),
+        (status = 401, description = "UNAUTHENTICATED: the bearer token is missing, invalid or expired", body = ErrorResponse),
+        (status = 403, description = "FORBIDDEN: only managers may manage webhooks", body = ErrorResponse),
+        (status = 404, description = "WEBHOOK_NOT_FOUND", body = ErrorResponse),
+        (status = 422, description = "VALIDATION_FAILED: the id is malformed, the fields array describes each violation", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[delete("/<id>")]
+fn delete_webhook(
+    id: String,
+    webhook_service: &State<Arc<dyn WebhookService>>,
+    _device: Authorized,
+) -> Result<Status, ApiError> {
+    validate_id(&id)?;
+    webhook_service.delete_webhook(id)?;
+    Ok(Status::Ok)
+}
+
+// GET /webhooks/deliveries?webhook_id=...&status=... lists the latest deliveries, newest first,
+// optionally only those of the given webhook or with the given status (pending, delivered or
+// failed). A delivery records the number of attempts made, the response status and error of
+// the last attempt, and when the next attempt is due if it is still pending.
+// Status: 200, Body: array of deliveries
+// Status: 400, Body: error description, error code (INVALID_STATUS in case of unknown status)
+// Status: 401, Body: error description, error code (UNAUTHENTICATED without a valid bearer token)
+// Status: 403, Body: error description, error code (FORBIDDEN unless a manager's device)
+// Status: 500, Body: error description, error code
+#[utoipa::path(
+    context_path = "/webhooks",
+    tag = "webhooks",
+    security(("bearer" = [])),
+    params(
+        ("webhook_id" = Option<String>, Query, description = "Only deliveries to this webhook"),
+        ("status" = Option<DeliveryStatus>, Query, description = "Only deliveries with this status"),
+    ),
+    responses(
+        (status = 200, description = "The latest deliveries, newest first", body = Vec<Delivery>),
+        (status = 400, description = "INVALID_STATUS: unknown status", body = ErrorResponse),
+        (status = 401, description = "UNAUTHENTICATED: the bearer token is missing, invalid or expired", body = ErrorResponse),
+        (status = 403, description = "FORBIDDEN: only managers may manage webhooks", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[get("/deliveries?<webhook_id>&<status>")]
+fn get_webhook_deliveries(
+    webhook_id: Option<String>,
+    status: Option<&str>,
+    webhook_service: &State<Arc<dyn WebhookService>>,
+    _device: Authorized,
+) -> Result<Json<Vec<Delivery>>, ApiError> {
+    let status = match status.map(DeliveryStatus::parse) {
+        None => None,
+        Some(Some(status)) => Some(status),
+        Some(None) => return Err(ApiError::new(ErrorCode::InvalidStatus, "Unknown delivery status")),
+    };
+
+    Ok(Json(webhook_service.get_deliveries(webhook_id, status)?))
+}
+
+// AuditRecord is an entry of the audit log as the API returns it, with the order before and
+// after the mutation in the same form as the order routes return orders.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct AuditRecord {
+    seq: u64,
+    timestamp: DateTime<Utc>,
+    // The device that made the change, the subject of its bearer token.
+    device: String,
+    role: Role,
+    operation: AuditOperation,
+    order_id: String,
+    before: Option<OrderDetails>,
+    after: Option<OrderDetails>,
+    reason: Option<String>,
+    correlation_id: String,
+}
+
+impl From<AuditEntry> for AuditRecord {
+    fn from(entry: AuditEntry) -> AuditRecord {
+        AuditRecord {
+            seq: entry.seq,
+            timestamp: entry.timestamp,
+            device: entry.device,
+            role: entry.role,
+            operation: entry.operation,
+            order_id: entry.order_id,
+            before: entry.before.map(OrderDetails::from),
+            after: entry.after.map(OrderDetails::from),
+            reason: entry.reason,
+            correlation_id: entry.correlation_id,
+        }
+    }
+}
+
+// GET /audit?order_id=...&device=...&operation=...&since=...&until=... lists the audit log, the
+// record of every change made to orders, in the order the changes were made. Every filter is
+// optional: order_id and device keep only the changes of the given order or made by the given
+// device, operation (create_order, delete_order, start_order, ready_order, serve_order or
+// cancel_order) only the changes of that kind, since/until (RFC 3339 timestamps) only the changes
+// made at or after since and before until.
+// Every record carries the device that made the change and its role, the order before and after
+// it, the reason given in X-Audit-Reason, if any, and the correlation id of the request.
+// GET /audit/export takes the same filters and returns the records as JSON Lines, one per line.
+// Only managers may read the audit log.
+// Status: 200, Body: array of audit records
+// Status: 400, Body: error description, error code
+//   (INVALID_OPERATION in case of unknown operation, INVALID_TIMESTAMP in case of malformed since or until)
+// Status: 401, Body: error description, error code (UNAUTHENTICATED without a valid bearer token)
+// Status: 403, Body: error description, error code (FORBIDDEN unless a manager's device)
+// Status: 500, Body: error description, error code
+#[derive(FromForm, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct AuditParams<'r> {
+    order_id: Option<String>,
+    device: Option<String>,
+    #[param(value_type = Option<AuditOperation>)]
+    operation: Option<&'r str>,
+    #[param(value_type = Option<DateTime<Utc>>)]
+    since: Option<&'r str>,
+    #[param(value_type = Option<DateTime<Utc>>)]
+    until: Option<&'r str>,
+}
+
+fn parse_audit_query(params: AuditParams<'_>) -> Result<AuditQuery, ApiError> {
+    let operation = match params.operation.map(AuditOperation::parse) {
+        None => None,
+        Some(Some(operation)) => Some(operation),
+        Some(None) => return Err(ApiError::new(ErrorCode::InvalidOperation, "Unknown audit operation")),
+    };
+
+    let (since, until) = match (parse_timestamp(params.since), parse_timestamp(params.until)) {
+        (Ok(since), Ok(until)) => (since, until),
+        _ => return Err(ApiError::new(ErrorCode::InvalidTimestamp, "Timestamps must be in RFC 3339 format")),
+    };
+
+    Ok(AuditQuery {
+        order_id: params.order_id,
+        device: params.device,
+        operation,
+        since,
+        until,
+    })
+}
+
+#[utoipa::path(
+    path = "/audit",
+    tag = "audit",
+    security(("bearer" = [])),
+    params(AuditParams),
+    responses(
+        (status = 200, description = "The audit records, oldest first", body = Vec<AuditRecord>),
+        (status = 400, description = "INVALID_OPERATION: unknown operation; INVALID_TIMESTAMP: since or until is not an RFC 3339 timestamp", body = ErrorResponse),
+        (status = 401, description = "UNAUTHENTICATED: the bearer token is missing, invalid or expired", body = ErrorResponse),
+        (status = 403, description = "FORBIDDEN: only managers may read the audit log", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[get("/?<params..>")]
+fn get_audit(
+    params: AuditParams<'_>,
+    audit_log: &State<Arc<dyn AuditLog>>,
+    _device: Authorized,
+) -> Result<Json<Vec<AuditRecord>>, ApiError> {
+    let query = parse_audit_query(params)?;
+    let entries = audit_log.get_entries(&query)?;
+    Ok(Json(entries.into_iter().map(AuditRecord::from).collect()))
+}
+
+#[utoipa::path(
+    context_path = "/audit",
+    tag = "audit",
+    security(("bearer" = [])),
+    params(AuditParams),
+    responses(
+        (status = 200, description = "The audit records, oldest first, one JSON object per line", content_type = "application/x-ndjson"),
+        (status = 400, description = "INVALID_OPERATION: unknown operation; INVALID_TIMESTAMP: since or until is not an RFC 3339 timestamp", body = ErrorResponse),
+        (status = 401, description = "UNAUTHENTICATED: the bearer token is missing, invalid or expired", body = ErrorResponse),
+        (status = 403, description = "FORBIDDEN: only managers may read the audit log", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[get("/export?<params..>")]
+fn export_audit(
+    params: AuditParams<'_>,
+    audit_log: &State<Arc<dyn AuditLog>>,
+    _device: Authorized,
+) -> Result<(ContentType, String), ApiError> {
+    let query = parse_audit_query(params)?;
+    let mut lines = String::new();
+    for entry in audit_log.get_entries(&query)? {
+        let line = serde_json::to_string(&AuditRecord::from(entry)).map_err(|e| ApiError::Internal(e.to_string()))?;
+        lines.push_str(&line);
+        lines.push('\n');
+    }
+    Ok((ContentType::new("application", "x-ndjson"), lines))
+}
+
+// POST /auth/token enrolls a staff device: given the enrollment key of the role (waiter, kitchen or
+// manager, see policy::POLICY for what each may do), it issues a bearer token for the device,
+// valid for APP_TOKEN_TTL_HOURS (12 by default). Waiters may name the section they serve.
+// Every request to /order and /orders must carry it in an "Authorization: Bearer <token>" header.
+// A device whose token has expired enrolls again.
+// e.g. {"device_id": "watch-17", "role": "waiter", "section": "terrace", "enrollment_key": "..."}
+// Status: 200, Body: the token and its expiry
+// Status: 400, Body: error description, error code (in case of malformed request body)
+// Status: 401, Body: error description, error code (UNAUTHENTICATED if the enrollment key is wrong)
+// Status: 415, Body: error description, error code (UNSUPPORTED_MEDIA_TYPE unless Content-Type is application/json)
+// Status: 422, Body: error description, error code (VALIDATION_FAILED if the device id or the section is malformed)
+// Status: 500, Body: error description, error code
+#[utoipa::path(
+    context_path = "/auth",
+    tag = "auth",
+    request_body = EnrollDevice,
+    responses(
+        (status = 200, description = "The bearer token of the device", body = IssuedToken),
+        (status = 400, description = "INVALID_BODY: malformed request body", body = ErrorResponse),
+        (status = 401, description = "UNAUTHENTICATED: the enrollment key is wrong", body = ErrorResponse),
+        (status = 415, description = "UNSUPPORTED_MEDIA_TYPE: Content-Type is not application/json", body = ErrorResponse),
+        (status = 422, description = "VALIDATION_FAILED: the device id or the section is malformed, the fields array describes each violation", body = ErrorResponse),
+        (status = 500, description = "INTERNAL_ERROR", body = ErrorResponse),
+    )
+)]
+#[post("/token", format = "json", data = "<enrollment>")]
+fn issue_token(
+    enrollment: Result<Json<EnrollDevice>, JsonError<'_>>,
+    auth: &State<Auth>,
+) -> Result<Json<IssuedToken>, ApiError> {
+    let enrollment = enrollment.map_err(ApiError::invalid_body)?.into_inner();
+
+    let mut validator = Validator::new();
+    validator.id("device_id", &enrollment.device_id);
+    if let Some(section) = &enrollment.section {
+        validator.text("section", section, MAX_NAME_LEN);
+    }
+    validator.finish()?;
+
+    let token = auth.enroll(&enrollment.device_id, enrollment.role, enrollment.section, &enrollment.enrollment_key)?;
+    info!("Enrolled device {} as {}", enrollment.device_id, enrollment.role.as_str());
+    Ok(Json(token))
+}
+
+// Catchers give the errors Rocket responds with on its own, before or instead of a handler,
+// the same ErrorResponse body as the handlers' errors. The error code is derived from the status,
+// e.g. UNPROCESSABLE_ENTITY for a request Rocket failed to parse.
+//
+// Rocket responds with 404 to a request no route matches. If a route matches its path, though,
+// the method or the content type was wrong, which is reported as such:
+// Status: 404, Body: error description, error code (NOT_FOUND)
+// Status: 405, Body: error description, error code (METHOD_NOT_ALLOWED), Allow: the methods of the path
+// Status: 415, Body: error description, error code (UNSUPPORTED_MEDIA_TYPE)
+#[catch(404)]
+fn not_found(req: &Request<'_>) -> ApiError {
+    let routes: Vec<&Route> = req.rocket().routes().filter(|route| path_matches(route, req)).collect();
+    if routes.is_empty() {
+        return ApiError::new(ErrorCode::NotFound, "Not found");
+    }
+
+    let same_method: Vec<&Route> = routes.iter().copied().filter(|route| route.method == req.method()).collect();
+    if same_method.is_empty() {
+        let mut methods: Vec<String> = routes.iter().map(|route| route.method.as_str().to_string()).collect();
+        methods.sort();
+        methods.dedup();
+        return ApiError::MethodNotAllowed(methods);
+    }
+
+    let content_type = req.content_type().map(|content_type| content_type.media_type());
+    if let Some(format) = same_method.iter().find_map(|route| route.format.as_ref().filter(|format| Some(*format) != content_type)) {
+        return ApiError::new(ErrorCode::UnsupportedMediaType, format!("Content-Type must be {}", format));
+    }
+
+    ApiError::new(ErrorCode::NotFound, "Not found")
+}
+
+// Status: 401, Body: error description, error code (UNAUTHENTICATED), WWW-Authenticate: Bearer
+#[catch(401)]
+fn unauthenticated(req: &Request<'_>) -> ApiError {
+    ApiError::new(ErrorCode::Unauthenticated, auth::failure(req))
+}
+
+// Status: 403, Body: error description, error code (FORBIDDEN)
+#[catch(403)]
+fn forbidden(req: &Request<'_>) -> ApiError {
+    ApiError::new(ErrorCode::Forbidden, auth::failure(req))
+}
+
+// Status: 500, Body: error description, error code (INTERNAL_ERROR)
+#[catch(500)]
+fn internal_error() -> ApiError {
+    ApiError::Status(Status::InternalServerError)
+}
+
+#[catch(default)]
+fn default_catcher(status: Status, _req: &Request<'_>) -> ApiError {
+    ApiError::Status(status)
+}
+
+// Whether the route's path matches the request's, regardless of the method, query and format.
+fn path_matches(route: &Route, req: &Request<'_>) -> bool {
+    let mut segments = req.uri().path().segments();
+    for route_segment in route.uri.path().split('/').filter(|segment| !segment.is_empty()) {
+        if route_segment.starts_with('<') && route_segment.ends_with("..>") {
+            return true;
+        }
+        match segments.next() {
+            Some(segment) if route_segment.starts_with('<') || route_segment == segment => {}
+            _ => return false,
         }
     }
+    segments.next().is_none()
+}
+
+// ApiDoc is the OpenAPI 3 description of the API, generated from the routes' utoipa::path
+// attributes and the request and response types. Every route has to be listed here.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Restaurant API",
+        description = "Handling orders in an imaginary restaurant chain",
+        license(name = "MIT"),
+    ),
+    paths(
+        put_order, get_order_by_id, delete_order, start_order, ready_order, serve_order, cancel_order,
+        get_order, stream_order_events, get_order_changes,
+        get_menu, get_menu_item, put_menu_item, delete_menu_item,
+        get_tables, get_table, put_table, open_table, close_table, delete_table,
+        get_webhooks, get_webhook, put_webhook, delete_webhook, get_webhook_deliveries,
+        get_audit, export_audit,
+        issue_token, openapi, get_errors,
+    ),
+    components(schemas(ErrorResponse)),
+    modifiers(&BearerAuth),
+    tags(
+        (name = "orders", description = "Orders placed by the waitstaff and their progress in the kitchen"),
+        (name = "menu", description = "Items that can be ordered"),
+        (name = "tables", description = "Tables orders can be placed for"),
+        (name = "webhooks", description = "Notifications of order events to other systems"),
+        (name = "audit", description = "Record of the changes made to orders"),
+        (name = "auth", description = "Enrollment of staff devices"),
+    ),
+)]
+struct ApiDoc;
+
+// BearerAuth adds the "bearer" security scheme the order routes require.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()),
+        );
+    }
+}
+
+// GET /openapi.json returns the OpenAPI 3 document describing every route of the API, with its
+// parameters, request body and responses, including the error codes returned with each status.
+// Status: 200, Body: the OpenAPI document
+#[utoipa::path(
+    responses(
+        (status = 200, description = "The OpenAPI document of this API", content_type = "application/json"),
+    )
+)]
+#[get("/openapi.json")]
+fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+// GET /errors lists every error code the API responds with, along with its status and meaning.
+// Every error body carries one of these codes, except for statuses the API never responds with
+// on its own, whose code is derived from the status, e.g. IM_A_TEAPOT.
+// Status: 200, Body: array of error codes
+#[utoipa::path(
+    responses(
+        (status = 200, description = "Every error code, sorted by status", body = Vec<ErrorCodeInfo>),
+    )
+)]
+#[get("/errors")]
+fn get_errors() -> Json<Vec<ErrorCodeInfo>> {
+    Json(api_error::catalog())
 }
 
 #[launch]
@@ -220,18 +1745,258 @@ fn create_rocket() -> rocket::Rocket<Build> {
         .merge(("port", port))
         .merge(("workers", workers));
 
-    // Using rocket's Request Guard we could implement an authentication middleware which
-    // would be applied to all requests. Such middleware could verify Authorization header with
-    // access tokens send by restaurant's staff devices and "bounce back" requests without
-    // valid authentication, possibly prompting them to obtain a valid token.
-    //
-    // In a similar manner an authorization middleware could be implemented. Based on access token
-    // contents and request's params (path, method, params) such middleware could either allow
-    // or forbid the request.
+    let events_history: usize = env::var("APP_EVENTS_HISTORY")
+        .unwrap_or_else(|_| DEFAULT_HISTORY.to_string())
+        .parse()
+        .expect("APP_EVENTS_HISTORY must be a valid usize integer");
+
+    let menu_service = create_menu_service();
+    let estimator = create_estimator(menu_service.clone());
+    let events = Arc::new(OrderEvents::new(events_history));
+    let order_service = AsyncOrderService::new(create_order_service(estimator, events.clone()));
+    let table_service = create_table_service();
+    let webhook_service: Arc<dyn WebhookService> = Arc::new(webhook_service::new_in_memory());
+    let dispatcher = Arc::new(WebhookDispatcher::new(webhook_service.clone(), create_retry_policy()));
+    // Subscribed right away, so that the events of requests served before the dispatcher's task
+    // first runs are delivered as well.
+    let subscription = events.subscribe(None);
+
+    // Every route under /order, /orders, /webhooks and /audit, as well as every route changing the
+    // menu or the tables, takes the Authorized request guard. It verifies the bearer token in the
+    // Authorization header and "bounces back" requests without a valid one with 401. Devices
+    // obtain a token at POST /auth/token. Based on the role in the token and the request's route
+    // and method, it then allows the request or forbids it with 403, see policy::POLICY.
     rocket::custom(figment)
-        .manage(Arc::new(order_service::new_in_memory()) as Arc<dyn OrderService>)
-        .mount("/order", routes![put_order, delete_order])
-        .mount("/orders", routes![get_order])
+        .manage(order_service)
+        .manage(AsyncMenuService::new(menu_service))
+        .manage(AsyncTableService::new(table_service))
+        .manage(events.clone())
+        .manage(webhook_service)
+        .manage(create_audit_log())
+        .manage(create_id_format())
+        .manage(create_auth())
+        .attach(AdHoc::on_liftoff("Webhook dispatcher", move |rocket| Box::pin(async move {
+            rocket::tokio::spawn(dispatcher.run(events, subscription, rocket.shutdown()));
+        })))
+        .register("/", catchers![unauthenticated, forbidden, not_found, internal_error, default_catcher])
+        .mount("/", routes![openapi, get_errors])
+        .mount("/order", routes![put_order, get_order_by_id, delete_order, start_order, ready_order, serve_order, cancel_order])
+        .mount("/orders", routes![get_order, stream_order_events, get_order_changes])
+        .mount("/menu", routes![get_menu, get_menu_item, put_menu_item, delete_menu_item])
+        .mount("/tables", routes![get_tables, get_table, put_table, open_table, close_table, delete_table])
+        .mount("/webhooks", routes![get_webhooks, get_webhook, put_webhook, delete_webhook, get_webhook_deliveries])
+        .mount("/audit", routes![get_audit, export_audit])
+        .mount("/auth", routes![issue_token])
+}
+
+// APP_STORAGE selects the OrderService implementation:
+// memory (default): orders are kept in memory; if APP_JOURNAL_DIR is set, every mutation is
+//   journaled there and replayed on startup, compacted every APP_JOURNAL_COMPACT_EVERY mutations
+// sqlite: orders are persisted in the SQLite database file given by APP_SQLITE_PATH
+fn create_order_service(estimator: Arc<dyn CookingTimeEstimator>, events: Arc<OrderEvents>) -> Arc<dyn OrderService> {
+    let storage = env::var("APP_STORAGE").unwrap_or_else(|_| "memory".to_string());
+
+    match storage.as_str() {
+        "memory" => match env::var("APP_JOURNAL_DIR") {
+            Ok(dir) => {
+                let compact_every: usize = env::var("APP_JOURNAL_COMPACT_EVERY")
+                    .unwrap_or_else(|_| "1000".to_string())
+                    .parse()
+                    .expect("APP_JOURNAL_COMPACT_EVERY must be a valid usize integer");
+                let service = order_service::new_in_memory_with_journal(&dir, compact_every)
+                    .unwrap_or_else(|e| panic!("Failed to recover journal from '{}': {}", dir, e));
+                Arc::new(service.with_estimator(estimator).with_events(events))
+            }
+            Err(_) => Arc::new(order_service::new_in_memory().with_estimator(estimator).with_events(events)),
+        },
+        "sqlite" => {
+            let path = env::var("APP_SQLITE_PATH").unwrap_or_else(|_| "orders.db".to_string());
+            let service = sqlite_order_service::new_sqlite(&path)
+                .unwrap_or_else(|e| panic!("Failed to open SQLite database '{}': {}", path, e));
+            Arc::new(service.with_estimator(estimator).with_events(events))
+        }
+        other => panic!("APP_STORAGE must be either 'memory' or 'sqlite', got '{}'", other),
+    }
+}
+
+// APP_COOKING_TIME_ESTIMATOR selects how cooking times are assigned to new orders:
+// kitchen_load (default): the item's preparation time, plus APP_KITCHEN_MINUTES_PER_BATCH (5)
+//   for every APP_KITCHEN_CAPACITY (10) orders the kitchen is already working on
+// random: a random time between 5 and 15 minutes
+fn create_estimator(menu_service: Arc<dyn MenuService>) -> Arc<dyn CookingTimeEstimator> {
+    let estimator = env::var("APP_COOKING_TIME_ESTIMATOR").unwrap_or_else(|_| "kitchen_load".to_string());
+
+    match estimator.as_str() {
+        "kitchen_load" => {
+            let capacity: usize = env::var("APP_KITCHEN_CAPACITY")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .expect("APP_KITCHEN_CAPACITY must be a valid usize integer");
+            let minutes_per_batch: i32 = env::var("APP_KITCHEN_MINUTES_PER_BATCH")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .expect("APP_KITCHEN_MINUTES_PER_BATCH must be a valid i32 integer");
+            Arc::new(KitchenLoadEstimator::new(menu_service, capacity, minutes_per_batch))
+        }
+        "random" => Arc::new(RandomEstimator),
+        other => panic!("APP_COOKING_TIME_ESTIMATOR must be either 'kitchen_load' or 'random', got '{}'", other),
+    }
+}
+
+// Webhook deliveries are attempted up to APP_WEBHOOK_MAX_ATTEMPTS (5) times. The wait after the
+// first failed attempt is APP_WEBHOOK_BACKOFF_MS (1000) milliseconds and doubles after every
+// further one.
+fn create_retry_policy() -> RetryPolicy {
+    let max_attempts: u32 = env::var("APP_WEBHOOK_MAX_ATTEMPTS")
+        .unwrap_or_else(|_| "5".to_string())
+        .parse()
+        .expect("APP_WEBHOOK_MAX_ATTEMPTS must be a valid u32 integer");
+    let backoff_ms: u64 = env::var("APP_WEBHOOK_BACKOFF_MS")
+        .unwrap_or_else(|_| "1000".to_string())
+        .parse()
+        .expect("APP_WEBHOOK_BACKOFF_MS must be a valid u64 integer");
+
+    RetryPolicy { max_attempts: max_attempts.max(1), initial_backoff: Duration::from_millis(backoff_ms) }
+}
+
+// APP_ORDER_ID_FORMAT selects the format order ids must have, either opaque (default) or uuid4.
+fn create_id_format() -> IdFormat {
+    let format = env::var("APP_ORDER_ID_FORMAT").unwrap_or_else(|_| "opaque".to_string());
+    IdFormat::parse(&format)
+        .unwrap_or_else(|| panic!("APP_ORDER_ID_FORMAT must be either 'opaque' or 'uuid4', got '{}'", format))
+}
+
+// Bearer tokens are signed with APP_JWT_SECRET, at least MIN_SECRET_LEN bytes long, and valid for
+// APP_TOKEN_TTL_HOURS (12 by default). Devices enroll with the key of their role:
+// APP_WAITER_ENROLLMENT_KEY, APP_KITCHEN_ENROLLMENT_KEY or APP_MANAGER_ENROLLMENT_KEY. Without a
+// secret, a random one is generated, so tokens do not survive a restart; without an enrollment key,
+// a random one is generated and written to APP_ENROLLMENT_KEYS_FILE, see write_enrollment_keys.
+fn create_auth() -> Auth {
+    let secret = match env::var("APP_JWT_SECRET") {
+        Ok(secret) if secret.len() < MIN_SECRET_LEN =>
+            panic!("APP_JWT_SECRET must be at least {} bytes long, got {}", MIN_SECRET_LEN, secret.len()),
+        Ok(secret) => secret.into_bytes(),
+        Err(_) => {
+            warn!("APP_JWT_SECRET is not set, tokens will be invalidated by a restart");
+            rand::random::<[u8; 32]>().to_vec()
+        }
+    };
+    let mut generated = Vec::new();
+    let enrollment_keys = Role::ALL
+        .into_iter()
+        .map(|role| {
+            let var = format!("APP_{}_ENROLLMENT_KEY", role.as_str().to_uppercase());
+            let key = env::var(&var).unwrap_or_else(|_| {
+                let key = hex::encode(rand::random::<[u8; 16]>());
+                generated.push((role, key.clone()));
+                key
+            });
+            (role, key)
+        })
+        .collect();
+    if !generated.is_empty() {
+        let path = env::var("APP_ENROLLMENT_KEYS_FILE").unwrap_or_else(|_| "enrollment_keys.txt".to_string());
+        write_enrollment_keys(&path, &generated)
+            .unwrap_or_else(|e| panic!("Failed to write enrollment keys to '{}': {}", path, e));
+        let roles: Vec<&str> = generated.iter().map(|(role, _)| role.as_str()).collect();
+        warn!("No enrollment key is set for {}, random ones were written to '{}'", roles.join(", "), path);
+    }
+    let ttl_hours: u64 = env::var("APP_TOKEN_TTL_HOURS")
+        .unwrap_or_else(|_| "12".to_string())
+        .parse()
+        .expect("APP_TOKEN_TTL_HOURS must be a valid u64 integer");
+
+    Auth::new(&secret, enrollment_keys, Duration::from_secs(ttl_hours * 60 * 60))
+}
+
+// Writes generated enrollment keys to a file only its owner may read, one "role key" per line,
+// so that they end up in neither the application log nor the output of the process. The keys of
+// a previous start are replaced.
+fn write_enrollment_keys(path: &str, keys: &[(Role, String)]) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    // The mode only applies to a new file.
+    #[cfg(unix)]
+    file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+
+    let contents: String = keys.iter().map(|(role, key)| format!("{} {}\n", role.as_str(), key)).collect();
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()
+}
+
+// The menu is kept the same way as the orders, see create_order_service: in memory, in the
+// journal directory or in the SQLite database file. If APP_MENU_FILE is set, the items of that
+// file, which must contain a JSON array of menu items, are put on the menu on startup.
+fn create_menu_service() -> Arc<dyn MenuService> {
+    let menu_service: Arc<dyn MenuService> = match env::var("APP_STORAGE").as_deref() {
+        Ok("sqlite") => {
+            let path = env::var("APP_SQLITE_PATH").unwrap_or_else(|_| "orders.db".to_string());
+            Arc::new(sqlite_menu_service::new_sqlite(&path)
+                .unwrap_or_else(|e| panic!("Failed to open SQLite database '{}': {}", path, e)))
+        }
+        _ => match env::var("APP_JOURNAL_DIR") {
+            Ok(dir) => Arc::new(menu_service::new_in_memory_with_journal(&dir)
+                .unwrap_or_else(|e| panic!("Failed to recover menu from '{}': {}", dir, e))),
+            Err(_) => Arc::new(menu_service::new_in_memory()),
+        },
+    };
+
+    if let Ok(path) = env::var("APP_MENU_FILE") {
+        let contents = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Failed to read menu file '{}': {}", path, e));
+        let items: Vec<MenuItem> = rocket::serde::json::from_str(&contents)
+            .unwrap_or_else(|e| panic!("Failed to parse menu file '{}': {}", path, e));
+        for item in items {
+            menu_service.put_item(item)
+                .unwrap_or_else(|e| panic!("Failed to load menu file '{}': {}", path, e));
+        }
+    }
+
+    menu_service
+}
+
+// Tables are kept the same way as the orders, see create_order_service: in memory, in the
+// journal directory or in the SQLite database file. If APP_TABLES_FILE is set, the tables of that
+// file, which must contain a JSON array of tables, are put on startup.
+fn create_table_service() -> Arc<dyn TableService> {
+    let table_service: Arc<dyn TableService> = match env::var("APP_STORAGE").as_deref() {
+        Ok("sqlite") => {
+            let path = env::var("APP_SQLITE_PATH").unwrap_or_else(|_| "orders.db".to_string());
+            Arc::new(sqlite_table_service::new_sqlite(&path)
+                .unwrap_or_else(|e| panic!("Failed to open SQLite database '{}': {}", path, e)))
+        }
+        _ => match env::var("APP_JOURNAL_DIR") {
+            Ok(dir) => Arc::new(table_service::new_in_memory_with_journal(&dir)
+                .unwrap_or_else(|e| panic!("Failed to recover tables from '{}': {}", dir, e))),
+            Err(_) => Arc::new(table_service::new_in_memory()),
+        },
+    };
+
+    if let Ok(path) = env::var("APP_TABLES_FILE") {
+        let contents = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Failed to read tables file '{}': {}", path, e));
+        let tables: Vec<Table> = rocket::serde::json::from_str(&contents)
+            .unwrap_or_else(|e| panic!("Failed to parse tables file '{}': {}", path, e));
+        for table in tables {
+            table_service.put_table(table)
+                .unwrap_or_else(|e| panic!("Failed to load tables file '{}': {}", path, e));
+        }
+    }
+
+    table_service
+}
+
+// The audit log is kept in memory. If APP_AUDIT_FILE is set, every entry is also appended to
+// that file as a JSON line, and the entries already in it are loaded on startup.
+fn create_audit_log() -> Arc<dyn AuditLog> {
+    match env::var("APP_AUDIT_FILE") {
+        Ok(path) => Arc::new(audit_log::open(&path)
+            .unwrap_or_else(|e| panic!("Failed to open audit log '{}': {}", path, e))),
+        Err(_) => Arc::new(audit_log::new_in_memory()),
+    }
 }
 
 fn setup_logger() -> Result<(), fern::InitError> {
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use rocket::serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::journal;
+
+// The file the menu is kept in within the journal directory.
+const MENU_FILE: &str = "menu.json";
+
+// MenuItem is a dish (or drink) that can be ordered.
+// price_cents is the price in the smallest currency unit, to avoid rounding issues.
+// preparation_time is the base time in minutes it takes the kitchen to prepare the item.
+// Items that are temporarily unavailable (e.g. sold out) are kept with available = false.
+#[derive(Serialize, Clone, Deserialize, Debug, PartialEq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct MenuItem {
+    pub item_id: String,
+    pub name: String,
+    pub price_cents: u32,
+    pub category: String,
+    pub preparation_time: i32,
+    pub available: bool,
+}
+
+#[derive(Debug)]
+pub enum MenuServiceError {
+    ItemNotFound(String),
+    InvalidItem(String),
+    MutexPoisoned(String),
+    Storage(String),
+    TaskFailed(String),
+}
+
+impl fmt::Display for MenuServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MenuServiceError::ItemNotFound(id) => write!(f, "Menu item with id '{}' not found.", id),
+            MenuServiceError::InvalidItem(msg) => write!(f, "Invalid menu item: {}", msg),
+            MenuServiceError::MutexPoisoned(msg) => write!(f, "Mutex poisoned: {}", msg),
+            MenuServiceError::Storage(msg) => write!(f, "Storage error: {}", msg),
+            MenuServiceError::TaskFailed(msg) => write!(f, "Background task failed: {}", msg),
+        }
+    }
+}
+
+impl Error for MenuServiceError {}
+
+// MenuService manages the catalog of items that can be ordered.
+// put_item creates a new item or replaces an existing one with the same id.
+pub trait MenuService: Sync + Send {
+    fn put_item(&self, item: MenuItem) -> Result<MenuItem, MenuServiceError>;
+    fn get_item(&self, item_id: &str) -> Result<MenuItem, MenuServiceError>;
+    fn get_items(&self, category: Option<String>) -> Result<Vec<MenuItem>, MenuServiceError>;
+    fn delete_item(&self, item_id: String) -> Result<MenuItem, MenuServiceError>;
+
+    // Whether calls may block the current thread, see OrderService::is_blocking.
+    fn is_blocking(&self) -> bool {
+        true
+    }
+}
+
+// InMemoryMenuService keeps the menu in a HashMap wrapped in RwLock for thread safety.
+// The menu is small and changes rarely, so there is no need for indexes.
+// With a journal directory, every change rewrites the menu file there before it is applied, so
+// that the menu survives a restart along with the journaled orders.
+pub struct InMemoryMenuService {
+    items: RwLock<HashMap<String, MenuItem>>,
+    dir: Option<PathBuf>,
+}
+
+pub fn new_in_memory() -> InMemoryMenuService {
+    InMemoryMenuService {
+        items: RwLock::new(HashMap::new()),
+        dir: None,
+    }
+}
+
+// Creates an InMemoryMenuService keeping the menu in `dir`, with the items recovered from it.
+pub fn new_in_memory_with_journal(dir: impl AsRef<Path>) -> Result<InMemoryMenuService, MenuServiceError> {
+    let dir = dir.as_ref().to_path_buf();
+    std::fs::create_dir_all(&dir).map_err(storage_error)?;
+    let items: Vec<MenuItem> = journal::read_registry(&dir, MENU_FILE).map_err(storage_error)?;
+
+    Ok(InMemoryMenuService {
+        items: RwLock::new(items.into_iter().map(|item| (item.item_id.clone(), item)).collect()),
+        dir: Some(dir),
+    })
+}
+
+fn storage_error(e: std::io::Error) -> MenuServiceError {
+    MenuServiceError::Storage(format!("menu I/O failed: {}", e))
+}
+
+impl InMemoryMenuService {
+    // Writes the menu as it is going to be after a change, before the change is applied.
+    fn persist(&self, items: &HashMap<String, MenuItem>) -> Result<(), MenuServiceError> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        let mut items: Vec<&MenuItem> = items.values().collect();
+        items.sort_by(|a, b| a.item_id.cmp(&b.item_id));
+        journal::write_registry(dir, MENU_FILE, &items).map_err(storage_error)
+    }
+}
+
+pub fn validate(item: &MenuItem) -> Result<(), MenuServiceError> {
+    if item.item_id.trim().is_empty() {
+        return Err(MenuServiceError::InvalidItem("item_id must not be empty".into()));
+    }
+    if item.name.trim().is_empty() {
+        return Err(MenuServiceError::InvalidItem("name must not be empty".into()));
+    }
+    if item.category.trim().is_empty() {
+        return Err(MenuServiceError::InvalidItem("category must not be empty".into()));
+    }
+    if item.preparation_time <= 0 {
+        return Err(MenuServiceError::InvalidItem("preparation_time must be positive".into()));
+    }
+    Ok(())
+}
+
+impl MenuService for InMemoryMenuService {
+    fn put_item(&self, item: MenuItem) -> Result<MenuItem, MenuServiceError> {
+        validate(&item)?;
+
+        let mut items = self.items.write()
+            .map_err(|_| MenuServiceError::MutexPoisoned("Failed to obtain write mutex".into()))?;
+        let mut updated = items.clone();
+        updated.insert(item.item_id.clone(), item.clone());
+        self.persist(&updated)?;
+        *items = updated;
+
+        Ok(item)
+    }
+
+    fn get_item(&self, item_id: &str) -> Result<MenuItem, MenuServiceError> {
+        let items = self.items.read()
+            .map_err(|_| MenuServiceError::MutexPoisoned("Failed to obtain read mutex".into()))?;
+
+        items.get(item_id)
+            .cloned()
+            .ok_or_else(|| MenuServiceError::ItemNotFound(item_id.to_string()))
+    }
+
+    fn get_items(&self, category: Option<String>) -> Result<Vec<MenuItem>, MenuServiceError> {
+        let items = self.items.read()
+            .map_err(|_| MenuServiceError::MutexPoisoned("Failed to obtain read mutex".into()))?;
+
+        let mut result: Vec<MenuItem> = items
+            .values()
+            .filter(|item| category.as_ref().is_none_or(|category| &item.category == category))
+            .cloned()
+            .collect();
+        result.sort_by(|a, b| a.category.cmp(&b.category).then_with(|| a.name.cmp(&b.name)));
+
+        Ok(result)
+    }
+
+    fn delete_item(&self, item_id: String) -> Result<MenuItem, MenuServiceError> {
+        let mut items = self.items.write()
+            .map_err(|_| MenuServiceError::MutexPoisoned("Failed to obtain write mutex".into()))?;
+
+        let mut updated = items.clone();
+        let item = updated.remove(&item_id)
+            .ok_or(MenuServiceError::ItemNotFound(item_id))?;
+        self.persist(&updated)?;
+        *items = updated;
+
+        Ok(item)
+    }
+
+    // Only changes to a menu kept in a journal directory write to disk.
+    fn is_blocking(&self) -> bool {
+        self.dir.is_some()
+    }
+}
@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+    use crate::menu_service::{new_in_memory, new_in_memory_with_journal, InMemoryMenuService, MenuItem, MenuService, MenuServiceError};
+
+    fn setup_service() -> InMemoryMenuService {
+        new_in_memory()
+    }
+
+    fn menu_item(item_id: &str, name: &str, category: &str) -> MenuItem {
+        MenuItem {
+            item_id: item_id.to_string(),
+            name: name.to_string(),
+            price_cents: 1250,
+            category: category.to_string(),
+            preparation_time: 10,
+            available: true,
+        }
+    }
+
+    #[test]
+    fn test_put_and_get_item() {
+        let service = setup_service();
+        let item = menu_item("burger", "Burger", "mains");
+
+        assert_eq!(service.put_item(item.clone()).unwrap(), item);
+        assert_eq!(service.get_item("burger").unwrap(), item);
+    }
+
+    #[test]
+    fn test_put_item_replaces_existing() {
+        let service = setup_service();
+        service.put_item(menu_item("burger", "Burger", "mains")).unwrap();
+
+        let mut updated = menu_item("burger", "Cheeseburger", "mains");
+        updated.available = false;
+        service.put_item(updated.clone()).unwrap();
+
+        assert_eq!(service.get_item("burger").unwrap(), updated);
+        assert_eq!(service.get_items(None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_put_invalid_item_fails() {
+        let service = setup_service();
+
+        let mut item = menu_item("burger", "", "mains");
+        assert!(matches!(service.put_item(item.clone()), Err(MenuServiceError::InvalidItem(_))));
+
+        item.name = "Burger".to_string();
+        item.preparation_time = 0;
+        assert!(matches!(service.put_item(item), Err(MenuServiceError::InvalidItem(_))));
+
+        assert!(service.get_items(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_get_item_not_found() {
+        let service = setup_service();
+
+        if let Err(MenuServiceError::ItemNotFound(item_id)) = service.get_item("burger") {
+            assert_eq!(item_id, "burger");
+        } else {
+            panic!("Expected ItemNotFound error.");
+        }
+    }
+
+    #[test]
+    fn test_get_items_by_category() {
+        let service = setup_service();
+        service.put_item(menu_item("soup", "Soup", "starters")).unwrap();
+        service.put_item(menu_item("steak", "Steak", "mains")).unwrap();
+        service.put_item(menu_item("burger", "Burger", "mains")).unwrap();
+
+        let all: Vec<String> = service.get_items(None).unwrap().into_iter().map(|i| i.item_id).collect();
+        assert_eq!(all, vec!["burger", "steak", "soup"]);
+
+        let mains: Vec<String> = service.get_items(Some("mains".to_string())).unwrap().into_iter().map(|i| i.item_id).collect();
+        assert_eq!(mains, vec!["burger", "steak"]);
+    }
+
+    #[test]
+    fn test_delete_item() {
+        let service = setup_service();
+        service.put_item(menu_item("burger", "Burger", "mains")).unwrap();
+
+        assert_eq!(service.delete_item("burger".to_string()).unwrap().item_id, "burger");
+        assert!(matches!(service.get_item("burger"), Err(MenuServiceError::ItemNotFound(_))));
+        assert!(matches!(service.delete_item("burger".to_string()), Err(MenuServiceError::ItemNotFound(_))));
+    }
+
+    #[test]
+    fn test_menu_is_recovered_from_journal_dir() {
+        let dir = std::env::temp_dir().join(format!("restaurant_api_menu_{}", Uuid::new_v4()));
+        {
+            let service = new_in_memory_with_journal(&dir).unwrap();
+            service.put_item(menu_item("burger", "Burger", "mains")).unwrap();
+            service.put_item(menu_item("soup", "Soup", "starters")).unwrap();
+            service.delete_item("soup".to_string()).unwrap();
+        }
+
+        let service = new_in_memory_with_journal(&dir).unwrap();
+        assert_eq!(service.get_item("burger").unwrap(), menu_item("burger", "Burger", "mains"));
+        assert!(matches!(service.get_item("soup"), Err(MenuServiceError::ItemNotFound(_))));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
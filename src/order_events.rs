@@ -0,0 +1,157 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use rand::Rng;
+use rocket::serde::Serialize;
+use rocket::tokio::sync::broadcast;
+use crate::order_service::OrderResult;
+
+// Events kept for clients that reconnect, unless APP_EVENTS_HISTORY says otherwise.
+pub const DEFAULT_HISTORY: usize = 1000;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum OrderEventKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl OrderEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderEventKind::Created => "created",
+            OrderEventKind::Updated => "updated",
+            OrderEventKind::Deleted => "deleted",
+        }
+    }
+}
+
+// OrderEvent describes a mutation of an order, carrying the order as it was right after it
+// (or right before it, for a deleted order). Ids start at 1 and grow by one with every event
+// published by this process, see EventId for the ids clients get.
+#[derive(Clone, Debug)]
+pub struct OrderEvent {
+    pub id: u64,
+    pub kind: OrderEventKind,
+    pub order: OrderResult,
+}
+
+// EventId is the id of an event as clients see it: the epoch of the process that published it,
+// drawn at random on every start, and the event's id, e.g. 2718281828-17. Ids restart at 1 with
+// the process, so without the epoch a client could not tell the events it missed apart from ones
+// it has seen before the restart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EventId {
+    pub epoch: u32,
+    pub id: u64,
+}
+
+impl fmt::Display for EventId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.epoch, self.id)
+    }
+}
+
+impl FromStr for EventId {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<EventId, ()> {
+        match s.split_once('-') {
+            Some((epoch, id)) => Ok(EventId {
+                epoch: epoch.parse().map_err(|_| ())?,
+                id: id.parse().map_err(|_| ())?,
+            }),
+            // Ids handed out before they had an epoch. Epoch 0 is never drawn, so these always
+            // require a resync.
+            None => Ok(EventId { epoch: 0, id: s.parse().map_err(|_| ())? }),
+        }
+    }
+}
+
+// Subscription hands out the events published after the last event the client has seen, and
+// a receiver of the events published from now on. Together they miss or repeat no event.
+pub struct Subscription {
+    // None if some of the missed events are no longer kept, so the client has to refetch orders.
+    pub missed: Option<Vec<OrderEvent>>,
+    // The id of the last event published before subscribing, 0 if there was none.
+    pub last_id: u64,
+    pub receiver: broadcast::Receiver<OrderEvent>,
+}
+
+struct History {
+    next_id: u64,
+    events: VecDeque<OrderEvent>,
+}
+
+// OrderEvents fans out order mutations to the subscribed clients, e.g. kitchen screens, and
+// keeps the latest `capacity` events around for clients that got disconnected.
+//
+// Order services publish while still holding their own lock, so events come out in the order
+// the mutations were applied in. Only mutations made by this process are published.
+pub struct OrderEvents {
+    epoch: u32,
+    history: Mutex<History>,
+    capacity: usize,
+    sender: broadcast::Sender<OrderEvent>,
+}
+
+impl OrderEvents {
+    pub fn new(capacity: usize) -> OrderEvents {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        OrderEvents {
+            epoch: rand::thread_rng().gen_range(1..=u32::MAX),
+            history: Mutex::new(History { next_id: 1, events: VecDeque::new() }),
+            capacity,
+            sender,
+        }
+    }
+
+    pub fn epoch(&self) -> u32 {
+        self.epoch
+    }
+
+    pub fn publish(&self, kind: OrderEventKind, order: &OrderResult) {
+        // The mutation has already been applied at this point, so failing it is not an option.
+        // The history is consistent even if a thread panicked while holding the lock.
+        let mut history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        let event = OrderEvent { id: history.next_id, kind, order: order.clone() };
+        history.next_id += 1;
+
+        if self.capacity > 0 {
+            if history.events.len() == self.capacity {
+                history.events.pop_front();
+            }
+            history.events.push_back(event.clone());
+        }
+        // Sending only fails if nobody is subscribed.
+        let _ = self.sender.send(event);
+    }
+
+    // Subscribes a client that has seen events up to `last_event_id`, or a new client that does
+    // not need any past events if it is None.
+    pub fn subscribe(&self, last_event_id: Option<u64>) -> Subscription {
+        let history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        let last_id = history.next_id - 1;
+        let oldest_kept = history.events.front().map_or(history.next_id, |event| event.id);
+
+        let missed = match last_event_id {
+            None => Some(Vec::new()),
+            // Ids that were never handed out, or evicted events.
+            Some(id) if id > last_id || id + 1 < oldest_kept => None,
+            Some(id) => Some(history.events.iter().filter(|event| event.id > id).cloned().collect()),
+        };
+
+        Subscription { missed, last_id, receiver: self.sender.subscribe() }
+    }
+
+    // Subscribes an SSE client that reconnects with the id of the last event it has seen. An id
+    // handed out before the server restarted requires a resync, as the events after it are lost.
+    pub fn resume(&self, last_event_id: Option<EventId>) -> Subscription {
+        match last_event_id {
+            Some(last) if last.epoch != self.epoch => Subscription { missed: None, ..self.subscribe(None) },
+            last => self.subscribe(last.map(|last| last.id)),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::order_events::{EventId, OrderEvent, OrderEventKind, OrderEvents};
+    use crate::order_service::{new_in_memory, Order, OrderResult, OrderService, OrderStatus};
+    use crate::order_service_tests::OrderServiceExt;
+    use crate::sqlite_order_service::new_sqlite;
+
+    fn order(order_id: &str) -> OrderResult {
+        OrderResult::placed(order_id.to_string(), Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        }, 10)
+    }
+
+    fn publish_orders(events: &OrderEvents, count: usize) {
+        for i in 1..=count {
+            events.publish(OrderEventKind::Created, &order(&format!("order{}", i)));
+        }
+    }
+
+    fn ids(missed: Option<Vec<OrderEvent>>) -> Option<Vec<u64>> {
+        missed.map(|missed| missed.into_iter().map(|event| event.id).collect())
+    }
+
+    #[test]
+    fn test_subscribe_replays_missed_events() {
+        let events = OrderEvents::new(10);
+        publish_orders(&events, 3);
+
+        let subscription = events.subscribe(Some(1));
+        assert_eq!(subscription.last_id, 3);
+        assert_eq!(ids(subscription.missed), Some(vec![2, 3]));
+
+        assert_eq!(ids(events.subscribe(Some(3)).missed), Some(vec![]));
+        assert_eq!(ids(events.subscribe(Some(0)).missed), Some(vec![1, 2, 3]));
+        assert_eq!(ids(events.subscribe(None).missed), Some(vec![]));
+    }
+
+    #[test]
+    fn test_subscribe_requires_resync() {
+        let events = OrderEvents::new(3);
+        publish_orders(&events, 5);
+
+        // Events 1 and 2 are evicted, so only a client that saw event 2 can catch up.
+        assert_eq!(ids(events.subscribe(Some(2)).missed), Some(vec![3, 4, 5]));
+        assert_eq!(ids(events.subscribe(Some(1)).missed), None);
+        // An id the server never handed out, e.g. from before a restart.
+        assert_eq!(ids(events.subscribe(Some(6)).missed), None);
+
+        let events = OrderEvents::new(0);
+        publish_orders(&events, 1);
+        assert_eq!(ids(events.subscribe(Some(1)).missed), Some(vec![]));
+        assert_eq!(ids(events.subscribe(Some(0)).missed), None);
+    }
+
+    #[test]
+    fn test_resume_requires_resync_after_restart() {
+        let before = OrderEvents::new(10);
+        publish_orders(&before, 2);
+        let last_seen = EventId { epoch: before.epoch(), id: 1 };
+        assert_eq!(ids(before.resume(Some(last_seen)).missed), Some(vec![2]));
+
+        // The restarted server hands out the same ids again, for other events.
+        let after = OrderEvents::new(10);
+        publish_orders(&after, 3);
+        assert_ne!(after.epoch(), before.epoch());
+        assert_eq!(ids(after.resume(Some(last_seen)).missed), None);
+        assert_eq!(ids(after.resume(Some(EventId { epoch: 0, id: 1 })).missed), None);
+        assert_eq!(ids(after.resume(Some(EventId { epoch: after.epoch(), id: 1 })).missed), Some(vec![2, 3]));
+        assert_eq!(ids(after.resume(None).missed), Some(vec![]));
+    }
+
+    #[test]
+    fn test_event_id_format() {
+        let id = EventId { epoch: 2718281828, id: 17 };
+        assert_eq!(id.to_string(), "2718281828-17");
+        assert_eq!("2718281828-17".parse(), Ok(id));
+        assert_eq!("17".parse(), Ok(EventId { epoch: 0, id: 17 }));
+        assert!("latest".parse::<EventId>().is_err());
+        assert!("-17".parse::<EventId>().is_err());
+        assert!("2718281828-".parse::<EventId>().is_err());
+    }
+
+    #[test]
+    fn test_subscription_receives_new_events() {
+        let events = OrderEvents::new(10);
+        publish_orders(&events, 1);
+
+        let mut subscription = events.subscribe(Some(0));
+        events.publish(OrderEventKind::Deleted, &order("order1"));
+
+        assert_eq!(ids(subscription.missed), Some(vec![1]));
+        let event = subscription.receiver.try_recv().unwrap();
+        assert_eq!(event.id, 2);
+        assert_eq!(event.kind, OrderEventKind::Deleted);
+        assert_eq!(event.order.order_id, "order1");
+        assert!(subscription.receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_services_publish_mutations() {
+        let events = Arc::new(OrderEvents::new(10));
+        let backends: Vec<Arc<dyn OrderService>> = vec![
+            Arc::new(new_in_memory().with_events(events.clone())),
+            Arc::new(new_sqlite(":memory:").unwrap().with_events(events.clone())),
+        ];
+
+        for service in backends {
+            let mut subscription = events.subscribe(None);
+            let order = Order {
+                item_id: "item1".to_string(),
+                table_id: "table1".to_string(),
+                ..Default::default()
+            };
+
+            service.put_order("order1".to_string(), order.clone()).unwrap();
+            // Neither a retry nor a rejected mutation is an event.
+            service.put_order("order1".to_string(), order).unwrap();
+            service.update_status("order1".to_string(), OrderStatus::Served).unwrap_err();
+            service.update_status("order1".to_string(), OrderStatus::Cooking).unwrap();
+            service.delete_order("order1".to_string()).unwrap();
+            service.delete_order("order1".to_string()).unwrap_err();
+
+            let mut received = Vec::new();
+            while let Ok(event) = subscription.receiver.try_recv() {
+                received.push((event.kind, event.order.status));
+            }
+            assert_eq!(received, vec![
+                (OrderEventKind::Created, OrderStatus::Placed),
+                (OrderEventKind::Updated, OrderStatus::Cooking),
+                (OrderEventKind::Deleted, OrderStatus::Cooking),
+            ]);
+        }
+    }
+}
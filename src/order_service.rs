@@ -1,17 +1,208 @@
-use std::sync::RwLock;
-use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ops::Bound;
 use rocket::serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use std::fmt;
 use std::error::Error;
-use rand::Rng;
+use chrono::{DateTime, Duration, DurationRound, SecondsFormat, Utc};
+use crate::cooking_time::{CookingTimeEstimator, RandomEstimator};
+use crate::journal::{Journal, JournalEntry, Recovered};
+use crate::order_events::{OrderEventKind, OrderEvents};
+use crate::pagination::{paginate, OrderPage, PageRequest, SortDirection, SortKey};
 
+// Bounds on the customizations accepted with a single order.
+pub const MAX_QUANTITY: u32 = 20;
+pub const MAX_MODIFIERS: usize = 10;
+pub const MAX_INGREDIENT_LEN: usize = 50;
+pub const MAX_NOTES_LEN: usize = 200;
+
+// Deleted order ids kept for the change feed. Deletions beyond that are compacted away, and
+// clients that have not seen them have to resync.
+pub const MAX_TOMBSTONES: usize = 10_000;
+
+// Order is a request for `quantity` portions of a menu item, optionally customized with
+// modifiers and free-text notes for the kitchen.
 #[derive(Serialize, Clone, Deserialize, Debug)]
 #[serde(crate = "rocket::serde")]
 pub struct Order {
     pub item_id: String,
     pub table_id: String,
+    #[serde(default = "default_quantity")]
+    pub quantity: u32,
+    #[serde(default)]
+    pub modifiers: Vec<Modifier>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    // The device that placed the order, set by the API from its token.
+    #[serde(default)]
+    pub placed_by: Option<String>,
+}
+
+impl Default for Order {
+    fn default() -> Order {
+        Order {
+            item_id: String::new(),
+            table_id: String::new(),
+            quantity: default_quantity(),
+            modifiers: Vec::new(),
+            notes: None,
+            placed_by: None,
+        }
+    }
+}
+
+fn default_quantity() -> u32 {
+    1
+}
+
+// Modifier is a change to the menu item as it is listed: an extra ingredient, an ingredient
+// left out, or how the item should be cooked.
+//
+// {"type": "add", "ingredient": "bacon"}
+// {"type": "remove", "ingredient": "onion"}
+// {"type": "doneness", "level": "medium_rare"}
+#[derive(Serialize, Clone, Deserialize, Debug, PartialEq, Eq, ToSchema)]
+#[serde(crate = "rocket::serde", tag = "type", rename_all = "snake_case")]
+pub enum Modifier {
+    Add { ingredient: String },
+    Remove { ingredient: String },
+    Doneness { level: Doneness },
+}
+
+#[derive(Serialize, Clone, Copy, Deserialize, Debug, PartialEq, Eq, ToSchema)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum Doneness {
+    Rare,
+    MediumRare,
+    Medium,
+    MediumWell,
+    WellDone,
+}
+
+// Resolves a PUT of `order` for an id that is already taken by `existing`.
+pub fn retried(existing: OrderResult, order: &Order) -> Result<PutOutcome, OrderServiceError> {
+    let fields = existing.conflicting_fields(order);
+    if fields.is_empty() {
+        Ok(PutOutcome::Retried(existing))
+    } else {
+        Err(OrderServiceError::DuplicateOrder { order_id: existing.order_id, fields })
+    }
+}
+
+// Checks an order's quantity, modifiers and notes against the bounds above. Whether the item
+// and the table exist is checked by the API handlers.
+pub fn validate(order: &Order) -> Result<(), OrderServiceError> {
+    if order.quantity == 0 || order.quantity > MAX_QUANTITY {
+        return Err(OrderServiceError::InvalidOrder(format!("quantity must be between 1 and {}", MAX_QUANTITY)));
+    }
+    if order.modifiers.len() > MAX_MODIFIERS {
+        return Err(OrderServiceError::InvalidOrder(format!("at most {} modifiers are allowed", MAX_MODIFIERS)));
+    }
+
+    let mut added = HashSet::new();
+    let mut removed = HashSet::new();
+    let mut doneness = 0;
+    for modifier in &order.modifiers {
+        let ingredient = match modifier {
+            Modifier::Add { ingredient } | Modifier::Remove { ingredient } => ingredient.trim(),
+            Modifier::Doneness { .. } => {
+                doneness += 1;
+                continue;
+            }
+        };
+        if ingredient.is_empty() || ingredient.chars().count() > MAX_INGREDIENT_LEN {
+            return Err(OrderServiceError::InvalidOrder(
+                format!("ingredient must be between 1 and {} characters", MAX_INGREDIENT_LEN)));
+        }
+        let ingredient = ingredient.to_lowercase();
+        let conflicting = match modifier {
+            Modifier::Add { .. } => removed.contains(&ingredient) || !added.insert(ingredient),
+            _ => added.contains(&ingredient) || !removed.insert(ingredient),
+        };
+        if conflicting {
+            return Err(OrderServiceError::InvalidOrder("each ingredient may be modified only once".into()));
+        }
+    }
+    if doneness > 1 {
+        return Err(OrderServiceError::InvalidOrder("at most one doneness modifier is allowed".into()));
+    }
+
+    if let Some(notes) = &order.notes {
+        if notes.chars().count() > MAX_NOTES_LEN {
+            return Err(OrderServiceError::InvalidOrder(format!("notes must be at most {} characters", MAX_NOTES_LEN)));
+        }
+    }
+    Ok(())
+}
+
+// OrderStatus is the stage of an order's lifecycle:
+//
+// placed -> cooking -> ready -> served
+//    \         \
+//     +---------+--> cancelled
+//
+// served and cancelled are final.
+#[derive(Serialize, Clone, Copy, Deserialize, Debug, Default, PartialEq, Eq, Hash, ToSchema)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum OrderStatus {
+    #[default]
+    Placed,
+    Cooking,
+    Ready,
+    Served,
+    Cancelled,
+}
+
+impl OrderStatus {
+    pub const ALL: [OrderStatus; 5] = [
+        OrderStatus::Placed,
+        OrderStatus::Cooking,
+        OrderStatus::Ready,
+        OrderStatus::Served,
+        OrderStatus::Cancelled,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderStatus::Placed => "placed",
+            OrderStatus::Cooking => "cooking",
+            OrderStatus::Ready => "ready",
+            OrderStatus::Served => "served",
+            OrderStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<OrderStatus> {
+        OrderStatus::ALL.into_iter().find(|status| status.as_str() == s)
+    }
+
+    pub fn can_transition_to(&self, next: OrderStatus) -> bool {
+        matches!(
+            (self, next),
+            (OrderStatus::Placed, OrderStatus::Cooking)
+                | (OrderStatus::Cooking, OrderStatus::Ready)
+                | (OrderStatus::Ready, OrderStatus::Served)
+                | (OrderStatus::Placed, OrderStatus::Cancelled)
+                | (OrderStatus::Cooking, OrderStatus::Cancelled)
+        )
+    }
+
+    // Whether the kitchen still has to work on the order.
+    pub fn is_in_progress(&self) -> bool {
+        matches!(self, OrderStatus::Placed | OrderStatus::Cooking)
+    }
 }
 
+impl fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Timestamps are in UTC and serialized as RFC 3339. estimated_ready_at is fixed when the order
+// is accepted: created_at plus cooking_time minutes.
 #[derive(Serialize, Clone, Deserialize, Debug)]
 #[serde(crate = "rocket::serde")]
 pub struct OrderResult {
@@ -19,133 +210,814 @@ pub struct OrderResult {
     pub item_id: String,
     pub table_id: String,
     pub cooking_time: i32,
+    #[serde(default = "default_quantity")]
+    pub quantity: u32,
+    #[serde(default)]
+    pub modifiers: Vec<Modifier>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub placed_by: Option<String>,
+    #[serde(default)]
+    pub status: OrderStatus,
+    #[serde(default)]
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub estimated_ready_at: DateTime<Utc>,
+    // The sequence number of the last mutation of the order, see get_changes.
+    #[serde(default)]
+    pub seq: u64,
+}
+
+impl OrderResult {
+    // Builds a newly accepted order, created now.
+    pub fn placed(order_id: String, order: Order, cooking_time: i32) -> OrderResult {
+        let created_at = now();
+        OrderResult {
+            order_id,
+            item_id: order.item_id,
+            table_id: order.table_id,
+            cooking_time,
+            quantity: order.quantity,
+            modifiers: order.modifiers,
+            notes: order.notes,
+            placed_by: order.placed_by,
+            status: OrderStatus::Placed,
+            created_at,
+            updated_at: created_at,
+            estimated_ready_at: created_at + Duration::minutes(cooking_time.into()),
+            seq: 0,
+        }
+    }
+}
+
+impl OrderResult {
+    // Names of the fields in which the order differs from the one this order was accepted for.
+    // An empty list means that a PUT of `order` is a retry of the request that created it.
+    pub fn conflicting_fields(&self, order: &Order) -> Vec<String> {
+        let mut fields = Vec::new();
+        if self.item_id != order.item_id {
+            fields.push("item_id".to_string());
+        }
+        if self.table_id != order.table_id {
+            fields.push("table_id".to_string());
+        }
+        if self.quantity != order.quantity {
+            fields.push("quantity".to_string());
+        }
+        if self.modifiers != order.modifiers {
+            fields.push("modifiers".to_string());
+        }
+        if self.notes != order.notes {
+            fields.push("notes".to_string());
+        }
+        fields
+    }
+}
+
+// PutOutcome tells a newly accepted order from one that had already been accepted for an
+// identical request, i.e. the PUT was a retry.
+#[derive(Debug)]
+pub enum PutOutcome {
+    Created(OrderResult),
+    Retried(OrderResult),
+}
+
+impl PutOutcome {
+    pub fn into_order(self) -> OrderResult {
+        match self {
+            PutOutcome::Created(order) | PutOutcome::Retried(order) => order,
+        }
+    }
+
+    pub fn is_created(&self) -> bool {
+        matches!(self, PutOutcome::Created(_))
+    }
+}
+
+// Tombstone records the deletion of an order for the change feed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "rocket::serde")]
+pub struct Tombstone {
+    pub order_id: String,
+    pub seq: u64,
+}
+
+#[derive(Debug)]
+pub enum Change {
+    // The order was created or updated, it holds the order's current state.
+    Put(OrderResult),
+    Deleted(Tombstone),
+}
+
+impl Change {
+    pub fn seq(&self) -> u64 {
+        match self {
+            Change::Put(order) => order.seq,
+            Change::Deleted(tombstone) => tombstone.seq,
+        }
+    }
+}
+
+// Changes lists the orders' changes since a sequence number, ordered by their sequence numbers.
+// An order appears with its latest change only, except that an order deleted and then created
+// again under the same id appears with both. last_seq is the sequence number of the latest
+// mutation, to ask for the changes since next time.
+#[derive(Debug)]
+pub struct Changes {
+    pub changes: Vec<Change>,
+    pub last_seq: u64,
+}
+
+// Current time, truncated to whole microseconds so that timestamps survive a round trip
+// through every storage backend unchanged.
+pub fn now() -> DateTime<Utc> {
+    let now = Utc::now();
+    now.duration_trunc(Duration::microseconds(1)).unwrap_or(now)
+}
+
+// RFC 3339 with a fixed number of fractional digits and a 'Z' suffix, so that formatted
+// timestamps sort as strings in chronological order.
+pub fn format_timestamp(ts: &DateTime<Utc>) -> String {
+    ts.to_rfc3339_opts(SecondsFormat::Micros, true)
 }
 
 #[derive(Debug)]
 pub enum OrderServiceError {
-    DuplicateOrder(String),
+    // An order with the same id but different contents exists; fields names the differences.
+    DuplicateOrder { order_id: String, fields: Vec<String> },
     OrderNotFound(String),
+    InvalidOrder(String),
+    InvalidTransition { order_id: String, from: OrderStatus, to: OrderStatus },
+    // The order does not meet the condition of delete_order_audited.
+    ConditionFailed(String),
+    // The change could not be recorded in the audit log, so it was not made.
+    AuditFailed(String),
     MutexPoisoned(String),
+    Storage(String),
+    TaskFailed(String),
+    // The changes since the given sequence number are no longer known.
+    ChangesCompacted(u64),
 }
 
 impl fmt::Display for OrderServiceError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            OrderServiceError::DuplicateOrder(id) => write!(f, "Order with id '{}' already exists.", id),
+            OrderServiceError::DuplicateOrder { order_id, fields } if fields.is_empty() =>
+                write!(f, "Order with id '{}' already exists.", order_id),
+            OrderServiceError::DuplicateOrder { order_id, fields } =>
+                write!(f, "Order with id '{}' already exists with a different {}.", order_id, fields.join(", ")),
             OrderServiceError::OrderNotFound(id) => write!(f, "Order with id '{}' not found.", id),
+            OrderServiceError::InvalidOrder(msg) => write!(f, "Invalid order: {}", msg),
+            OrderServiceError::InvalidTransition { order_id, from, to } =>
+                write!(f, "Order with id '{}' cannot change from '{}' to '{}'.", order_id, from, to),
+            OrderServiceError::ConditionFailed(order_id) =>
+                write!(f, "Order with id '{}' does not meet the condition for the change.", order_id),
+            OrderServiceError::AuditFailed(msg) => write!(f, "Audit failed: {}", msg),
             OrderServiceError::MutexPoisoned(msg) => write!(f, "Mutex poisoned: {}", msg),
+            OrderServiceError::Storage(msg) => write!(f, "Storage error: {}", msg),
+            OrderServiceError::TaskFailed(msg) => write!(f, "Background task failed: {}", msg),
+            OrderServiceError::ChangesCompacted(since) =>
+                write!(f, "Changes since {} are no longer available, resync required.", since),
         }
     }
 }
 
 impl Error for OrderServiceError {}
 
+// OrderQuery selects orders. An empty list matches any value, several values in a list match
+// any of them, and the conditions on different fields must all hold. Time bounds select orders
+// with created_after <= created_at < created_before. placed_by selects orders by the device that
+// placed them, orders placed before devices were recorded match none.
+#[derive(Clone, Debug, Default)]
+pub struct OrderQuery {
+    pub table_ids: Vec<String>,
+    pub item_ids: Vec<String>,
+    pub placed_by: Vec<String>,
+    pub statuses: Vec<OrderStatus>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+}
+
+impl OrderQuery {
+    pub fn matches(&self, order: &OrderResult) -> bool {
+        (self.table_ids.is_empty() || self.table_ids.contains(&order.table_id))
+            && (self.item_ids.is_empty() || self.item_ids.contains(&order.item_id))
+            && (self.placed_by.is_empty() || order.placed_by.as_ref().is_some_and(|device| self.placed_by.contains(device)))
+            && (self.statuses.is_empty() || self.statuses.contains(&order.status))
+            && self.created_after.is_none_or(|after| order.created_at >= after)
+            && self.created_before.is_none_or(|before| order.created_at < before)
+    }
+
+    fn has_time_bounds(&self) -> bool {
+        self.created_after.is_some() || self.created_before.is_some()
+    }
+}
+
+// Audit records a change of an order, given the order before and after it: before is None for a
+// created order, after is None for a deleted one. Every mutation of an OrderService calls it in
+// the same step as it makes the change, right before the change is stored: with the store locked
+// before the change is journaled, or inside the change's SQLite transaction before it is
+// committed. If it fails, the change is not made, so no change goes unrecorded. The other way
+// round, a change that fails to be stored after it was recorded leaves a record of a change
+// that was never made.
+pub trait Audit: Sync + Send {
+    fn record(&self, before: Option<&OrderResult>, after: Option<&OrderResult>) -> Result<(), OrderServiceError>;
+
+    // Whether recording may block the current thread, see OrderService::is_blocking.
+    fn is_blocking(&self) -> bool {
+        true
+    }
+}
+
 // OrderService provides an abstract way to create, delete, and fetch orders.
 // We can do unit testing on our endpoints by providing a mock implementation of OrderService.
 // We can also easily switch between in-memory and on-disk (DB) implementations.
+//
+// New orders are placed. update_status_audited moves an order along its lifecycle and fails with
+// InvalidTransition if OrderStatus::can_transition_to does not allow the change. Every mutation
+// is recorded with the given Audit, along with the order as it was right before the change,
+// read in the same step as the change is made.
 pub trait OrderService: Sync + Send {
-    fn put_order(&self, id: String, order: Order) -> Result<OrderResult, OrderServiceError>;
-    fn delete_order(&self, order_id: String) -> Result<OrderResult, OrderServiceError>;
-    fn get_orders(&self, table_id: Option<String>, item_id: Option<String>) -> Result<Vec<OrderResult>, OrderServiceError>;
+    // put_order_audited accepts a new order. A PUT of an order that already exists with identical
+    // contents is a retry and returns the stored order, otherwise it fails with DuplicateOrder.
+    // Only a newly accepted order is recorded, a retry changes nothing.
+    fn put_order_audited(&self, id: String, order: Order, audit: &dyn Audit) -> Result<PutOutcome, OrderServiceError>;
+
+    // delete_order_audited deletes the order only if the condition holds for it, and fails with
+    // ConditionFailed otherwise. The condition is checked and the order deleted atomically,
+    // so the order cannot change or be replaced in between.
+    fn delete_order_audited(
+        &self,
+        order_id: String,
+        condition: &dyn Fn(&OrderResult) -> bool,
+        audit: &dyn Audit,
+    ) -> Result<OrderResult, OrderServiceError>;
+
+    fn update_status_audited(
+        &self,
+        order_id: String,
+        status: OrderStatus,
+        audit: &dyn Audit,
+    ) -> Result<OrderResult, OrderServiceError>;
+    fn get_order_by_id(&self, order_id: String) -> Result<OrderResult, OrderServiceError>;
+
+    // get_orders_page returns one page of the orders matching the query, see PageRequest.
+    fn get_orders_page(&self, query: &OrderQuery, page: &PageRequest) -> Result<OrderPage, OrderServiceError>;
+
+    // get_changes returns the orders created, updated or deleted after the mutation with the
+    // given sequence number, or all orders if since is None. Only the latest MAX_TOMBSTONES
+    // deletions are kept: asking for changes from before them (or for a sequence number
+    // that was never handed out) fails with ChangesCompacted.
+    fn get_changes(&self, since: Option<u64>) -> Result<Changes, OrderServiceError>;
+
+    // Whether calls may block the current thread (disk or network I/O). AsyncOrderService
+    // runs blocking implementations on a blocking thread pool.
+    fn is_blocking(&self) -> bool {
+        true
+    }
+}
+
+type CreatedBound = Bound<(DateTime<Utc>, String)>;
+
+// The part of created_idx within the query's time bounds.
+fn created_bounds(query: &OrderQuery) -> (CreatedBound, CreatedBound) {
+    let from = match query.created_after {
+        Some(after) => Bound::Included((after, String::new())),
+        None => Bound::Unbounded,
+    };
+    let to = match query.created_before {
+        Some(before) => Bound::Excluded((before, String::new())),
+        None => Bound::Unbounded,
+    };
+    (from, to)
+}
+
+// BTreeSet::range panics on an inverted range, so such ranges have to be caught beforehand.
+// `to` is never inclusive, so a range starting where it ends is empty as well.
+fn is_empty_range(from: &CreatedBound, to: &CreatedBound) -> bool {
+    match (from, to) {
+        (Bound::Included(start) | Bound::Excluded(start), Bound::Excluded(end)) => start >= end,
+        _ => false,
+    }
+}
+
+// OrderStore holds the orders together with four indexes, so that filtered queries only touch
+// matching orders:
+// tables_idx: table_id -> order ids
+// items_idx: item_id -> table_id -> order ids
+// statuses_idx: status -> order ids
+// created_idx: (created_at, order id), ordered by creation time
+// Index entries are removed as soon as they become empty. placed_by is not indexed, it only
+// narrows down the orders found through the other filters.
+//
+// Every mutation gets the next sequence number. seq_idx maps the sequence number of each order's
+// last mutation to the order, tombstones those of deletions, so changes since a sequence
+// number can be found without a scan. Changes up to compacted_through are no longer known.
+//
+// OrderStore itself is not synchronized. InMemoryOrderService keeps the whole store behind
+// a single RwLock, so every mutation updates the orders and all indexes atomically and
+// readers always observe them in sync.
+#[derive(Default)]
+struct OrderStore {
+    orders: HashMap<String, OrderResult>,
+    tables_idx: HashMap<String, Vec<String>>,
+    items_idx: HashMap<String, HashMap<String, Vec<String>>>,
+    statuses_idx: HashMap<OrderStatus, HashSet<String>>,
+    created_idx: BTreeSet<(DateTime<Utc>, String)>,
+    seq_idx: BTreeMap<u64, String>,
+    tombstones: BTreeMap<u64, String>,
+    last_seq: u64,
+    compacted_through: u64,
+}
+
+impl OrderStore {
+    fn next_seq(&self) -> u64 {
+        self.last_seq + 1
+    }
+
+    fn insert(&mut self, order: OrderResult) {
+        self.last_seq = self.last_seq.max(order.seq);
+        self.seq_idx.insert(order.seq, order.order_id.clone());
+        self.created_idx.insert((order.created_at, order.order_id.clone()));
+        self.statuses_idx.entry(order.status).or_default().insert(order.order_id.clone());
+        self.tables_idx.entry(order.table_id.clone()).or_default().push(order.order_id.clone());
+        self.items_idx
+            .entry(order.item_id.clone())
+            .or_default()
+            .entry(order.table_id.clone())
+            .or_default()
+            .push(order.order_id.clone());
+        self.orders.insert(order.order_id.clone(), order);
+    }
+
+    // Removes the order, leaving a tombstone with the deletion's sequence number behind.
+    fn remove(&mut self, order_id: &str, seq: u64) -> Option<OrderResult> {
+        let order = self.orders.remove(order_id)?;
+        self.seq_idx.remove(&order.seq);
+        self.last_seq = self.last_seq.max(seq);
+        self.tombstones.insert(seq, order_id.to_string());
+        while self.tombstones.len() > MAX_TOMBSTONES {
+            if let Some((evicted, _)) = self.tombstones.pop_first() {
+                self.compacted_through = evicted;
+            }
+        }
+        self.unindex_status(order_id, order.status);
+        self.created_idx.remove(&(order.created_at, order.order_id.clone()));
+
+        if let Some(table) = self.tables_idx.get_mut(&order.table_id) {
+            table.retain(|x| x != order_id);
+            if table.is_empty() {
+                self.tables_idx.remove(&order.table_id);
+            }
+        }
+
+        if let Some(item_tables) = self.items_idx.get_mut(&order.item_id) {
+            if let Some(table) = item_tables.get_mut(&order.table_id) {
+                table.retain(|x| x != order_id);
+                if table.is_empty() {
+                    item_tables.remove(&order.table_id);
+                }
+            }
+            if item_tables.is_empty() {
+                self.items_idx.remove(&order.item_id);
+            }
+        }
+
+        Some(order)
+    }
+
+    fn set_status(&mut self, order_id: &str, status: OrderStatus, updated_at: DateTime<Utc>, seq: u64) -> Option<OrderResult> {
+        let order = self.orders.get_mut(order_id)?;
+        let previous = order.status;
+        let previous_seq = order.seq;
+        order.status = status;
+        order.updated_at = updated_at;
+        order.seq = seq;
+        let order = order.clone();
+
+        self.last_seq = self.last_seq.max(seq);
+        self.seq_idx.remove(&previous_seq);
+        self.seq_idx.insert(seq, order_id.to_string());
+        self.unindex_status(order_id, previous);
+        self.statuses_idx.entry(status).or_default().insert(order_id.to_string());
+        Some(order)
+    }
+
+    fn unindex_status(&mut self, order_id: &str, status: OrderStatus) {
+        if let Some(order_ids) = self.statuses_idx.get_mut(&status) {
+            order_ids.remove(order_id);
+            if order_ids.is_empty() {
+                self.statuses_idx.remove(&status);
+            }
+        }
+    }
+
+    fn orders_in_progress(&self) -> usize {
+        self.statuses_idx
+            .iter()
+            .filter(|(status, _)| status.is_in_progress())
+            .map(|(_, order_ids)| order_ids.len())
+            .sum()
+    }
+
+    fn collect<'a>(&self, order_ids: impl IntoIterator<Item = &'a String>) -> Vec<OrderResult> {
+        order_ids
+            .into_iter()
+            .filter_map(|id| self.orders.get(id).cloned())
+            .collect()
+    }
+
+    // Candidates come from the most selective index the query allows, the remaining conditions
+    // are checked on each candidate.
+    fn query(&self, query: &OrderQuery) -> Vec<OrderResult> {
+        let table_ids: HashSet<&String> = query.table_ids.iter().collect();
+        let item_ids: HashSet<&String> = query.item_ids.iter().collect();
+        let statuses: HashSet<OrderStatus> = query.statuses.iter().copied().collect();
+
+        let mut candidates: Vec<OrderResult> = if !item_ids.is_empty() {
+            let item_tables = item_ids.iter().filter_map(|item_id| self.items_idx.get(*item_id));
+            if table_ids.is_empty() {
+                item_tables.flat_map(|item_tables| item_tables.values().flat_map(|order_ids| self.collect(order_ids))).collect()
+            } else {
+                item_tables
+                    .flat_map(|item_tables| table_ids.iter().filter_map(|table_id| item_tables.get(*table_id)))
+                    .flat_map(|order_ids| self.collect(order_ids))
+                    .collect()
+            }
+        } else if !table_ids.is_empty() {
+            table_ids.iter()
+                .filter_map(|table_id| self.tables_idx.get(*table_id))
+                .flat_map(|order_ids| self.collect(order_ids))
+                .collect()
+        } else if !statuses.is_empty() {
+            statuses.iter()
+                .filter_map(|status| self.statuses_idx.get(status))
+                .flat_map(|order_ids| self.collect(order_ids))
+                .collect()
+        } else if query.has_time_bounds() {
+            let (from, to) = created_bounds(query);
+            if is_empty_range(&from, &to) {
+                return Vec::new();
+            }
+            self.collect(self.created_idx.range((from, to)).map(|(_, id)| id))
+        } else {
+            self.orders.values().cloned().collect()
+        };
+
+        candidates.retain(|order| query.matches(order));
+        candidates
+    }
+
+    // Walks created_idx from the page's cursor on, so that listing all orders page by page only
+    // touches the orders of the requested page.
+    fn page_by_creation(&self, query: &OrderQuery, page: &PageRequest) -> Vec<OrderResult> {
+        let (mut from, mut to) = created_bounds(query);
+
+        let position = page.after.as_ref().and_then(|cursor| Some((cursor.created_at()?, cursor.order_id.clone())));
+        if let Some(position) = position {
+            match (page.direction, &from, &to) {
+                (SortDirection::Asc, Bound::Included(start), _) if *start > position => {}
+                (SortDirection::Asc, _, _) => from = Bound::Excluded(position),
+                (SortDirection::Desc, _, Bound::Excluded(end)) if *end < position => {}
+                (SortDirection::Desc, _, _) => to = Bound::Excluded(position),
+            }
+        }
+
+        if is_empty_range(&from, &to) {
+            return Vec::new();
+        }
+
+        let limit = page.limit.saturating_add(1);
+        let order_ids = self.created_idx.range((from, to)).map(|(_, id)| id);
+        match page.direction {
+            SortDirection::Asc => self.collect(order_ids.take(limit)),
+            SortDirection::Desc => self.collect(order_ids.rev().take(limit)),
+        }
+    }
+
+    fn changes(&self, since: Option<u64>) -> Result<Changes, OrderServiceError> {
+        let Some(since) = since else {
+            let changes = self.collect(self.seq_idx.values()).into_iter().map(Change::Put).collect();
+            return Ok(Changes { changes, last_seq: self.last_seq });
+        };
+        if since < self.compacted_through || since > self.last_seq {
+            return Err(OrderServiceError::ChangesCompacted(since));
+        }
+
+        let after = (Bound::Excluded(since), Bound::Unbounded);
+        let mut changes: Vec<Change> = self.collect(self.seq_idx.range(after).map(|(_, id)| id))
+            .into_iter()
+            .map(Change::Put)
+            .collect();
+        changes.extend(self.tombstones.range(after).map(|(seq, order_id)| {
+            Change::Deleted(Tombstone { order_id: order_id.clone(), seq: *seq })
+        }));
+        changes.sort_by_key(Change::seq);
+        Ok(Changes { changes, last_seq: self.last_seq })
+    }
+
+    // Verifies that the indexes describe exactly the stored orders.
+    #[cfg(test)]
+    fn check_consistency(&self) -> Result<(), String> {
+        let mut indexed = 0;
+        for (table_id, order_ids) in &self.tables_idx {
+            if order_ids.is_empty() {
+                return Err(format!("empty tables_idx entry for '{}'", table_id));
+            }
+            for id in order_ids {
+                match self.orders.get(id) {
+                    Some(order) if &order.table_id == table_id => indexed += 1,
+                    _ => return Err(format!("tables_idx['{}'] points to missing order '{}'", table_id, id)),
+                }
+            }
+        }
+        if indexed != self.orders.len() {
+            return Err(format!("tables_idx holds {} orders, store holds {}", indexed, self.orders.len()));
+        }
+
+        let mut indexed = 0;
+        for (item_id, item_tables) in &self.items_idx {
+            if item_tables.is_empty() {
+                return Err(format!("empty items_idx entry for '{}'", item_id));
+            }
+            for (table_id, order_ids) in item_tables {
+                if order_ids.is_empty() {
+                    return Err(format!("empty items_idx entry for '{}'/'{}'", item_id, table_id));
+                }
+                for id in order_ids {
+                    match self.orders.get(id) {
+                        Some(order) if &order.item_id == item_id && &order.table_id == table_id => indexed += 1,
+                        _ => return Err(format!("items_idx['{}']['{}'] points to missing order '{}'", item_id, table_id, id)),
+                    }
+                }
+            }
+        }
+        if indexed != self.orders.len() {
+            return Err(format!("items_idx holds {} orders, store holds {}", indexed, self.orders.len()));
+        }
+
+        let mut indexed = 0;
+        for (status, order_ids) in &self.statuses_idx {
+            if order_ids.is_empty() {
+                return Err(format!("empty statuses_idx entry for '{}'", status));
+            }
+            for id in order_ids {
+                match self.orders.get(id) {
+                    Some(order) if &order.status == status => indexed += 1,
+                    _ => return Err(format!("statuses_idx['{}'] points to missing order '{}'", status, id)),
+                }
+            }
+        }
+        if indexed != self.orders.len() {
+            return Err(format!("statuses_idx holds {} orders, store holds {}", indexed, self.orders.len()));
+        }
+
+        for (created_at, id) in &self.created_idx {
+            match self.orders.get(id) {
+                Some(order) if &order.created_at == created_at => {}
+                _ => return Err(format!("created_idx points to missing order '{}'", id)),
+            }
+        }
+        if self.created_idx.len() != self.orders.len() {
+            return Err(format!("created_idx holds {} orders, store holds {}", self.created_idx.len(), self.orders.len()));
+        }
+
+        for (seq, id) in &self.seq_idx {
+            match self.orders.get(id) {
+                Some(order) if order.seq == *seq && *seq <= self.last_seq => {}
+                _ => return Err(format!("seq_idx points to missing order '{}'", id)),
+            }
+        }
+        if self.seq_idx.len() != self.orders.len() {
+            return Err(format!("seq_idx holds {} orders, store holds {}", self.seq_idx.len(), self.orders.len()));
+        }
+        Ok(())
+    }
 }
 
-// InMemoryOrderService stores orders in memory using HashMaps wrapped in RwLock for thread safety.
+// InMemoryOrderService stores orders in memory, in an OrderStore wrapped in RwLock for thread safety.
+// Optionally every mutation is also written to a Journal, so the state can be recovered
+// after a crash.
+// Cooking times are assigned by a CookingTimeEstimator, random by default.
+// Mutations are published to OrderEvents, if set.
 pub struct InMemoryOrderService {
-    orders: RwLock<HashMap<String, OrderResult>>,
-    tables_idx: RwLock<HashMap<String, Vec<String>>>,
+    store: RwLock<OrderStore>,
+    journal: Option<Mutex<Journal>>,
+    estimator: Arc<dyn CookingTimeEstimator>,
+    events: Option<Arc<OrderEvents>>,
 }
 
 pub fn new_in_memory() -> InMemoryOrderService {
     InMemoryOrderService {
-        orders: RwLock::new(HashMap::new()),
-        tables_idx: RwLock::new(HashMap::new()),
+        store: RwLock::new(OrderStore::default()),
+        journal: None,
+        estimator: Arc::new(RandomEstimator),
+        events: None,
     }
 }
 
-impl OrderService for InMemoryOrderService {
-    fn put_order(&self, id: String, order: Order) -> Result<OrderResult, OrderServiceError> {
-        let mut orders = self.orders.write()
-            .map_err(|_| OrderServiceError::MutexPoisoned("Failed to obtain write mutex".into()))?;
+// Creates an InMemoryOrderService journaling into `dir`, with the orders recovered from it.
+// The journal is compacted into a snapshot after every `compact_every` mutations.
+pub fn new_in_memory_with_journal(
+    dir: impl AsRef<Path>,
+    compact_every: usize,
+) -> Result<InMemoryOrderService, OrderServiceError> {
+    let (journal, Recovered { mut orders, mut last_seq }) = Journal::open(dir, compact_every)?;
 
-        if orders.contains_key(&id) {
-            return Err(OrderServiceError::DuplicateOrder(id));
+    let mut store = OrderStore::default();
+    orders.sort_by_key(|order| (order.seq, order.created_at));
+    for mut order in orders {
+        // Orders journaled before mutations got sequence numbers get one now.
+        if order.seq == 0 {
+            last_seq += 1;
+            order.seq = last_seq;
         }
+        store.insert(order);
+    }
+    // Tombstones are not journaled, so a restart compacts the change history.
+    store.last_seq = last_seq;
+    store.compacted_through = last_seq;
+
+    Ok(InMemoryOrderService {
+        store: RwLock::new(store),
+        journal: Some(Mutex::new(journal)),
+        estimator: Arc::new(RandomEstimator),
+        events: None,
+    })
+}
+
+impl InMemoryOrderService {
+    pub fn with_estimator(mut self, estimator: Arc<dyn CookingTimeEstimator>) -> InMemoryOrderService {
+        self.estimator = estimator;
+        self
+    }
 
-        let order_result = OrderResult {
-            order_id: id.clone(),
-            item_id: order.item_id.clone(),
-            table_id: order.table_id.clone(),
-            cooking_time: rand::thread_rng().gen_range(5..16),
+    pub fn with_events(mut self, events: Arc<OrderEvents>) -> InMemoryOrderService {
+        self.events = Some(events);
+        self
+    }
+
+    // Number of (table, item) index entries, exposed so tests can check that empty
+    // entries get pruned.
+    #[cfg(test)]
+    pub fn index_len(&self) -> (usize, usize) {
+        let store = self.store.read().unwrap();
+        (store.tables_idx.len(), store.items_idx.len())
+    }
+
+    #[cfg(test)]
+    pub fn check_consistency(&self) -> Result<(), String> {
+        self.store.read().unwrap().check_consistency()
+    }
+
+    // Records the mutation in the journal (if any) before it is applied. `apply` is only
+    // called once the entry is durable; afterwards the journal is compacted if it got too long.
+    // Callers must hold the `store` write lock, which is what `store` is borrowed from.
+    fn journaled<T>(
+        &self,
+        entry: JournalEntry,
+        store: &mut OrderStore,
+        apply: impl FnOnce(&mut OrderStore) -> T,
+    ) -> Result<T, OrderServiceError> {
+        let Some(journal) = &self.journal else {
+            return Ok(apply(store));
         };
 
-        orders.insert(id.clone(), order_result.clone());
+        let mut journal = journal.lock()
+            .map_err(|_| OrderServiceError::MutexPoisoned("Failed to obtain journal mutex".into()))?;
+        journal.append(&entry)?;
+        let res = apply(store);
 
-        let mut tables_idx = self.tables_idx.write()
-            .map_err(|_| OrderServiceError::MutexPoisoned("Failed to obtain write mutex".into()))?;
-        tables_idx.entry(order.table_id.clone()).or_insert_with(Vec::new).push(id);
+        if journal.should_compact() {
+            // The mutation is already durable, so a failed compaction must not fail it.
+            if let Err(e) = journal.compact(store.orders.values(), store.last_seq) {
+                error!("Failed to compact journal: {}", e);
+            }
+        }
+        Ok(res)
+    }
 
-        Ok(order_result)
+    // Callers must still hold the `store` write lock, so that events are published in the
+    // order the mutations were applied in.
+    fn publish(&self, kind: OrderEventKind, order: &OrderResult) {
+        if let Some(events) = &self.events {
+            events.publish(kind, order);
+        }
     }
+}
 
-    fn delete_order(&self, order_id: String) -> Result<OrderResult, OrderServiceError> {
-        let mut orders = self.orders.write()
+impl OrderService for InMemoryOrderService {
+    fn put_order_audited(&self, id: String, order: Order, audit: &dyn Audit) -> Result<PutOutcome, OrderServiceError> {
+        validate(&order)?;
+        let base_time = self.estimator.base_time(&order);
+
+        let mut store = self.store.write()
             .map_err(|_| OrderServiceError::MutexPoisoned("Failed to obtain write mutex".into()))?;
 
-        let order = orders.remove(&order_id)
-            .ok_or(OrderServiceError::OrderNotFound(order_id.clone()))?;
+        if let Some(existing) = store.orders.get(&id) {
+            return retried(existing.clone(), &order);
+        }
+
+        let cooking_time = self.estimator.estimate(base_time, store.orders_in_progress());
+        let mut order_result = OrderResult::placed(id, order, cooking_time);
+        order_result.seq = store.next_seq();
+        audit.record(None, Some(&order_result))?;
+
+        let entry = JournalEntry::Put { order: order_result.clone() };
+        self.journaled(entry, &mut store, |store| store.insert(order_result.clone()))?;
+        self.publish(OrderEventKind::Created, &order_result);
+
+        Ok(PutOutcome::Created(order_result))
+    }
 
-        let mut tables_idx = self.tables_idx.write()
+    fn delete_order_audited(
+        &self,
+        order_id: String,
+        condition: &dyn Fn(&OrderResult) -> bool,
+        audit: &dyn Audit,
+    ) -> Result<OrderResult, OrderServiceError> {
+        let mut store = self.store.write()
             .map_err(|_| OrderServiceError::MutexPoisoned("Failed to obtain write mutex".into()))?;
-        if let Some(table) = tables_idx.get_mut(&order.table_id) {
-            table.retain(|x| x != &order_id);
+
+        match store.orders.get(&order_id) {
+            None => return Err(OrderServiceError::OrderNotFound(order_id)),
+            Some(order) if !condition(order) => return Err(OrderServiceError::ConditionFailed(order_id)),
+            Some(order) => audit.record(Some(order), None)?,
         }
 
+        let seq = store.next_seq();
+        let entry = JournalEntry::Delete { order_id: order_id.clone(), seq };
+        let order = self.journaled(entry, &mut store, |store| store.remove(&order_id, seq))?
+            .ok_or(OrderServiceError::OrderNotFound(order_id))?;
+        self.publish(OrderEventKind::Deleted, &order);
         Ok(order)
     }
 
-    fn get_orders(
+    fn update_status_audited(
         &self,
-        table_id: Option<String>,
-        item_id: Option<String>,
-    ) -> Result<Vec<OrderResult>, OrderServiceError> {
-        let orders = self.orders.read().
-            map_err(|_| OrderServiceError::MutexPoisoned("Failed to obtain read mutex".into()))?;
-        let tables_idx = self.tables_idx.read()
-            .map_err(|_| OrderServiceError::MutexPoisoned("Failed to obtain read mutex".into()))?;
+        order_id: String,
+        status: OrderStatus,
+        audit: &dyn Audit,
+    ) -> Result<OrderResult, OrderServiceError> {
+        let mut store = self.store.write()
+            .map_err(|_| OrderServiceError::MutexPoisoned("Failed to obtain write mutex".into()))?;
 
-        let result = match (table_id, item_id) {
-            (None, None) => orders.values().cloned().collect(),
-            (Some(table_id), None) => {
-                if let Some(order_ids) = tables_idx.get(&table_id) {
-                    order_ids
-                        .iter()
-                        .filter_map(|id| orders.get(id).cloned())
-                        .collect()
-                } else {
-                    Vec::new()
-                }
-            }
-            (Some(table_id), Some(item_id)) => {
-                if let Some(order_ids) = tables_idx.get(&table_id) {
-                    order_ids
-                        .iter()
-                        .filter_map(|id| {
-                            orders
-                                .get(id)
-                                .filter(|order| order.item_id == item_id)
-                                .cloned()
-                        })
-                        .collect()
-                } else {
-                    Vec::new()
-                }
-            }
-            (None, Some(item_id)) => orders
-                .values()
-                .filter(|order| order.item_id == item_id)
-                .cloned()
-                .collect(),
+        let before = match store.orders.get(&order_id) {
+            Some(order) => order.clone(),
+            None => return Err(OrderServiceError::OrderNotFound(order_id)),
         };
-        Ok(result)
+        if !before.status.can_transition_to(status) {
+            return Err(OrderServiceError::InvalidTransition { order_id, from: before.status, to: status });
+        }
+
+        let updated_at = now();
+        let seq = store.next_seq();
+        audit.record(Some(&before), Some(&OrderResult { status, updated_at, seq, ..before.clone() }))?;
+
+        let entry = JournalEntry::UpdateStatus { order_id: order_id.clone(), status, updated_at, seq };
+        let order = self.journaled(entry, &mut store, |store| store.set_status(&order_id, status, updated_at, seq))?
+            .ok_or(OrderServiceError::OrderNotFound(order_id))?;
+        self.publish(OrderEventKind::Updated, &order);
+        Ok(order)
+    }
+
+    fn get_orders_page(&self, query: &OrderQuery, page: &PageRequest) -> Result<OrderPage, OrderServiceError> {
+        let store = self.store.read()
+            .map_err(|_| OrderServiceError::MutexPoisoned("Failed to obtain read mutex".into()))?;
+
+        if page.sort == SortKey::Created
+            && query.table_ids.is_empty()
+            && query.item_ids.is_empty()
+            && query.placed_by.is_empty()
+            && query.statuses.is_empty()
+        {
+            let orders = store.page_by_creation(query, page);
+            return Ok(OrderPage::from_sorted(orders, page));
+        }
+        Ok(paginate(store.query(query), page))
+    }
+
+    fn get_order_by_id(&self, order_id: String) -> Result<OrderResult, OrderServiceError> {
+        let store = self.store.read()
+            .map_err(|_| OrderServiceError::MutexPoisoned("Failed to obtain read mutex".into()))?;
+
+        store.orders.get(&order_id)
+            .cloned()
+            .ok_or(OrderServiceError::OrderNotFound(order_id))
+    }
+
+    fn get_changes(&self, since: Option<u64>) -> Result<Changes, OrderServiceError> {
+        let store = self.store.read()
+            .map_err(|_| OrderServiceError::MutexPoisoned("Failed to obtain read mutex".into()))?;
+
+        store.changes(since)
+    }
+
+    // Without a journal all work happens in memory; with one every mutation waits for fsync.
+    fn is_blocking(&self) -> bool {
+        self.journal.is_some()
     }
 }
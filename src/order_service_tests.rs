@@ -1,6 +1,103 @@
+#[cfg(test)]
+use std::sync::Mutex;
+#[cfg(test)]
+use crate::order_service::{Audit, Order, OrderQuery, OrderResult, OrderService, OrderServiceError, OrderStatus, PutOutcome};
+#[cfg(test)]
+use crate::pagination::PageRequest;
+
+// OrderServiceExt lets the tests of every OrderService fetch all matching orders at once,
+// oldest first, and make changes without recording them. The API always paginates, see
+// get_orders_page, and records every change, see Audit.
+#[cfg(test)]
+pub trait OrderServiceExt: OrderService {
+    fn get_orders(&self, query: &OrderQuery) -> Result<Vec<OrderResult>, OrderServiceError> {
+        Ok(self.get_orders_page(query, &PageRequest::all())?.orders)
+    }
+
+    fn put_order(&self, id: String, order: Order) -> Result<PutOutcome, OrderServiceError> {
+        self.put_order_audited(id, order, &Unaudited)
+    }
+
+    fn delete_order(&self, order_id: String) -> Result<OrderResult, OrderServiceError> {
+        self.delete_order_audited(order_id, &|_| true, &Unaudited)
+    }
+
+    fn delete_order_if(
+        &self,
+        order_id: String,
+        condition: &dyn Fn(&OrderResult) -> bool,
+    ) -> Result<OrderResult, OrderServiceError> {
+        self.delete_order_audited(order_id, condition, &Unaudited)
+    }
+
+    fn update_status(&self, order_id: String, status: OrderStatus) -> Result<OrderResult, OrderServiceError> {
+        self.update_status_audited(order_id, status, &Unaudited)
+    }
+}
+
+// Unaudited records nothing.
+#[cfg(test)]
+pub struct Unaudited;
+
+#[cfg(test)]
+impl Audit for Unaudited {
+    fn record(&self, _: Option<&OrderResult>, _: Option<&OrderResult>) -> Result<(), OrderServiceError> {
+        Ok(())
+    }
+
+    fn is_blocking(&self) -> bool {
+        false
+    }
+}
+
+// RecordingAudit keeps the changes it records as (before, after) pairs. If fail is set, it
+// fails to record them instead.
+#[cfg(test)]
+#[derive(Default)]
+pub struct RecordingAudit {
+    pub changes: Mutex<Vec<(Option<OrderResult>, Option<OrderResult>)>>,
+    pub fail: bool,
+}
+
+#[cfg(test)]
+impl RecordingAudit {
+    pub fn failing() -> RecordingAudit {
+        RecordingAudit { fail: true, ..Default::default() }
+    }
+
+    // The statuses before and after each recorded change, None for a missing order.
+    pub fn statuses(&self) -> Vec<(Option<OrderStatus>, Option<OrderStatus>)> {
+        self.changes.lock().unwrap()
+            .iter()
+            .map(|(before, after)| (before.as_ref().map(|order| order.status), after.as_ref().map(|order| order.status)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+impl Audit for RecordingAudit {
+    fn record(&self, before: Option<&OrderResult>, after: Option<&OrderResult>) -> Result<(), OrderServiceError> {
+        if self.fail {
+            return Err(OrderServiceError::AuditFailed("audit log is unavailable".to_string()));
+        }
+        self.changes.lock().unwrap().push((before.cloned(), after.cloned()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl<T: OrderService + ?Sized> OrderServiceExt for T {}
+
 #[cfg(test)]
 mod tests {
-    use crate::order_service::{new_in_memory, InMemoryOrderService, Order, OrderService, OrderServiceError};
+    use chrono::Duration;
+    use crate::pagination::{PageRequest, SortDirection, SortKey};
+    use crate::order_service_tests::{OrderServiceExt, RecordingAudit};
+    use crate::order_service::{
+        new_in_memory, Change, Changes, Doneness, InMemoryOrderService, Modifier, Order, OrderQuery, OrderResult,
+        OrderService, OrderServiceError, OrderStatus, MAX_INGREDIENT_LEN, MAX_MODIFIERS, MAX_NOTES_LEN, MAX_QUANTITY,
+        MAX_TOMBSTONES,
+    };
 
     fn setup_service() -> InMemoryOrderService {
         new_in_memory()
@@ -12,11 +109,14 @@ mod tests {
         let order = Order {
             item_id: "item1".to_string(),
             table_id: "table1".to_string(),
+            ..Default::default()
         };
 
         let result = service.put_order("order1".to_string(), order);
         assert!(result.is_ok());
-        let order_result = result.unwrap();
+        let outcome = result.unwrap();
+        assert!(outcome.is_created());
+        let order_result = outcome.into_order();
         assert_eq!(order_result.order_id, "order1");
         assert_eq!(order_result.item_id, "item1");
         assert_eq!(order_result.table_id, "table1");
@@ -28,27 +128,56 @@ mod tests {
         let order = Order {
             item_id: "item1".to_string(),
             table_id: "table1".to_string(),
+            ..Default::default()
         };
 
         let result = service.put_order("order1".to_string(), order.clone());
         assert!(result.is_ok());
 
-        let duplicate_result = service.put_order("order1".to_string(), order);
+        let duplicate_result = service.put_order("order1".to_string(), Order {
+            table_id: "table2".to_string(),
+            quantity: 2,
+            ..order
+        });
         assert!(duplicate_result.is_err());
 
-        if let Err(OrderServiceError::DuplicateOrder(order_id)) = duplicate_result {
+        if let Err(OrderServiceError::DuplicateOrder { order_id, fields }) = duplicate_result {
             assert_eq!(order_id, "order1");
+            assert_eq!(fields, vec!["table_id", "quantity"]);
         } else {
             panic!("Expected DuplicateOrder error.");
         }
     }
 
+    #[test]
+    fn test_put_order_retry_returns_stored_order() {
+        let service = setup_service();
+        let order = Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            modifiers: vec![Modifier::Remove { ingredient: "onion".to_string() }],
+            ..Default::default()
+        };
+
+        let created = service.put_order("order1".to_string(), order.clone()).unwrap().into_order();
+        service.update_status("order1".to_string(), OrderStatus::Cooking).unwrap();
+
+        let retried = service.put_order("order1".to_string(), order).unwrap();
+        assert!(!retried.is_created());
+        let retried = retried.into_order();
+        assert_eq!(retried.cooking_time, created.cooking_time);
+        assert_eq!(retried.created_at, created.created_at);
+        assert_eq!(retried.status, OrderStatus::Cooking);
+        assert_eq!(service.get_orders(&OrderQuery::default()).unwrap().len(), 1);
+    }
+
     #[test]
     fn test_delete_order_success() {
         let service = setup_service();
         let order = Order {
             item_id: "item1".to_string(),
             table_id: "table1".to_string(),
+            ..Default::default()
         };
 
         let result = service.put_order("order1".to_string(), order);
@@ -74,41 +203,151 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_delete_order_if() {
+        let service = setup_service();
+        service.put_order("order1".to_string(), Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            placed_by: Some("watch-17".to_string()),
+            ..Default::default()
+        }).unwrap();
+
+        let placed_by = |device: &'static str| move |order: &OrderResult| order.placed_by.as_deref() == Some(device);
+        assert!(matches!(
+            service.delete_order_if("order1".to_string(), &placed_by("watch-18")),
+            Err(OrderServiceError::ConditionFailed(_))
+        ));
+        assert!(service.get_order_by_id("order1".to_string()).is_ok());
+
+        let deleted = service.delete_order_if("order1".to_string(), &placed_by("watch-17")).unwrap();
+        assert_eq!(deleted.order_id, "order1");
+        assert!(matches!(
+            service.delete_order_if("order1".to_string(), &placed_by("watch-17")),
+            Err(OrderServiceError::OrderNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_changes_are_audited() {
+        let service = setup_service();
+        let order = Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        };
+        let audit = RecordingAudit::default();
+
+        service.put_order_audited("order1".to_string(), order.clone(), &audit).unwrap();
+        // A retry changes nothing, so it is not recorded again.
+        service.put_order_audited("order1".to_string(), order, &audit).unwrap();
+        service.update_status_audited("order1".to_string(), OrderStatus::Cooking, &audit).unwrap();
+        service.delete_order_audited("order1".to_string(), &|_| true, &audit).unwrap();
+
+        assert_eq!(audit.statuses(), vec![
+            (None, Some(OrderStatus::Placed)),
+            (Some(OrderStatus::Placed), Some(OrderStatus::Cooking)),
+            (Some(OrderStatus::Cooking), None),
+        ]);
+        let changes = audit.changes.lock().unwrap();
+        assert_eq!(changes[1].1.as_ref().unwrap().seq, 2);
+    }
+
+    #[test]
+    fn test_changes_that_cannot_be_audited_are_not_made() {
+        let service = setup_service();
+        let order = Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        };
+        let failing = RecordingAudit::failing();
+
+        let res = service.put_order_audited("order1".to_string(), order.clone(), &failing);
+        assert!(matches!(res, Err(OrderServiceError::AuditFailed(_))));
+        assert!(matches!(service.get_order_by_id("order1".to_string()), Err(OrderServiceError::OrderNotFound(_))));
+
+        service.put_order("order1".to_string(), order).unwrap();
+        let res = service.update_status_audited("order1".to_string(), OrderStatus::Cooking, &failing);
+        assert!(matches!(res, Err(OrderServiceError::AuditFailed(_))));
+        let res = service.delete_order_audited("order1".to_string(), &|_| true, &failing);
+        assert!(matches!(res, Err(OrderServiceError::AuditFailed(_))));
+
+        let stored = service.get_order_by_id("order1".to_string()).unwrap();
+        assert_eq!(stored.status, OrderStatus::Placed);
+        // Neither the status change nor the deletion used up a sequence number.
+        assert_eq!(service.get_changes(None).unwrap().last_seq, 1);
+    }
+
     #[test]
     fn test_get_orders_by_table_id() {
         let service = setup_service();
         let order1 = Order {
             item_id: "item1".to_string(),
             table_id: "table1".to_string(),
+            ..Default::default()
         };
         let order2 = Order {
             item_id: "item2".to_string(),
             table_id: "table1".to_string(),
+            ..Default::default()
         };
 
         service.put_order("order1".to_string(), order1).unwrap();
         service.put_order("order2".to_string(), order2).unwrap();
 
-        let orders = service.get_orders(Some("table1".to_string()), None).unwrap();
+        let orders = service.get_orders(&OrderQuery {
+            table_ids: vec!["table1".to_string()],
+            ..Default::default()
+        }).unwrap();
         assert_eq!(orders.len(), 2);
     }
 
+    #[test]
+    fn test_get_orders_by_placed_by() {
+        let service = setup_service();
+        for (order_id, placed_by) in [("order1", Some("watch-17")), ("order2", Some("watch-18")), ("order3", None)] {
+            service.put_order(order_id.to_string(), Order {
+                item_id: "item1".to_string(),
+                table_id: "table1".to_string(),
+                placed_by: placed_by.map(str::to_string),
+                ..Default::default()
+            }).unwrap();
+        }
+
+        let orders = service.get_orders(&OrderQuery {
+            placed_by: vec!["watch-17".to_string()],
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].order_id, "order1");
+        assert_eq!(service.get_orders(&OrderQuery {
+            placed_by: vec!["watch-17".to_string(), "watch-18".to_string()],
+            ..Default::default()
+        }).unwrap().len(), 2);
+    }
+
     #[test]
     fn test_get_orders_by_item_id() {
         let service = setup_service();
         let order1 = Order {
             item_id: "item1".to_string(),
             table_id: "table1".to_string(),
+            ..Default::default()
         };
         let order2 = Order {
             item_id: "item2".to_string(),
             table_id: "table1".to_string(),
+            ..Default::default()
         };
 
         service.put_order("order1".to_string(), order1).unwrap();
         service.put_order("order2".to_string(), order2).unwrap();
 
-        let orders = service.get_orders(None, Some("item1".to_string())).unwrap();
+        let orders = service.get_orders(&OrderQuery {
+            item_ids: vec!["item1".to_string()],
+            ..Default::default()
+        }).unwrap();
         assert_eq!(orders.len(), 1);
         assert_eq!(orders[0].item_id, "item1");
     }
@@ -119,16 +358,22 @@ mod tests {
         let order1 = Order {
             item_id: "item1".to_string(),
             table_id: "table1".to_string(),
+            ..Default::default()
         };
         let order2 = Order {
             item_id: "item2".to_string(),
             table_id: "table1".to_string(),
+            ..Default::default()
         };
 
         service.put_order("order1".to_string(), order1).unwrap();
         service.put_order("order2".to_string(), order2).unwrap();
 
-        let orders = service.get_orders(Some("table1".to_string()), Some("item1".to_string())).unwrap();
+        let orders = service.get_orders(&OrderQuery {
+            table_ids: vec!["table1".to_string()],
+            item_ids: vec!["item1".to_string()],
+            ..Default::default()
+        }).unwrap();
         assert_eq!(orders.len(), 1);
         assert_eq!(orders[0].item_id, "item1");
     }
@@ -139,16 +384,583 @@ mod tests {
         let order1 = Order {
             item_id: "item1".to_string(),
             table_id: "table1".to_string(),
+            ..Default::default()
         };
         let order2 = Order {
             item_id: "item2".to_string(),
             table_id: "table2".to_string(),
+            ..Default::default()
         };
 
         service.put_order("order1".to_string(), order1).unwrap();
         service.put_order("order2".to_string(), order2).unwrap();
 
-        let orders = service.get_orders(None, None).unwrap();
+        let orders = service.get_orders(&OrderQuery::default()).unwrap();
+        assert_eq!(orders.len(), 2);
+    }
+
+    #[test]
+    fn test_get_orders_by_item_id_across_tables() {
+        let service = setup_service();
+        for (order_id, item_id, table_id) in [
+            ("order1", "item1", "table1"),
+            ("order2", "item1", "table2"),
+            ("order3", "item2", "table2"),
+        ] {
+            service.put_order(order_id.to_string(), Order {
+                item_id: item_id.to_string(),
+                table_id: table_id.to_string(),
+                ..Default::default()
+            }).unwrap();
+        }
+
+        let mut orders = service.get_orders(&OrderQuery {
+            item_ids: vec!["item1".to_string()],
+            ..Default::default()
+        }).unwrap();
+        orders.sort_by(|a, b| a.order_id.cmp(&b.order_id));
         assert_eq!(orders.len(), 2);
+        assert_eq!(orders[0].order_id, "order1");
+        assert_eq!(orders[1].order_id, "order2");
+
+        let orders = service.get_orders(&OrderQuery {
+            table_ids: vec!["table2".to_string()],
+            item_ids: vec!["item1".to_string()],
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].order_id, "order2");
+
+        assert!(service.get_orders(&OrderQuery {
+            table_ids: vec!["table1".to_string()],
+            item_ids: vec!["item2".to_string()],
+            ..Default::default()
+        }).unwrap().is_empty());
+        assert!(service.get_orders(&OrderQuery {
+            item_ids: vec!["item3".to_string()],
+            ..Default::default()
+        }).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_delete_order_prunes_indexes() {
+        let service = setup_service();
+        service.put_order("order1".to_string(), Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        }).unwrap();
+        service.put_order("order2".to_string(), Order {
+            item_id: "item2".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(service.index_len(), (1, 2));
+
+        service.delete_order("order1".to_string()).unwrap();
+        assert_eq!(service.index_len(), (1, 1));
+        assert!(service.get_orders(&OrderQuery {
+            item_ids: vec!["item1".to_string()],
+            ..Default::default()
+        }).unwrap().is_empty());
+
+        service.delete_order("order2".to_string()).unwrap();
+        assert_eq!(service.index_len(), (0, 0));
+        assert!(service.get_orders(&OrderQuery {
+            table_ids: vec!["table1".to_string()],
+            ..Default::default()
+        }).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_indexes_stay_consistent_under_contention() {
+        let service = setup_service();
+
+        std::thread::scope(|scope| {
+            for writer in 0..8 {
+                let service = &service;
+                scope.spawn(move || {
+                    for i in 0..500 {
+                        let order_id = format!("order{}_{}", writer, i);
+                        service.put_order(order_id.clone(), Order {
+                            item_id: format!("item{}", i % 5),
+                            table_id: format!("table{}", (writer + i) % 3),
+                            ..Default::default()
+                        }).unwrap();
+                        if i % 2 == 0 {
+                            service.delete_order(order_id).unwrap();
+                        }
+                    }
+                });
+            }
+
+            for _ in 0..4 {
+                let service = &service;
+                scope.spawn(move || {
+                    for i in 0..500 {
+                        service.check_consistency().unwrap();
+
+                        let table_id = format!("table{}", i % 3);
+                        let item_id = format!("item{}", i % 5);
+                        for order in service.get_orders(&OrderQuery {
+                            table_ids: vec![table_id.clone()],
+                            item_ids: vec![item_id.clone()],
+                            ..Default::default()
+                        }).unwrap() {
+                            assert_eq!(order.table_id, table_id);
+                            assert_eq!(order.item_id, item_id);
+                        }
+                    }
+                });
+            }
+        });
+
+        service.check_consistency().unwrap();
+        assert_eq!(service.get_orders(&OrderQuery::default()).unwrap().len(), 8 * 250);
+    }
+
+    #[test]
+    fn test_order_lifecycle() {
+        let service = setup_service();
+        let order = Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        };
+
+        let placed = service.put_order("order1".to_string(), order).unwrap().into_order();
+        assert_eq!(placed.status, OrderStatus::Placed);
+
+        for status in [OrderStatus::Cooking, OrderStatus::Ready, OrderStatus::Served] {
+            let updated = service.update_status("order1".to_string(), status).unwrap();
+            assert_eq!(updated.status, status);
+            assert_eq!(updated.cooking_time, placed.cooking_time);
+        }
+
+        let orders = service.get_orders(&OrderQuery {
+            statuses: vec![OrderStatus::Served],
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(orders.len(), 1);
+        service.check_consistency().unwrap();
+    }
+
+    #[test]
+    fn test_invalid_transition_fails() {
+        let service = setup_service();
+        let order = Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        };
+        service.put_order("order1".to_string(), order).unwrap();
+
+        let res = service.update_status("order1".to_string(), OrderStatus::Served);
+        if let Err(OrderServiceError::InvalidTransition { order_id, from, to }) = res {
+            assert_eq!(order_id, "order1");
+            assert_eq!(from, OrderStatus::Placed);
+            assert_eq!(to, OrderStatus::Served);
+        } else {
+            panic!("Expected InvalidTransition error.");
+        }
+
+        service.update_status("order1".to_string(), OrderStatus::Cancelled).unwrap();
+        let res = service.update_status("order1".to_string(), OrderStatus::Cooking);
+        assert!(matches!(res, Err(OrderServiceError::InvalidTransition { .. })));
+
+        let res = service.update_status("order2".to_string(), OrderStatus::Cooking);
+        assert!(matches!(res, Err(OrderServiceError::OrderNotFound(_))));
+    }
+
+    #[test]
+    fn test_get_orders_by_status() {
+        let service = setup_service();
+        for (order_id, table_id) in [("order1", "table1"), ("order2", "table1"), ("order3", "table2")] {
+            service.put_order(order_id.to_string(), Order {
+                item_id: "item1".to_string(),
+                table_id: table_id.to_string(),
+                ..Default::default()
+            }).unwrap();
+        }
+        service.update_status("order1".to_string(), OrderStatus::Cooking).unwrap();
+        service.update_status("order3".to_string(), OrderStatus::Cooking).unwrap();
+
+        assert_eq!(service.get_orders(&OrderQuery {
+            statuses: vec![OrderStatus::Cooking],
+            ..Default::default()
+        }).unwrap().len(), 2);
+        assert_eq!(service.get_orders(&OrderQuery {
+            statuses: vec![OrderStatus::Ready],
+            ..Default::default()
+        }).unwrap().len(), 0);
+
+        let orders = service.get_orders(&OrderQuery {
+            table_ids: vec!["table1".to_string()],
+            item_ids: vec!["item1".to_string()],
+            statuses: vec![OrderStatus::Placed],
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].order_id, "order2");
+    }
+
+    #[test]
+    fn test_get_orders_by_multiple_values() {
+        let service = setup_service();
+        let orders = [
+            ("order1", "item1", "table1"),
+            ("order2", "item2", "table1"),
+            ("order3", "item1", "table2"),
+            ("order4", "item3", "table3"),
+        ];
+        for (order_id, item_id, table_id) in orders {
+            service.put_order(order_id.to_string(), Order {
+                item_id: item_id.to_string(),
+                table_id: table_id.to_string(),
+                ..Default::default()
+            }).unwrap();
+        }
+        service.update_status("order2".to_string(), OrderStatus::Cooking).unwrap();
+        service.update_status("order4".to_string(), OrderStatus::Cooking).unwrap();
+        service.check_consistency().unwrap();
+
+        let order_ids = |query: OrderQuery| {
+            let mut ids: Vec<String> = service.get_orders(&query).unwrap().into_iter().map(|order| order.order_id).collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(order_ids(OrderQuery {
+            table_ids: vec!["table1".to_string(), "table2".to_string()],
+            ..Default::default()
+        }), vec!["order1", "order2", "order3"]);
+        assert_eq!(order_ids(OrderQuery {
+            item_ids: vec!["item2".to_string(), "item3".to_string()],
+            ..Default::default()
+        }), vec!["order2", "order4"]);
+        assert_eq!(order_ids(OrderQuery {
+            statuses: vec![OrderStatus::Placed, OrderStatus::Cooking],
+            ..Default::default()
+        }), vec!["order1", "order2", "order3", "order4"]);
+
+        // Values within a field are alternatives, fields narrow each other down.
+        assert_eq!(order_ids(OrderQuery {
+            table_ids: vec!["table1".to_string(), "table3".to_string()],
+            item_ids: vec!["item1".to_string(), "item3".to_string()],
+            ..Default::default()
+        }), vec!["order1", "order4"]);
+        assert_eq!(order_ids(OrderQuery {
+            table_ids: vec!["table1".to_string(), "table3".to_string()],
+            statuses: vec![OrderStatus::Cooking],
+            ..Default::default()
+        }), vec!["order2", "order4"]);
+
+        // Repeating a value does not repeat the orders it matches.
+        assert_eq!(order_ids(OrderQuery {
+            table_ids: vec!["table1".to_string(), "table1".to_string()],
+            item_ids: vec!["item1".to_string(), "item1".to_string()],
+            ..Default::default()
+        }), vec!["order1"]);
+
+        assert_eq!(order_ids(OrderQuery {
+            table_ids: vec!["table1".to_string(), "table2".to_string()],
+            created_after: Some(chrono::Utc::now() + Duration::hours(1)),
+            ..Default::default()
+        }), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_order_timestamps() {
+        let service = setup_service();
+        let placed = service.put_order("order1".to_string(), Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        }).unwrap().into_order();
+        assert_eq!(placed.created_at, placed.updated_at);
+        assert_eq!(placed.estimated_ready_at, placed.created_at + Duration::minutes(placed.cooking_time as i64));
+
+        let updated = service.update_status("order1".to_string(), OrderStatus::Cooking).unwrap();
+        assert_eq!(updated.created_at, placed.created_at);
+        assert!(updated.updated_at >= placed.updated_at);
+    }
+
+    #[test]
+    fn test_get_orders_by_creation_time() {
+        let service = setup_service();
+        let mut created = Vec::new();
+        for (order_id, table_id) in [("order1", "table1"), ("order2", "table2"), ("order3", "table1")] {
+            let order = service.put_order(order_id.to_string(), Order {
+                item_id: "item1".to_string(),
+                table_id: table_id.to_string(),
+                ..Default::default()
+            }).unwrap().into_order();
+            created.push(order.created_at);
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+
+        let ids = |orders: Vec<OrderResult>| -> Vec<String> {
+            let mut ids: Vec<String> = orders.into_iter().map(|o| o.order_id).collect();
+            ids.sort();
+            ids
+        };
+
+        let orders = service.get_orders(&OrderQuery { created_after: Some(created[1]), ..Default::default() }).unwrap();
+        assert_eq!(ids(orders), vec!["order2", "order3"]);
+
+        let orders = service.get_orders(&OrderQuery {
+            created_before: Some(created[1]),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(ids(orders), vec!["order1"]);
+
+        let orders = service.get_orders(&OrderQuery {
+            table_ids: vec!["table1".to_string()],
+            created_after: Some(created[1]),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(ids(orders), vec!["order3"]);
+
+        assert!(service.get_orders(&OrderQuery {
+            created_after: Some(created[2]),
+            created_before: Some(created[0]),
+            ..Default::default()
+        }).unwrap().is_empty());
+
+        service.delete_order("order2".to_string()).unwrap();
+        let orders = service.get_orders(&OrderQuery {
+            created_after: Some(created[1]),
+            created_before: Some(created[2]),
+            ..Default::default()
+        }).unwrap();
+        assert!(orders.is_empty());
+        service.check_consistency().unwrap();
+    }
+
+    #[test]
+    fn test_put_order_with_modifiers() {
+        let service = setup_service();
+        let modifiers = vec![
+            Modifier::Remove { ingredient: "onion".to_string() },
+            Modifier::Add { ingredient: "bacon".to_string() },
+            Modifier::Doneness { level: Doneness::MediumRare },
+        ];
+        let placed = service.put_order("order1".to_string(), Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            quantity: 2,
+            modifiers: modifiers.clone(),
+            notes: Some("sauce on the side".to_string()),
+            placed_by: Some("watch-17".to_string()),
+        }).unwrap().into_order();
+        assert_eq!(placed.quantity, 2);
+        assert_eq!(placed.modifiers, modifiers);
+        assert_eq!(placed.notes.as_deref(), Some("sauce on the side"));
+        assert_eq!(placed.placed_by.as_deref(), Some("watch-17"));
+
+        let orders = service.get_orders(&OrderQuery::default()).unwrap();
+        assert_eq!(orders[0].modifiers, modifiers);
+    }
+
+    #[test]
+    fn test_put_invalid_order_fails() {
+        let service = setup_service();
+        let invalid = [
+            Order { quantity: 0, ..Default::default() },
+            Order { quantity: MAX_QUANTITY + 1, ..Default::default() },
+            Order { modifiers: vec![Modifier::Add { ingredient: " ".to_string() }], ..Default::default() },
+            Order { modifiers: vec![Modifier::Add { ingredient: "x".repeat(MAX_INGREDIENT_LEN + 1) }], ..Default::default() },
+            Order {
+                modifiers: vec![
+                    Modifier::Add { ingredient: "onion".to_string() },
+                    Modifier::Remove { ingredient: "Onion".to_string() },
+                ],
+                ..Default::default()
+            },
+            Order {
+                modifiers: vec![
+                    Modifier::Doneness { level: Doneness::Rare },
+                    Modifier::Doneness { level: Doneness::WellDone },
+                ],
+                ..Default::default()
+            },
+            Order {
+                modifiers: (0..=MAX_MODIFIERS).map(|i| Modifier::Add { ingredient: format!("extra{}", i) }).collect(),
+                ..Default::default()
+            },
+            Order { notes: Some("x".repeat(MAX_NOTES_LEN + 1)), ..Default::default() },
+        ];
+
+        for (i, order) in invalid.into_iter().enumerate() {
+            let res = service.put_order(format!("order{}", i), order);
+            assert!(matches!(res, Err(OrderServiceError::InvalidOrder(_))), "order{} was accepted", i);
+        }
+        assert!(service.get_orders(&OrderQuery::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_get_order_by_id() {
+        let service = setup_service();
+        let created = service.put_order("order1".to_string(), Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        }).unwrap().into_order();
+        service.update_status("order1".to_string(), OrderStatus::Cooking).unwrap();
+
+        let order = service.get_order_by_id("order1".to_string()).unwrap();
+        assert_eq!(order.cooking_time, created.cooking_time);
+        assert_eq!(order.status, OrderStatus::Cooking);
+
+        service.delete_order("order1".to_string()).unwrap();
+        let res = service.get_order_by_id("order1".to_string());
+        assert!(matches!(res, Err(OrderServiceError::OrderNotFound(id)) if id == "order1"));
+    }
+
+    // Reads all pages one by one, creating and deleting orders in between.
+    fn read_all_pages(service: &dyn OrderService, page: PageRequest) -> Vec<String> {
+        let mut page = page;
+        let mut seen = Vec::new();
+        for i in 0.. {
+            let res = service.get_orders_page(&OrderQuery::default(), &page).unwrap();
+            assert!(res.orders.len() <= page.limit);
+            seen.extend(res.orders.into_iter().map(|order| order.order_id));
+
+            service.put_order(format!("new{}", i), Order {
+                item_id: "item1".to_string(),
+                table_id: "table1".to_string(),
+                ..Default::default()
+            }).unwrap();
+            if i == 0 {
+                service.delete_order("order0".to_string()).unwrap();
+                service.delete_order("order5".to_string()).unwrap();
+            }
+
+            match res.next {
+                Some(next) => page.after = Some(next),
+                None => break,
+            }
+        }
+        seen
+    }
+
+    fn seed_orders(service: &dyn OrderService) {
+        for i in 0..7 {
+            service.put_order(format!("order{}", i), Order {
+                item_id: "item1".to_string(),
+                table_id: format!("table{}", i % 2),
+                ..Default::default()
+            }).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_pages_survive_concurrent_changes() {
+        for (sort, direction) in [
+            (SortKey::Created, SortDirection::Asc),
+            (SortKey::Created, SortDirection::Desc),
+            (SortKey::Table, SortDirection::Asc),
+        ] {
+            let service = setup_service();
+            seed_orders(&service);
+
+            let seen = read_all_pages(&service, PageRequest { sort, direction, limit: 2, after: None });
+
+            // Every order that existed throughout is returned exactly once, the ones deleted
+            // after the first page only if they were on it.
+            let mut unique = seen.clone();
+            unique.sort();
+            unique.dedup();
+            assert_eq!(unique.len(), seen.len(), "{:?} {:?}: {:?}", sort, direction, seen);
+            for i in 1..7 {
+                if i != 5 {
+                    assert!(seen.contains(&format!("order{}", i)), "{:?} {:?}: {:?}", sort, direction, seen);
+                }
+            }
+            if direction == SortDirection::Asc && sort == SortKey::Created {
+                assert!(!seen.contains(&"order5".to_string()));
+                assert!(seen.contains(&"new0".to_string()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_filtered_pages() {
+        let service = setup_service();
+        seed_orders(&service);
+
+        let page = PageRequest { sort: SortKey::Created, direction: SortDirection::Desc, limit: 10, after: None };
+        let res = service.get_orders_page(&OrderQuery {
+            table_ids: vec!["table0".to_string()],
+            ..Default::default()
+        }, &page).unwrap();
+        let ids: Vec<String> = res.orders.into_iter().map(|order| order.order_id).collect();
+        assert_eq!(ids, vec!["order6", "order4", "order2", "order0"]);
+        assert!(res.next.is_none());
+    }
+
+    fn describe(changes: &Changes) -> Vec<(u64, &str, bool)> {
+        changes.changes.iter().map(|change| match change {
+            Change::Put(order) => (order.seq, order.order_id.as_str(), false),
+            Change::Deleted(tombstone) => (tombstone.seq, tombstone.order_id.as_str(), true),
+        }).collect()
+    }
+
+    #[test]
+    fn test_get_changes() {
+        let service = setup_service();
+        let order = || Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        };
+        for order_id in ["order1", "order2", "order3"] {
+            service.put_order(order_id.to_string(), order()).unwrap();
+        }
+        service.update_status("order1".to_string(), OrderStatus::Cooking).unwrap();
+        service.delete_order("order2".to_string()).unwrap();
+        service.put_order("order2".to_string(), order()).unwrap();
+        // Neither a rejected mutation nor a retry gets a sequence number.
+        service.update_status("order3".to_string(), OrderStatus::Served).unwrap_err();
+        service.put_order("order3".to_string(), order()).unwrap();
+        service.check_consistency().unwrap();
+
+        let changes = service.get_changes(Some(0)).unwrap();
+        assert_eq!(changes.last_seq, 6);
+        assert_eq!(describe(&changes), vec![
+            (3, "order3", false),
+            (4, "order1", false),
+            (5, "order2", true),
+            (6, "order2", false),
+        ]);
+        assert_eq!(describe(&service.get_changes(Some(4)).unwrap()), vec![(5, "order2", true), (6, "order2", false)]);
+        assert!(service.get_changes(Some(6)).unwrap().changes.is_empty());
+
+        let changes = service.get_changes(None).unwrap();
+        assert_eq!(changes.last_seq, 6);
+        assert_eq!(describe(&changes), vec![(3, "order3", false), (4, "order1", false), (6, "order2", false)]);
+
+        assert!(matches!(service.get_changes(Some(7)), Err(OrderServiceError::ChangesCompacted(7))));
+    }
+
+    #[test]
+    fn test_get_changes_after_compaction() {
+        let service = setup_service();
+        for i in 0..=MAX_TOMBSTONES {
+            let order_id = format!("order{}", i);
+            service.put_order(order_id.clone(), Order {
+                item_id: "item1".to_string(),
+                table_id: "table1".to_string(),
+                ..Default::default()
+            }).unwrap();
+            service.delete_order(order_id).unwrap();
+        }
+
+        // The tombstone of the first deletion, with sequence number 2, was dropped.
+        assert!(matches!(service.get_changes(Some(1)), Err(OrderServiceError::ChangesCompacted(1))));
+        let changes = service.get_changes(Some(2)).unwrap();
+        assert_eq!(changes.changes.len(), MAX_TOMBSTONES);
+        assert_eq!(changes.changes[0].seq(), 4);
+        service.check_consistency().unwrap();
     }
 }
@@ -0,0 +1,180 @@
+use std::cmp::Ordering;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use rocket::serde::{Deserialize, Serialize};
+use rocket::serde::json::serde_json;
+use crate::order_service::{format_timestamp, OrderResult};
+
+// Orders per page when the client does not ask for a limit, and the most it may ask for.
+pub const DEFAULT_LIMIT: usize = 100;
+pub const MAX_LIMIT: usize = 1000;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum SortKey {
+    #[default]
+    Created,
+    Table,
+    Item,
+}
+
+impl SortKey {
+    pub fn parse(s: &str) -> Option<SortKey> {
+        match s {
+            "created" => Some(SortKey::Created),
+            "table" => Some(SortKey::Table),
+            "item" => Some(SortKey::Item),
+            _ => None,
+        }
+    }
+
+    // The value orders are sorted by. Timestamps are formatted with a fixed number of digits,
+    // so that they sort as strings.
+    pub fn value(&self, order: &OrderResult) -> String {
+        match self {
+            SortKey::Created => format_timestamp(&order.created_at),
+            SortKey::Table => order.table_id.clone(),
+            SortKey::Item => order.item_id.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortDirection {
+    pub fn parse(s: &str) -> Option<SortDirection> {
+        match s {
+            "asc" => Some(SortDirection::Asc),
+            "desc" => Some(SortDirection::Desc),
+            _ => None,
+        }
+    }
+}
+
+// Cursor marks the last order of a page, the next page starts right after it.
+//
+// Orders are sorted by (sort key value, order id), which is unique, so a cursor keeps its place
+// when orders are created or deleted between pages: no order is returned twice or skipped, and
+// new orders show up on a later page if they sort after the cursor.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "rocket::serde")]
+pub struct Cursor {
+    pub sort: SortKey,
+    pub direction: SortDirection,
+    pub value: String,
+    pub order_id: String,
+}
+
+impl Cursor {
+    pub fn after(order: &OrderResult, sort: SortKey, direction: SortDirection) -> Cursor {
+        Cursor {
+            sort,
+            direction,
+            value: sort.value(order),
+            order_id: order.order_id.clone(),
+        }
+    }
+
+    // Clients get the cursor as an opaque string: URL-safe base64 of its JSON.
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("cursor is always serializable");
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    pub fn decode(s: &str) -> Option<Cursor> {
+        let json = URL_SAFE_NO_PAD.decode(s).ok()?;
+        let cursor: Cursor = serde_json::from_slice(&json).ok()?;
+        if cursor.sort == SortKey::Created && cursor.created_at().is_none() {
+            return None;
+        }
+        Some(cursor)
+    }
+
+    // The creation time of the last order of the page, for cursors sorted by creation time.
+    pub fn created_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.value).ok().map(|ts| ts.with_timezone(&Utc))
+    }
+
+    // Whether `order` belongs to a page after the cursor.
+    pub fn precedes(&self, order: &OrderResult) -> bool {
+        let ordering = self.sort.value(order).cmp(&self.value)
+            .then_with(|| order.order_id.cmp(&self.order_id));
+        match self.direction {
+            SortDirection::Asc => ordering == Ordering::Greater,
+            SortDirection::Desc => ordering == Ordering::Less,
+        }
+    }
+}
+
+// PageRequest asks for at most `limit` orders in the given order, starting after the cursor.
+#[derive(Clone, Debug)]
+pub struct PageRequest {
+    pub sort: SortKey,
+    pub direction: SortDirection,
+    pub limit: usize,
+    pub after: Option<Cursor>,
+}
+
+impl PageRequest {
+    // A single page holding all orders, oldest first.
+    #[cfg(test)]
+    pub fn all() -> PageRequest {
+        PageRequest {
+            sort: SortKey::default(),
+            direction: SortDirection::default(),
+            limit: usize::MAX,
+            after: None,
+        }
+    }
+
+    pub fn compare(&self, a: &OrderResult, b: &OrderResult) -> Ordering {
+        let ordering = match self.sort {
+            SortKey::Created => a.created_at.cmp(&b.created_at),
+            SortKey::Table => a.table_id.cmp(&b.table_id),
+            SortKey::Item => a.item_id.cmp(&b.item_id),
+        };
+        let ordering = ordering.then_with(|| a.order_id.cmp(&b.order_id));
+        match self.direction {
+            SortDirection::Asc => ordering,
+            SortDirection::Desc => ordering.reverse(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct OrderPage {
+    pub orders: Vec<OrderResult>,
+    // Where the next page starts, None if this is the last page.
+    pub next: Option<Cursor>,
+}
+
+impl OrderPage {
+    // Builds a page out of orders that are already sorted and start after the request's cursor.
+    // Fetching up to limit + 1 of them tells whether there is a next page.
+    pub fn from_sorted(mut orders: Vec<OrderResult>, page: &PageRequest) -> OrderPage {
+        let next = if orders.len() > page.limit {
+            orders.truncate(page.limit);
+            orders.last().map(|last| Cursor::after(last, page.sort, page.direction))
+        } else {
+            None
+        };
+        OrderPage { orders, next }
+    }
+}
+
+// Sorts the orders matching a query and cuts out the requested page.
+pub fn paginate(mut orders: Vec<OrderResult>, page: &PageRequest) -> OrderPage {
+    if let Some(cursor) = &page.after {
+        orders.retain(|order| cursor.precedes(order));
+    }
+    orders.sort_by(|a, b| page.compare(a, b));
+    orders.truncate(page.limit.saturating_add(1));
+    OrderPage::from_sorted(orders, page)
+}
@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+    use crate::order_service::{Order, OrderResult};
+    use crate::pagination::{paginate, Cursor, PageRequest, SortDirection, SortKey};
+
+    fn order(order_id: &str, table_id: &str, minutes: i64) -> OrderResult {
+        let mut order = OrderResult::placed(order_id.to_string(), Order {
+            item_id: "item1".to_string(),
+            table_id: table_id.to_string(),
+            ..Default::default()
+        }, 10);
+        order.created_at = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap() + Duration::minutes(minutes);
+        order
+    }
+
+    fn ids(orders: &[OrderResult]) -> Vec<&str> {
+        orders.iter().map(|order| order.order_id.as_str()).collect()
+    }
+
+    fn page_request(sort: SortKey, direction: SortDirection, limit: usize) -> PageRequest {
+        PageRequest { sort, direction, limit, after: None }
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = Cursor::after(&order("order1", "table1", 0), SortKey::Created, SortDirection::Desc);
+        assert_eq!(cursor.value, "2024-05-01T12:00:00.000000Z");
+        assert_eq!(Cursor::decode(&cursor.encode()), Some(cursor));
+    }
+
+    #[test]
+    fn test_malformed_cursor_is_rejected() {
+        assert_eq!(Cursor::decode("not a cursor"), None);
+        assert_eq!(Cursor::decode(""), None);
+
+        let mut cursor = Cursor::after(&order("order1", "table1", 0), SortKey::Created, SortDirection::Asc);
+        cursor.value = "yesterday".to_string();
+        assert_eq!(Cursor::decode(&cursor.encode()), None);
+    }
+
+    #[test]
+    fn test_paginate_by_creation_time() {
+        let orders = vec![order("c", "table1", 2), order("a", "table1", 0), order("b", "table2", 1)];
+
+        let mut page = page_request(SortKey::Created, SortDirection::Asc, 2);
+        let first = paginate(orders.clone(), &page);
+        assert_eq!(ids(&first.orders), vec!["a", "b"]);
+
+        page.after = first.next;
+        let second = paginate(orders.clone(), &page);
+        assert_eq!(ids(&second.orders), vec!["c"]);
+        assert!(second.next.is_none());
+
+        let page = page_request(SortKey::Created, SortDirection::Desc, 10);
+        assert_eq!(ids(&paginate(orders, &page).orders), vec!["c", "b", "a"]);
+    }
+
+    #[test]
+    fn test_ties_are_broken_by_order_id() {
+        let orders = vec![order("b", "table1", 0), order("c", "table2", 0), order("a", "table1", 0)];
+
+        let mut page = page_request(SortKey::Table, SortDirection::Asc, 1);
+        let mut seen = Vec::new();
+        loop {
+            let res = paginate(orders.clone(), &page);
+            seen.extend(res.orders.into_iter().map(|order| order.order_id));
+            match res.next {
+                Some(next) => page.after = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(seen, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_exact_last_page_has_no_next_cursor() {
+        let orders = vec![order("a", "table1", 0), order("b", "table1", 1)];
+
+        let res = paginate(orders, &page_request(SortKey::Item, SortDirection::Asc, 2));
+        assert_eq!(res.orders.len(), 2);
+        assert!(res.next.is_none());
+    }
+}
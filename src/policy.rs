@@ -0,0 +1,115 @@
+use rocket::http::{Method, Status};
+use rocket::outcome::try_outcome;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::auth::{AuthError, Authenticated, Claims};
+use crate::order_service::OrderResult;
+
+// Role is what a staff device may do, granted at enrollment and carried in its token.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, ToSchema)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum Role {
+    Waiter,
+    Kitchen,
+    Manager,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Role::Waiter, Role::Kitchen, Role::Manager];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Waiter => "waiter",
+            Role::Kitchen => "kitchen",
+            Role::Manager => "manager",
+        }
+    }
+}
+
+const EVERYONE: &[Role] = &Role::ALL;
+const MANAGERS: &[Role] = &[Role::Manager];
+
+// POLICY lists the roles allowed to call each route, keyed by the route's method and path as
+// mounted. Managers may call every route, including routes missing from the table, e.g. bulk
+// operations; everyone else is forbidden from those.
+//
+// Changing the menu, the tables and the webhooks, as well as reading the webhooks, their
+// deliveries and the audit log, is up to managers. Reading the menu and the tables is open to
+// anyone and needs no token.
+//
+// A waiter may only delete orders they placed or orders for a table of their section, which
+// depends on the order and is checked by delete_order, see Claims::may_delete.
+pub const POLICY: &[(Method, &str, &[Role])] = &[
+    (Method::Put, "/order/<id>", &[Role::Waiter, Role::Manager]),
+    (Method::Get, "/order/<id>", EVERYONE),
+    (Method::Delete, "/order/<id>", &[Role::Waiter, Role::Manager]),
+    (Method::Post, "/order/<id>/start", &[Role::Kitchen, Role::Manager]),
+    (Method::Post, "/order/<id>/ready", &[Role::Kitchen, Role::Manager]),
+    (Method::Post, "/order/<id>/served", &[Role::Kitchen, Role::Manager]),
+    (Method::Post, "/order/<id>/cancel", &[Role::Kitchen, Role::Manager]),
+    (Method::Get, "/orders", EVERYONE),
+    (Method::Get, "/orders/events", EVERYONE),
+    (Method::Get, "/orders/changes", EVERYONE),
+    (Method::Put, "/menu/<id>", MANAGERS),
+    (Method::Delete, "/menu/<id>", MANAGERS),
+    (Method::Put, "/tables/<id>", MANAGERS),
+    (Method::Post, "/tables/<id>/open", MANAGERS),
+    (Method::Post, "/tables/<id>/close", MANAGERS),
+    (Method::Delete, "/tables/<id>", MANAGERS),
+    (Method::Get, "/webhooks", MANAGERS),
+    (Method::Get, "/webhooks/<id>", MANAGERS),
+    (Method::Put, "/webhooks/<id>", MANAGERS),
+    (Method::Delete, "/webhooks/<id>", MANAGERS),
+    (Method::Get, "/webhooks/deliveries", MANAGERS),
+    (Method::Get, "/audit", MANAGERS),
+    (Method::Get, "/audit/export", MANAGERS),
+];
+
+pub fn is_allowed(role: Role, method: Method, path: &str) -> bool {
+    role == Role::Manager
+        || POLICY
+            .iter()
+            .any(|(m, p, roles)| *m == method && *p == path && roles.contains(&role))
+}
+
+impl Claims {
+    // Whether the device may delete the order, placed for a table of the given section.
+    pub fn may_delete(&self, order: &OrderResult, section: Option<&str>) -> bool {
+        match self.role {
+            Role::Manager => true,
+            Role::Kitchen => false,
+            Role::Waiter => {
+                order.placed_by.as_deref() == Some(self.sub.as_str())
+                    || (self.section.is_some() && self.section.as_deref() == section)
+            }
+        }
+    }
+}
+
+// Authorized is a request guard that requires an Authenticated device whose role POLICY allows
+// to call the route. Requests from other devices fail with 403.
+pub struct Authorized {
+    pub claims: Claims,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Authorized {
+    type Error = AuthError;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, AuthError> {
+        let claims = try_outcome!(req.guard::<Authenticated>().await).claims;
+
+        // Paths are compared without the query, e.g. /orders for /orders/?<params..>.
+        let path = req.route()
+            .map(|route| route.uri.path().trim_end_matches('/').to_string())
+            .unwrap_or_default();
+        if is_allowed(claims.role, req.method(), &path) {
+            return Outcome::Success(Authorized { claims });
+        }
+
+        let e = AuthError::Forbidden(format!("A {} may not {} {}", claims.role.as_str(), req.method(), path));
+        crate::auth::set_failure(req, &e);
+        Outcome::Error((Status::Forbidden, e))
+    }
+}
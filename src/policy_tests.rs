@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod tests {
+    use rocket::http::Method;
+    use crate::auth::Claims;
+    use crate::order_service::{Order, OrderResult};
+    use crate::policy::{is_allowed, Role, POLICY};
+
+    fn claims(device_id: &str, role: Role, section: Option<&str>) -> Claims {
+        Claims {
+            sub: device_id.to_string(),
+            role,
+            section: section.map(str::to_string),
+            iat: 0,
+            exp: 0,
+        }
+    }
+
+    fn order(placed_by: Option<&str>) -> OrderResult {
+        OrderResult::placed("order1".to_string(), Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            placed_by: placed_by.map(str::to_string),
+            ..Order::default()
+        }, 10)
+    }
+
+    #[test]
+    fn test_roles_per_route() {
+        assert!(is_allowed(Role::Waiter, Method::Put, "/order/<id>"));
+        assert!(!is_allowed(Role::Kitchen, Method::Put, "/order/<id>"));
+        assert!(is_allowed(Role::Waiter, Method::Delete, "/order/<id>"));
+        assert!(!is_allowed(Role::Kitchen, Method::Delete, "/order/<id>"));
+        assert!(!is_allowed(Role::Waiter, Method::Post, "/order/<id>/start"));
+        assert!(is_allowed(Role::Kitchen, Method::Post, "/order/<id>/served"));
+        for role in Role::ALL {
+            assert!(is_allowed(role, Method::Get, "/order/<id>"));
+            assert!(is_allowed(role, Method::Get, "/orders"));
+        }
+        for role in [Role::Waiter, Role::Kitchen] {
+            assert!(!is_allowed(role, Method::Put, "/menu/<id>"));
+            assert!(!is_allowed(role, Method::Post, "/tables/<id>/close"));
+            assert!(!is_allowed(role, Method::Get, "/webhooks"));
+        }
+    }
+
+    #[test]
+    fn test_managers_may_call_every_route() {
+        for (method, path, _) in POLICY {
+            assert!(is_allowed(Role::Manager, *method, path), "{} {}", method, path);
+        }
+        // Routes missing from the table are for managers only.
+        assert!(is_allowed(Role::Manager, Method::Delete, "/orders"));
+        assert!(!is_allowed(Role::Waiter, Method::Delete, "/orders"));
+        assert!(!is_allowed(Role::Kitchen, Method::Delete, "/orders"));
+    }
+
+    #[test]
+    fn test_waiters_delete_own_or_section_orders() {
+        let waiter = claims("watch-17", Role::Waiter, Some("terrace"));
+        assert!(waiter.may_delete(&order(Some("watch-17")), Some("main")));
+        assert!(waiter.may_delete(&order(Some("watch-18")), Some("terrace")));
+        assert!(!waiter.may_delete(&order(Some("watch-18")), Some("main")));
+        assert!(!waiter.may_delete(&order(None), None));
+
+        // A waiter without a section does not serve tables whose section is unknown.
+        let waiter = claims("watch-17", Role::Waiter, None);
+        assert!(!waiter.may_delete(&order(Some("watch-18")), None));
+
+        assert!(claims("tablet-1", Role::Manager, None).may_delete(&order(None), None));
+        assert!(!claims("pass-1", Role::Kitchen, Some("main")).may_delete(&order(Some("pass-1")), Some("main")));
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::{Mutex, MutexGuard};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use crate::menu_service::{validate, MenuItem, MenuService, MenuServiceError};
+use crate::sqlite_order_service::migrate;
+
+// SqliteMenuService keeps the menu in the SQLite database file the orders are persisted in,
+// so that orders and the items they refer to survive a restart together.
+// Like SqliteOrderService, it guards a single connection with a Mutex.
+pub struct SqliteMenuService {
+    conn: Mutex<Connection>,
+}
+
+// Opens (or creates) the database at the given path and brings its schema up to date.
+pub fn new_sqlite(path: &str) -> Result<SqliteMenuService, MenuServiceError> {
+    let mut conn = Connection::open(path).map_err(storage_error)?;
+    migrate(&mut conn).map_err(|e| MenuServiceError::Storage(e.to_string()))?;
+
+    Ok(SqliteMenuService { conn: Mutex::new(conn) })
+}
+
+fn storage_error(e: rusqlite::Error) -> MenuServiceError {
+    MenuServiceError::Storage(e.to_string())
+}
+
+fn item_from_row(row: &Row) -> rusqlite::Result<MenuItem> {
+    Ok(MenuItem {
+        item_id: row.get("item_id")?,
+        name: row.get("name")?,
+        price_cents: row.get("price_cents")?,
+        category: row.get("category")?,
+        preparation_time: row.get("preparation_time")?,
+        available: row.get("available")?,
+    })
+}
+
+impl SqliteMenuService {
+    fn conn(&self) -> Result<MutexGuard<'_, Connection>, MenuServiceError> {
+        self.conn.lock()
+            .map_err(|_| MenuServiceError::MutexPoisoned("Failed to obtain connection mutex".into()))
+    }
+}
+
+impl MenuService for SqliteMenuService {
+    fn put_item(&self, item: MenuItem) -> Result<MenuItem, MenuServiceError> {
+        validate(&item)?;
+
+        self.conn()?
+            .execute(
+                "INSERT OR REPLACE INTO menu_items (item_id, name, price_cents, category, preparation_time, available)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![item.item_id, item.name, item.price_cents, item.category, item.preparation_time, item.available],
+            )
+            .map_err(storage_error)?;
+
+        Ok(item)
+    }
+
+    fn get_item(&self, item_id: &str) -> Result<MenuItem, MenuServiceError> {
+        self.conn()?
+            .query_row("SELECT * FROM menu_items WHERE item_id = ?1", params![item_id], item_from_row)
+            .optional()
+            .map_err(storage_error)?
+            .ok_or_else(|| MenuServiceError::ItemNotFound(item_id.to_string()))
+    }
+
+    fn get_items(&self, category: Option<String>) -> Result<Vec<MenuItem>, MenuServiceError> {
+        let conn = self.conn()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT * FROM menu_items WHERE ?1 IS NULL OR category = ?1
+                 ORDER BY category, name",
+            )
+            .map_err(storage_error)?;
+        let items = stmt
+            .query_map(params![category], item_from_row)
+            .map_err(storage_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(storage_error)?;
+
+        Ok(items)
+    }
+
+    fn delete_item(&self, item_id: String) -> Result<MenuItem, MenuServiceError> {
+        self.conn()?
+            .query_row("DELETE FROM menu_items WHERE item_id = ?1 RETURNING *", params![item_id], item_from_row)
+            .optional()
+            .map_err(storage_error)?
+            .ok_or(MenuServiceError::ItemNotFound(item_id))
+    }
+}
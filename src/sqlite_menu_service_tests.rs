@@ -0,0 +1,63 @@
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+    use crate::menu_service::{MenuItem, MenuService, MenuServiceError};
+    use crate::sqlite_menu_service::{new_sqlite, SqliteMenuService};
+
+    fn setup_service() -> SqliteMenuService {
+        new_sqlite(":memory:").unwrap()
+    }
+
+    fn menu_item(item_id: &str, name: &str, category: &str) -> MenuItem {
+        MenuItem {
+            item_id: item_id.to_string(),
+            name: name.to_string(),
+            price_cents: 1250,
+            category: category.to_string(),
+            preparation_time: 10,
+            available: true,
+        }
+    }
+
+    #[test]
+    fn test_put_get_and_delete_item() {
+        let service = setup_service();
+        let item = menu_item("burger", "Burger", "mains");
+        assert_eq!(service.put_item(item.clone()).unwrap(), item);
+        assert_eq!(service.get_item("burger").unwrap(), item);
+
+        let mut updated = menu_item("burger", "Cheeseburger", "mains");
+        updated.available = false;
+        service.put_item(updated.clone()).unwrap();
+        assert_eq!(service.get_item("burger").unwrap(), updated);
+
+        assert_eq!(service.delete_item("burger".to_string()).unwrap(), updated);
+        assert!(matches!(service.get_item("burger"), Err(MenuServiceError::ItemNotFound(_))));
+        assert!(matches!(service.delete_item("burger".to_string()), Err(MenuServiceError::ItemNotFound(_))));
+    }
+
+    #[test]
+    fn test_get_items_by_category() {
+        let service = setup_service();
+        service.put_item(menu_item("soup", "Soup", "starters")).unwrap();
+        service.put_item(menu_item("steak", "Steak", "mains")).unwrap();
+        service.put_item(menu_item("burger", "Burger", "mains")).unwrap();
+
+        let ids = |items: Vec<MenuItem>| items.into_iter().map(|item| item.item_id).collect::<Vec<_>>();
+        assert_eq!(ids(service.get_items(None).unwrap()), vec!["burger", "steak", "soup"]);
+        assert_eq!(ids(service.get_items(Some("mains".to_string())).unwrap()), vec!["burger", "steak"]);
+    }
+
+    #[test]
+    fn test_menu_survives_restart() {
+        let path = std::env::temp_dir()
+            .join(format!("restaurant_api_test_{}.db", Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned();
+        let item = menu_item("burger", "Burger", "mains");
+        new_sqlite(&path).unwrap().put_item(item.clone()).unwrap();
+
+        assert_eq!(new_sqlite(&path).unwrap().get_item("burger").unwrap(), item);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
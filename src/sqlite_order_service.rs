@@ -0,0 +1,474 @@
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, Type, ValueRef};
+use rusqlite::{ffi, params, params_from_iter, Connection, OptionalExtension, Row, ToSql};
+use crate::cooking_time::{CookingTimeEstimator, RandomEstimator};
+use crate::order_events::{OrderEventKind, OrderEvents};
+use rocket::serde::json::serde_json;
+use crate::pagination::{OrderPage, PageRequest, SortDirection, SortKey};
+use crate::order_service::{
+    format_timestamp, now, retried, validate, Audit, Change, Changes, Modifier, Order, OrderResult, OrderService, OrderQuery,
+    OrderServiceError, OrderStatus, PutOutcome, Tombstone, MAX_TOMBSTONES,
+};
+
+// Schema migrations, applied in order. The index of the last applied migration is tracked
+// in SQLite's `user_version` pragma, so an existing database file is upgraded in place.
+// The schema covers the menu and the tables too, which share the database file with the orders.
+// Never edit an already released migration, append a new one instead.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE orders (
+        order_id TEXT PRIMARY KEY NOT NULL,
+        item_id TEXT NOT NULL,
+        table_id TEXT NOT NULL,
+        cooking_time INTEGER NOT NULL
+    );
+    CREATE INDEX orders_table_id_idx ON orders(table_id);
+    CREATE INDEX orders_item_id_idx ON orders(item_id);",
+    "ALTER TABLE orders ADD COLUMN status TEXT NOT NULL DEFAULT 'placed';
+    CREATE INDEX orders_status_idx ON orders(status);",
+    // Orders that existed before timestamps were recorded are treated as created by the migration.
+    "ALTER TABLE orders ADD COLUMN created_at TEXT NOT NULL DEFAULT '';
+    ALTER TABLE orders ADD COLUMN updated_at TEXT NOT NULL DEFAULT '';
+    ALTER TABLE orders ADD COLUMN estimated_ready_at TEXT NOT NULL DEFAULT '';
+    UPDATE orders SET
+        created_at = strftime('%Y-%m-%dT%H:%M:%S.000000Z', 'now'),
+        updated_at = strftime('%Y-%m-%dT%H:%M:%S.000000Z', 'now'),
+        estimated_ready_at = strftime('%Y-%m-%dT%H:%M:%S.000000Z', 'now', '+' || cooking_time || ' minutes');
+    CREATE INDEX orders_created_at_idx ON orders(created_at);",
+    "ALTER TABLE orders ADD COLUMN quantity INTEGER NOT NULL DEFAULT 1;
+    ALTER TABLE orders ADD COLUMN modifiers TEXT NOT NULL DEFAULT '[]';
+    ALTER TABLE orders ADD COLUMN notes TEXT;",
+    // changes_state holds a single row. Deletions from before sequence numbers are unknown, so
+    // the change history of an existing database starts out compacted.
+    "ALTER TABLE orders ADD COLUMN seq INTEGER NOT NULL DEFAULT 0;
+    UPDATE orders SET seq = rowid;
+    CREATE INDEX orders_seq_idx ON orders(seq);
+    CREATE TABLE tombstones (
+        seq INTEGER PRIMARY KEY NOT NULL,
+        order_id TEXT NOT NULL
+    );
+    CREATE TABLE changes_state (
+        last_seq INTEGER NOT NULL,
+        compacted_through INTEGER NOT NULL
+    );
+    INSERT INTO changes_state SELECT COALESCE(MAX(seq), 0), COALESCE(MAX(seq), 0) FROM orders;",
+    "ALTER TABLE orders ADD COLUMN placed_by TEXT;",
+    "CREATE TABLE menu_items (
+        item_id TEXT PRIMARY KEY NOT NULL,
+        name TEXT NOT NULL,
+        price_cents INTEGER NOT NULL,
+        category TEXT NOT NULL,
+        preparation_time INTEGER NOT NULL,
+        available INTEGER NOT NULL
+    );",
+    "CREATE TABLE tables (
+        table_id TEXT PRIMARY KEY NOT NULL,
+        capacity INTEGER NOT NULL,
+        section TEXT NOT NULL,
+        status TEXT NOT NULL
+    );",
+    "CREATE INDEX orders_placed_by_idx ON orders(placed_by);",
+];
+
+// SqliteOrderService persists orders in a local SQLite database file, so open orders
+// survive a restart of the application.
+//
+// rusqlite's Connection is not Sync, so a single connection is guarded by a Mutex.
+// That serializes all queries, which is fine for the load of a single restaurant
+// and lets SQLite itself enforce order id uniqueness via the primary key.
+// Cooking times are assigned by a CookingTimeEstimator, random by default.
+// Mutations are published to OrderEvents, if set. Mutations made by other processes sharing
+// the database file are not.
+pub struct SqliteOrderService {
+    conn: Mutex<Connection>,
+    estimator: Arc<dyn CookingTimeEstimator>,
+    events: Option<Arc<OrderEvents>>,
+}
+
+// Opens (or creates) the database at the given path and brings its schema up to date.
+// ":memory:" can be used to get a throwaway database, e.g. in tests.
+pub fn new_sqlite(path: &str) -> Result<SqliteOrderService, OrderServiceError> {
+    let mut conn = Connection::open(path).map_err(storage_error)?;
+    migrate(&mut conn)?;
+
+    Ok(SqliteOrderService {
+        conn: Mutex::new(conn),
+        estimator: Arc::new(RandomEstimator),
+        events: None,
+    })
+}
+
+pub fn migrate(conn: &mut Connection) -> Result<(), OrderServiceError> {
+    let version: usize = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(storage_error)?;
+
+    for (idx, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction().map_err(storage_error)?;
+        tx.execute_batch(migration).map_err(storage_error)?;
+        tx.pragma_update(None, "user_version", idx + 1).map_err(storage_error)?;
+        tx.commit().map_err(storage_error)?;
+    }
+    Ok(())
+}
+
+fn storage_error(e: rusqlite::Error) -> OrderServiceError {
+    OrderServiceError::Storage(e.to_string())
+}
+
+fn is_primary_key_violation(e: &rusqlite::Error) -> bool {
+    matches!(e, rusqlite::Error::SqliteFailure(err, _) if err.extended_code == ffi::SQLITE_CONSTRAINT_PRIMARYKEY)
+}
+
+impl ToSql for OrderStatus {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for OrderStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let s = value.as_str()?;
+        OrderStatus::parse(s).ok_or_else(|| FromSqlError::Other(format!("unknown order status '{}'", s).into()))
+    }
+}
+
+// Timestamps are stored formatted by format_timestamp, so that comparing them as strings
+// orders them chronologically.
+fn get_timestamp(row: &Row, column: &str) -> rusqlite::Result<DateTime<Utc>> {
+    let value: String = row.get(column)?;
+    DateTime::parse_from_rfc3339(&value)
+        .map(|ts| ts.with_timezone(&Utc))
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, e.into()))
+}
+
+// Modifiers are stored as a JSON array, the same way they are serialized in the API.
+fn get_modifiers(row: &Row) -> rusqlite::Result<Vec<Modifier>> {
+    let value: String = row.get("modifiers")?;
+    serde_json::from_str(&value)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, e.into()))
+}
+
+// `column IN (?, ?, ...)` with one placeholder per value.
+fn in_clause(column: &str, values: usize) -> String {
+    format!("{} IN ({})", column, vec!["?"; values].join(", "))
+}
+
+fn order_from_row(row: &Row) -> rusqlite::Result<OrderResult> {
+    Ok(OrderResult {
+        order_id: row.get("order_id")?,
+        item_id: row.get("item_id")?,
+        table_id: row.get("table_id")?,
+        cooking_time: row.get("cooking_time")?,
+        quantity: row.get("quantity")?,
+        modifiers: get_modifiers(row)?,
+        notes: row.get("notes")?,
+        placed_by: row.get("placed_by")?,
+        status: row.get("status")?,
+        created_at: get_timestamp(row, "created_at")?,
+        updated_at: get_timestamp(row, "updated_at")?,
+        estimated_ready_at: get_timestamp(row, "estimated_ready_at")?,
+        seq: row.get("seq")?,
+    })
+}
+
+// Hands out the next sequence number. It must be called within the mutation's transaction, so
+// that the number is only used up if the mutation is committed.
+fn next_seq(tx: &Connection) -> Result<u64, OrderServiceError> {
+    tx.query_row("UPDATE changes_state SET last_seq = last_seq + 1 RETURNING last_seq", [], |row| row.get(0))
+        .map_err(storage_error)
+}
+
+// Keeps the latest MAX_TOMBSTONES tombstones. Changes up to the newest dropped one are compacted.
+fn compact_tombstones(tx: &Connection) -> Result<(), OrderServiceError> {
+    let evicted: Option<u64> = tx
+        .query_row(
+            "SELECT seq FROM tombstones ORDER BY seq DESC LIMIT 1 OFFSET ?1",
+            params![MAX_TOMBSTONES],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(storage_error)?;
+
+    if let Some(seq) = evicted {
+        tx.execute("DELETE FROM tombstones WHERE seq <= ?1", params![seq]).map_err(storage_error)?;
+        tx.execute("UPDATE changes_state SET compacted_through = MAX(compacted_through, ?1)", params![seq])
+            .map_err(storage_error)?;
+    }
+    Ok(())
+}
+
+impl SqliteOrderService {
+    pub fn with_estimator(mut self, estimator: Arc<dyn CookingTimeEstimator>) -> SqliteOrderService {
+        self.estimator = estimator;
+        self
+    }
+
+    pub fn with_events(mut self, events: Arc<OrderEvents>) -> SqliteOrderService {
+        self.events = Some(events);
+        self
+    }
+
+    // Callers must still hold the connection, so that events are published in the order the
+    // mutations were applied in.
+    fn publish(&self, kind: OrderEventKind, order: &OrderResult) {
+        if let Some(events) = &self.events {
+            events.publish(kind, order);
+        }
+    }
+
+    fn conn(&self) -> Result<std::sync::MutexGuard<'_, Connection>, OrderServiceError> {
+        self.conn.lock()
+            .map_err(|_| OrderServiceError::MutexPoisoned("Failed to obtain connection mutex".into()))
+    }
+}
+
+impl OrderService for SqliteOrderService {
+    fn put_order_audited(&self, id: String, order: Order, audit: &dyn Audit) -> Result<PutOutcome, OrderServiceError> {
+        validate(&order)?;
+        let modifiers = serde_json::to_string(&order.modifiers)
+            .map_err(|e| OrderServiceError::Storage(e.to_string()))?;
+        let base_time = self.estimator.base_time(&order);
+
+        let mut conn = self.conn()?;
+
+        let orders_in_progress: usize = conn
+            .query_row(
+                "SELECT COUNT(*) FROM orders WHERE status IN (?1, ?2)",
+                params![OrderStatus::Placed, OrderStatus::Cooking],
+                |row| row.get(0),
+            )
+            .map_err(storage_error)?;
+        let cooking_time = self.estimator.estimate(base_time, orders_in_progress);
+
+        let mut order_result = OrderResult::placed(id.clone(), order.clone(), cooking_time);
+
+        let tx = conn.transaction().map_err(storage_error)?;
+        order_result.seq = next_seq(&tx)?;
+
+        // Duplicates are rejected by the primary key constraint rather than by a prior
+        // lookup, so the check holds even if several processes share the database file.
+        let inserted = tx.execute(
+            "INSERT INTO orders (order_id, item_id, table_id, cooking_time, quantity, modifiers, notes, placed_by,
+                                 status, created_at, updated_at, estimated_ready_at, seq)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                order_result.order_id,
+                order_result.item_id,
+                order_result.table_id,
+                order_result.cooking_time,
+                order_result.quantity,
+                modifiers,
+                order_result.notes,
+                order_result.placed_by,
+                order_result.status,
+                format_timestamp(&order_result.created_at),
+                format_timestamp(&order_result.updated_at),
+                format_timestamp(&order_result.estimated_ready_at),
+                order_result.seq,
+            ],
+        );
+
+        // A failed insert leaves the transaction usable. It is rolled back when dropped, which
+        // also returns the sequence number.
+        match inserted {
+            Ok(_) => {
+                audit.record(None, Some(&order_result))?;
+                tx.commit().map_err(storage_error)?;
+                self.publish(OrderEventKind::Created, &order_result);
+                Ok(PutOutcome::Created(order_result))
+            }
+            Err(e) if is_primary_key_violation(&e) => {
+                let existing = tx
+                    .query_row("SELECT * FROM orders WHERE order_id = ?1", params![id], order_from_row)
+                    .optional()
+                    .map_err(storage_error)?;
+                match existing {
+                    Some(existing) => retried(existing, &order),
+                    // Deleted again in the meantime by another process sharing the file.
+                    None => Err(OrderServiceError::DuplicateOrder { order_id: id, fields: Vec::new() }),
+                }
+            }
+            Err(e) => Err(storage_error(e)),
+        }
+    }
+
+    fn delete_order_audited(
+        &self,
+        order_id: String,
+        condition: &dyn Fn(&OrderResult) -> bool,
+        audit: &dyn Audit,
+    ) -> Result<OrderResult, OrderServiceError> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(storage_error)?;
+
+        let order = tx
+            .query_row("SELECT * FROM orders WHERE order_id = ?1", params![order_id], order_from_row)
+            .optional()
+            .map_err(storage_error)?
+            .ok_or_else(|| OrderServiceError::OrderNotFound(order_id.clone()))?;
+        if !condition(&order) {
+            return Err(OrderServiceError::ConditionFailed(order_id));
+        }
+        tx.execute("DELETE FROM orders WHERE order_id = ?1", params![order_id])
+            .map_err(storage_error)?;
+
+        let seq = next_seq(&tx)?;
+        tx.execute("INSERT INTO tombstones (seq, order_id) VALUES (?1, ?2)", params![seq, order_id])
+            .map_err(storage_error)?;
+        compact_tombstones(&tx)?;
+        audit.record(Some(&order), None)?;
+        tx.commit().map_err(storage_error)?;
+        self.publish(OrderEventKind::Deleted, &order);
+
+        Ok(order)
+    }
+
+    fn update_status_audited(
+        &self,
+        order_id: String,
+        status: OrderStatus,
+        audit: &dyn Audit,
+    ) -> Result<OrderResult, OrderServiceError> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(storage_error)?;
+
+        let before = tx
+            .query_row("SELECT * FROM orders WHERE order_id = ?1", params![order_id], order_from_row)
+            .optional()
+            .map_err(storage_error)?
+            .ok_or_else(|| OrderServiceError::OrderNotFound(order_id.clone()))?;
+        if !before.status.can_transition_to(status) {
+            return Err(OrderServiceError::InvalidTransition { order_id, from: before.status, to: status });
+        }
+
+        let seq = next_seq(&tx)?;
+        let after = tx
+            .query_row(
+                "UPDATE orders SET status = ?2, updated_at = ?3, seq = ?4 WHERE order_id = ?1 RETURNING *",
+                params![order_id, status, format_timestamp(&now()), seq],
+                order_from_row,
+            )
+            .map_err(storage_error)?;
+        audit.record(Some(&before), Some(&after))?;
+        tx.commit().map_err(storage_error)?;
+        self.publish(OrderEventKind::Updated, &after);
+
+        Ok(after)
+    }
+
+    fn get_order_by_id(&self, order_id: String) -> Result<OrderResult, OrderServiceError> {
+        let conn = self.conn()?;
+
+        conn.query_row("SELECT * FROM orders WHERE order_id = ?1", params![order_id], order_from_row)
+            .optional()
+            .map_err(storage_error)?
+            .ok_or(OrderServiceError::OrderNotFound(order_id))
+    }
+
+    fn get_changes(&self, since: Option<u64>) -> Result<Changes, OrderServiceError> {
+        let mut conn = self.conn()?;
+        // Other processes sharing the file may write in between the queries.
+        let tx = conn.transaction().map_err(storage_error)?;
+
+        let (last_seq, compacted_through): (u64, u64) = tx
+            .query_row("SELECT last_seq, compacted_through FROM changes_state", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(storage_error)?;
+        if since.is_some_and(|since| since < compacted_through || since > last_seq) {
+            return Err(OrderServiceError::ChangesCompacted(since.unwrap_or_default()));
+        }
+
+        let after = since.unwrap_or_default();
+        let mut stmt = tx
+            .prepare_cached("SELECT * FROM orders WHERE seq > ?1 ORDER BY seq")
+            .map_err(storage_error)?;
+        let mut changes = stmt
+            .query_map(params![after], |row| order_from_row(row).map(Change::Put))
+            .map_err(storage_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(storage_error)?;
+
+        if since.is_some() {
+            let mut stmt = tx
+                .prepare_cached("SELECT seq, order_id FROM tombstones WHERE seq > ?1")
+                .map_err(storage_error)?;
+            let tombstones = stmt
+                .query_map(params![after], |row| Ok(Change::Deleted(Tombstone { seq: row.get(0)?, order_id: row.get(1)? })))
+                .map_err(storage_error)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(storage_error)?;
+            changes.extend(tombstones);
+            changes.sort_by_key(Change::seq);
+        }
+
+        Ok(Changes { changes, last_seq })
+    }
+
+    fn get_orders_page(&self, query: &OrderQuery, page: &PageRequest) -> Result<OrderPage, OrderServiceError> {
+        let conn = self.conn()?;
+
+        // The WHERE clause only mentions the filters that were given, so that SQLite
+        // can pick the table_id, item_id, placed_by, status or created_at index for the query.
+        let mut clauses = Vec::new();
+        let mut args = Vec::new();
+        if !query.table_ids.is_empty() {
+            clauses.push(in_clause("table_id", query.table_ids.len()));
+            args.extend(query.table_ids.iter().cloned());
+        }
+        if !query.item_ids.is_empty() {
+            clauses.push(in_clause("item_id", query.item_ids.len()));
+            args.extend(query.item_ids.iter().cloned());
+        }
+        if !query.placed_by.is_empty() {
+            clauses.push(in_clause("placed_by", query.placed_by.len()));
+            args.extend(query.placed_by.iter().cloned());
+        }
+        if !query.statuses.is_empty() {
+            clauses.push(in_clause("status", query.statuses.len()));
+            args.extend(query.statuses.iter().map(|status| status.as_str().to_string()));
+        }
+        if let Some(created_after) = query.created_after {
+            clauses.push("created_at >= ?".to_string());
+            args.push(format_timestamp(&created_after));
+        }
+        if let Some(created_before) = query.created_before {
+            clauses.push("created_at < ?".to_string());
+            args.push(format_timestamp(&created_before));
+        }
+
+        // Sort values are compared the same way as in SortKey::value, created_at is stored in
+        // the very same format.
+        let column = match page.sort {
+            SortKey::Created => "created_at",
+            SortKey::Table => "table_id",
+            SortKey::Item => "item_id",
+        };
+        let (comparison, direction) = match page.direction {
+            SortDirection::Asc => (">", "ASC"),
+            SortDirection::Desc => ("<", "DESC"),
+        };
+        if let Some(cursor) = &page.after {
+            clauses.push(format!("({}, order_id) {} (?, ?)", column, comparison));
+            args.push(cursor.value.clone());
+            args.push(cursor.order_id.clone());
+        }
+
+        let mut sql = String::from("SELECT * FROM orders");
+        if !clauses.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&clauses.join(" AND "));
+        }
+        // A negative LIMIT means no limit.
+        let limit = i64::try_from(page.limit.saturating_add(1)).unwrap_or(-1);
+        sql.push_str(&format!(" ORDER BY {} {}, order_id {} LIMIT {}", column, direction, direction, limit));
+
+        let mut stmt = conn.prepare_cached(&sql).map_err(storage_error)?;
+        let orders = stmt
+            .query_map(params_from_iter(args), order_from_row)
+            .map_err(storage_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(storage_error)?;
+
+        Ok(OrderPage::from_sorted(orders, page))
+    }
+}
@@ -0,0 +1,574 @@
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use crate::pagination::{PageRequest, SortDirection, SortKey};
+    use crate::order_service_tests::{OrderServiceExt, RecordingAudit};
+    use crate::order_service::{
+        Change, Changes, Doneness, Modifier, Order, OrderQuery, OrderResult, OrderService, OrderServiceError, OrderStatus,
+        MAX_TOMBSTONES,
+    };
+    use crate::sqlite_order_service::{new_sqlite, SqliteOrderService};
+    use uuid::Uuid;
+
+    fn setup_service() -> SqliteOrderService {
+        new_sqlite(":memory:").unwrap()
+    }
+
+    fn temp_db_path() -> String {
+        std::env::temp_dir()
+            .join(format!("restaurant_api_test_{}.db", Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn test_put_order_success() {
+        let service = setup_service();
+        let order = Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        };
+
+        let order_result = service.put_order("order1".to_string(), order).unwrap().into_order();
+        assert_eq!(order_result.order_id, "order1");
+        assert_eq!(order_result.item_id, "item1");
+        assert_eq!(order_result.table_id, "table1");
+        assert!((5..=15).contains(&order_result.cooking_time));
+    }
+
+    #[test]
+    fn test_put_duplicate_order_fails() {
+        let service = setup_service();
+        let order = Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        };
+
+        let created = service.put_order("order1".to_string(), order.clone()).unwrap().into_order();
+
+        let retried = service.put_order("order1".to_string(), order.clone()).unwrap();
+        assert!(!retried.is_created());
+        assert_eq!(retried.into_order().cooking_time, created.cooking_time);
+
+        let duplicate_result = service.put_order("order1".to_string(), Order {
+            notes: Some("no ice".to_string()),
+            ..order
+        });
+        if let Err(OrderServiceError::DuplicateOrder { order_id, fields }) = duplicate_result {
+            assert_eq!(order_id, "order1");
+            assert_eq!(fields, vec!["notes"]);
+        } else {
+            panic!("Expected DuplicateOrder error.");
+        }
+    }
+
+    #[test]
+    fn test_duplicate_detected_across_connections() {
+        let path = temp_db_path();
+        let first = new_sqlite(&path).unwrap();
+        let second = new_sqlite(&path).unwrap();
+        let order = Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        };
+
+        first.put_order("order1".to_string(), order.clone()).unwrap();
+        assert!(!second.put_order("order1".to_string(), order.clone()).unwrap().is_created());
+
+        let duplicate_result = second.put_order("order1".to_string(), Order { quantity: 2, ..order });
+        assert!(matches!(duplicate_result, Err(OrderServiceError::DuplicateOrder { .. })));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_orders_survive_reopen() {
+        let path = temp_db_path();
+        {
+            let service = new_sqlite(&path).unwrap();
+            service.put_order("order1".to_string(), Order {
+                item_id: "item1".to_string(),
+                table_id: "table1".to_string(),
+                ..Default::default()
+            }).unwrap();
+        }
+
+        let service = new_sqlite(&path).unwrap();
+        let orders = service.get_orders(&OrderQuery::default()).unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].order_id, "order1");
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_delete_order_success() {
+        let service = setup_service();
+        let order = Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        };
+
+        let created = service.put_order("order1".to_string(), order).unwrap().into_order();
+
+        let deleted_order = service.delete_order("order1".to_string()).unwrap();
+        assert_eq!(deleted_order.order_id, "order1");
+        assert_eq!(deleted_order.cooking_time, created.cooking_time);
+        assert!(service.get_orders(&OrderQuery::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_delete_order_not_found() {
+        let service = setup_service();
+
+        let delete_result = service.delete_order("non_existent_order".to_string());
+        if let Err(OrderServiceError::OrderNotFound(order_id)) = delete_result {
+            assert_eq!(order_id, "non_existent_order");
+        } else {
+            panic!("Expected OrderNotFound error.");
+        }
+    }
+
+    #[test]
+    fn test_delete_order_if() {
+        let service = setup_service();
+        service.put_order("order1".to_string(), Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            placed_by: Some("watch-17".to_string()),
+            ..Default::default()
+        }).unwrap();
+
+        let placed_by = |device: &'static str| move |order: &OrderResult| order.placed_by.as_deref() == Some(device);
+        assert!(matches!(
+            service.delete_order_if("order1".to_string(), &placed_by("watch-18")),
+            Err(OrderServiceError::ConditionFailed(_))
+        ));
+        assert!(service.get_order_by_id("order1".to_string()).is_ok());
+
+        let deleted = service.delete_order_if("order1".to_string(), &placed_by("watch-17")).unwrap();
+        assert_eq!(deleted.order_id, "order1");
+        assert!(matches!(
+            service.delete_order_if("order1".to_string(), &placed_by("watch-17")),
+            Err(OrderServiceError::OrderNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_changes_are_audited() {
+        let service = setup_service();
+        let order = Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        };
+        let audit = RecordingAudit::default();
+
+        service.put_order_audited("order1".to_string(), order.clone(), &audit).unwrap();
+        // A retry changes nothing, so it is not recorded again.
+        service.put_order_audited("order1".to_string(), order, &audit).unwrap();
+        service.update_status_audited("order1".to_string(), OrderStatus::Cooking, &audit).unwrap();
+        service.delete_order_audited("order1".to_string(), &|_| true, &audit).unwrap();
+
+        assert_eq!(audit.statuses(), vec![
+            (None, Some(OrderStatus::Placed)),
+            (Some(OrderStatus::Placed), Some(OrderStatus::Cooking)),
+            (Some(OrderStatus::Cooking), None),
+        ]);
+        let changes = audit.changes.lock().unwrap();
+        assert_eq!(changes[1].1.as_ref().unwrap().seq, 2);
+    }
+
+    #[test]
+    fn test_changes_that_cannot_be_audited_are_not_made() {
+        let service = setup_service();
+        let order = Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        };
+        let failing = RecordingAudit::failing();
+
+        let res = service.put_order_audited("order1".to_string(), order.clone(), &failing);
+        assert!(matches!(res, Err(OrderServiceError::AuditFailed(_))));
+        assert!(matches!(service.get_order_by_id("order1".to_string()), Err(OrderServiceError::OrderNotFound(_))));
+
+        service.put_order("order1".to_string(), order).unwrap();
+        let res = service.update_status_audited("order1".to_string(), OrderStatus::Cooking, &failing);
+        assert!(matches!(res, Err(OrderServiceError::AuditFailed(_))));
+        let res = service.delete_order_audited("order1".to_string(), &|_| true, &failing);
+        assert!(matches!(res, Err(OrderServiceError::AuditFailed(_))));
+
+        let stored = service.get_order_by_id("order1".to_string()).unwrap();
+        assert_eq!(stored.status, OrderStatus::Placed);
+        // Neither the status change nor the deletion used up a sequence number.
+        assert_eq!(service.get_changes(None).unwrap().last_seq, 1);
+    }
+
+    #[test]
+    fn test_get_orders_filters() {
+        let service = setup_service();
+        let orders = [
+            ("order1", "item1", "table1"),
+            ("order2", "item2", "table1"),
+            ("order3", "item1", "table2"),
+        ];
+        for (order_id, item_id, table_id) in orders {
+            service.put_order(order_id.to_string(), Order {
+                item_id: item_id.to_string(),
+                table_id: table_id.to_string(),
+                ..Default::default()
+            }).unwrap();
+        }
+
+        assert_eq!(service.get_orders(&OrderQuery::default()).unwrap().len(), 3);
+        assert_eq!(service.get_orders(&OrderQuery {
+            table_ids: vec!["table1".to_string()],
+            ..Default::default()
+        }).unwrap().len(), 2);
+        assert_eq!(service.get_orders(&OrderQuery {
+            item_ids: vec!["item1".to_string()],
+            ..Default::default()
+        }).unwrap().len(), 2);
+
+        let orders = service.get_orders(&OrderQuery {
+            table_ids: vec!["table1".to_string()],
+            item_ids: vec!["item1".to_string()],
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].order_id, "order1");
+
+        service.update_status("order2".to_string(), OrderStatus::Cooking).unwrap();
+        let orders = service.get_orders(&OrderQuery {
+            table_ids: vec!["table1".to_string(), "table2".to_string(), "table2".to_string()],
+            statuses: vec![OrderStatus::Placed, OrderStatus::Ready],
+            ..Default::default()
+        }).unwrap();
+        let order_ids: Vec<String> = orders.into_iter().map(|order| order.order_id).collect();
+        assert_eq!(order_ids, vec!["order1", "order3"]);
+    }
+
+    #[test]
+    fn test_get_orders_by_placed_by() {
+        let service = setup_service();
+        for (order_id, placed_by) in [("order1", Some("watch-17")), ("order2", Some("watch-18")), ("order3", None)] {
+            service.put_order(order_id.to_string(), Order {
+                item_id: "item1".to_string(),
+                table_id: "table1".to_string(),
+                placed_by: placed_by.map(str::to_string),
+                ..Default::default()
+            }).unwrap();
+        }
+
+        let orders = service.get_orders(&OrderQuery {
+            placed_by: vec!["watch-17".to_string()],
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].order_id, "order1");
+        assert_eq!(service.get_orders(&OrderQuery {
+            placed_by: vec!["watch-17".to_string(), "watch-18".to_string()],
+            ..Default::default()
+        }).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_order_lifecycle() {
+        let service = setup_service();
+        service.put_order("order1".to_string(), Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        }).unwrap();
+
+        let res = service.update_status("order1".to_string(), OrderStatus::Ready);
+        assert!(matches!(res, Err(OrderServiceError::InvalidTransition { from: OrderStatus::Placed, .. })));
+
+        let updated = service.update_status("order1".to_string(), OrderStatus::Cooking).unwrap();
+        assert_eq!(updated.status, OrderStatus::Cooking);
+
+        assert!(service.get_orders(&OrderQuery {
+            statuses: vec![OrderStatus::Placed],
+            ..Default::default()
+        }).unwrap().is_empty());
+        let orders = service.get_orders(&OrderQuery {
+            table_ids: vec!["table1".to_string()],
+            statuses: vec![OrderStatus::Cooking],
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].status, OrderStatus::Cooking);
+
+        let res = service.update_status("order2".to_string(), OrderStatus::Cooking);
+        assert!(matches!(res, Err(OrderServiceError::OrderNotFound(_))));
+    }
+
+    #[test]
+    fn test_migrates_existing_database() {
+        let path = temp_db_path();
+        {
+            let conn = rusqlite::Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE orders (
+                    order_id TEXT PRIMARY KEY NOT NULL,
+                    item_id TEXT NOT NULL,
+                    table_id TEXT NOT NULL,
+                    cooking_time INTEGER NOT NULL
+                );
+                INSERT INTO orders VALUES ('order1', 'item1', 'table1', 7);
+                PRAGMA user_version = 1;",
+            ).unwrap();
+        }
+
+        let service = new_sqlite(&path).unwrap();
+        let orders = service.get_orders(&OrderQuery::default()).unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].cooking_time, 7);
+        assert_eq!(orders[0].status, OrderStatus::Placed);
+        assert_eq!(orders[0].estimated_ready_at, orders[0].created_at + Duration::minutes(7));
+
+        // Deletions from before the migration are unknown.
+        assert!(matches!(service.get_changes(Some(0)), Err(OrderServiceError::ChangesCompacted(0))));
+        let changes = service.get_changes(None).unwrap();
+        assert_eq!(describe(&changes), vec![(1, "order1", false)]);
+        service.delete_order("order1".to_string()).unwrap();
+        assert_eq!(describe(&service.get_changes(Some(1)).unwrap()), vec![(2, "order1", true)]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_get_orders_by_creation_time() {
+        let service = setup_service();
+        let mut created = Vec::new();
+        for (order_id, table_id) in [("order1", "table1"), ("order2", "table2"), ("order3", "table1")] {
+            let order = service.put_order(order_id.to_string(), Order {
+                item_id: "item1".to_string(),
+                table_id: table_id.to_string(),
+                ..Default::default()
+            }).unwrap().into_order();
+            created.push(order.created_at);
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+
+        let orders = service.get_orders(&OrderQuery { created_after: Some(created[1]), ..Default::default() }).unwrap();
+        assert_eq!(orders.len(), 2);
+
+        let orders = service.get_orders(&OrderQuery {
+            table_ids: vec!["table1".to_string()],
+            created_after: Some(created[1]),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].order_id, "order3");
+        assert_eq!(orders[0].created_at, created[2]);
+
+        let orders = service.get_orders(&OrderQuery {
+            created_before: Some(created[1]),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].order_id, "order1");
+    }
+
+    #[test]
+    fn test_update_status_touches_updated_at() {
+        let service = setup_service();
+        let placed = service.put_order("order1".to_string(), Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        }).unwrap().into_order();
+
+        let updated = service.update_status("order1".to_string(), OrderStatus::Cooking).unwrap();
+        assert_eq!(updated.created_at, placed.created_at);
+        assert_eq!(updated.estimated_ready_at, placed.estimated_ready_at);
+        assert!(updated.updated_at >= placed.updated_at);
+    }
+
+    #[test]
+    fn test_modifiers_round_trip() {
+        let service = setup_service();
+        let modifiers = vec![
+            Modifier::Remove { ingredient: "onion".to_string() },
+            Modifier::Doneness { level: Doneness::WellDone },
+        ];
+        service.put_order("order1".to_string(), Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            quantity: 3,
+            modifiers: modifiers.clone(),
+            notes: Some("birthday".to_string()),
+            placed_by: Some("watch-17".to_string()),
+        }).unwrap();
+
+        let orders = service.get_orders(&OrderQuery::default()).unwrap();
+        assert_eq!(orders[0].quantity, 3);
+        assert_eq!(orders[0].modifiers, modifiers);
+        assert_eq!(orders[0].notes.as_deref(), Some("birthday"));
+        assert_eq!(orders[0].placed_by.as_deref(), Some("watch-17"));
+
+        let res = service.put_order("order2".to_string(), Order { quantity: 0, ..Default::default() });
+        assert!(matches!(res, Err(OrderServiceError::InvalidOrder(_))));
+    }
+
+    #[test]
+    fn test_get_order_by_id() {
+        let service = setup_service();
+        let created = service.put_order("order1".to_string(), Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        }).unwrap().into_order();
+
+        let order = service.get_order_by_id("order1".to_string()).unwrap();
+        assert_eq!(order.item_id, "item1");
+        assert_eq!(order.cooking_time, created.cooking_time);
+        assert_eq!(order.created_at, created.created_at);
+
+        let res = service.get_order_by_id("order2".to_string());
+        assert!(matches!(res, Err(OrderServiceError::OrderNotFound(_))));
+    }
+
+    // Reads all pages one by one, creating and deleting orders in between.
+    fn read_all_pages(service: &dyn OrderService, page: PageRequest) -> Vec<String> {
+        let mut page = page;
+        let mut seen = Vec::new();
+        for i in 0.. {
+            let res = service.get_orders_page(&OrderQuery::default(), &page).unwrap();
+            assert!(res.orders.len() <= page.limit);
+            seen.extend(res.orders.into_iter().map(|order| order.order_id));
+
+            service.put_order(format!("new{}", i), Order {
+                item_id: "item1".to_string(),
+                table_id: "table1".to_string(),
+                ..Default::default()
+            }).unwrap();
+            if i == 0 {
+                service.delete_order("order0".to_string()).unwrap();
+                service.delete_order("order5".to_string()).unwrap();
+            }
+
+            match res.next {
+                Some(next) => page.after = Some(next),
+                None => break,
+            }
+        }
+        seen
+    }
+
+    fn seed_orders(service: &dyn OrderService) {
+        for i in 0..7 {
+            service.put_order(format!("order{}", i), Order {
+                item_id: "item1".to_string(),
+                table_id: format!("table{}", i % 2),
+                ..Default::default()
+            }).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_pages_survive_concurrent_changes() {
+        for (sort, direction) in [(SortKey::Created, SortDirection::Asc), (SortKey::Table, SortDirection::Desc)] {
+            let service = setup_service();
+            seed_orders(&service);
+
+            let seen = read_all_pages(&service, PageRequest { sort, direction, limit: 2, after: None });
+
+            let mut unique = seen.clone();
+            unique.sort();
+            unique.dedup();
+            assert_eq!(unique.len(), seen.len(), "{:?} {:?}: {:?}", sort, direction, seen);
+            for i in [1, 2, 3, 4, 6] {
+                assert!(seen.contains(&format!("order{}", i)), "{:?} {:?}: {:?}", sort, direction, seen);
+            }
+        }
+    }
+
+    #[test]
+    fn test_filtered_pages() {
+        let service = setup_service();
+        seed_orders(&service);
+
+        let page = PageRequest { sort: SortKey::Created, direction: SortDirection::Desc, limit: 3, after: None };
+        let res = service.get_orders_page(&OrderQuery {
+            table_ids: vec!["table0".to_string()],
+            ..Default::default()
+        }, &page).unwrap();
+        let ids: Vec<String> = res.orders.into_iter().map(|order| order.order_id).collect();
+        assert_eq!(ids, vec!["order6", "order4", "order2"]);
+
+        let page = PageRequest { after: res.next, ..page };
+        let res = service.get_orders_page(&OrderQuery {
+            table_ids: vec!["table0".to_string()],
+            ..Default::default()
+        }, &page).unwrap();
+        let ids: Vec<String> = res.orders.into_iter().map(|order| order.order_id).collect();
+        assert_eq!(ids, vec!["order0"]);
+        assert!(res.next.is_none());
+    }
+
+    fn describe(changes: &Changes) -> Vec<(u64, &str, bool)> {
+        changes.changes.iter().map(|change| match change {
+            Change::Put(order) => (order.seq, order.order_id.as_str(), false),
+            Change::Deleted(tombstone) => (tombstone.seq, tombstone.order_id.as_str(), true),
+        }).collect()
+    }
+
+    #[test]
+    fn test_get_changes() {
+        let service = setup_service();
+        let order = || Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        };
+        for order_id in ["order1", "order2", "order3"] {
+            service.put_order(order_id.to_string(), order()).unwrap();
+        }
+        service.update_status("order1".to_string(), OrderStatus::Cooking).unwrap();
+        service.delete_order("order2".to_string()).unwrap();
+        service.put_order("order2".to_string(), order()).unwrap();
+        // Neither a rejected mutation nor a retry gets a sequence number.
+        service.update_status("order3".to_string(), OrderStatus::Served).unwrap_err();
+        service.put_order("order3".to_string(), order()).unwrap();
+        service.delete_order("order4".to_string()).unwrap_err();
+
+        let changes = service.get_changes(Some(0)).unwrap();
+        assert_eq!(changes.last_seq, 6);
+        assert_eq!(describe(&changes), vec![
+            (3, "order3", false),
+            (4, "order1", false),
+            (5, "order2", true),
+            (6, "order2", false),
+        ]);
+        assert_eq!(describe(&service.get_changes(Some(4)).unwrap()), vec![(5, "order2", true), (6, "order2", false)]);
+
+        let changes = service.get_changes(None).unwrap();
+        assert_eq!(describe(&changes), vec![(3, "order3", false), (4, "order1", false), (6, "order2", false)]);
+
+        assert!(matches!(service.get_changes(Some(7)), Err(OrderServiceError::ChangesCompacted(7))));
+    }
+
+    #[test]
+    fn test_get_changes_after_compaction() {
+        let service = setup_service();
+        for i in 0..=MAX_TOMBSTONES {
+            let order_id = format!("order{}", i);
+            service.put_order(order_id.clone(), Order {
+                item_id: "item1".to_string(),
+                table_id: "table1".to_string(),
+                ..Default::default()
+            }).unwrap();
+            service.delete_order(order_id).unwrap();
+        }
+
+        assert!(matches!(service.get_changes(Some(1)), Err(OrderServiceError::ChangesCompacted(1))));
+        assert_eq!(service.get_changes(Some(2)).unwrap().changes.len(), MAX_TOMBSTONES);
+    }
+}
@@ -0,0 +1,119 @@
+use std::sync::{Mutex, MutexGuard};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef};
+use rusqlite::{params, Connection, OptionalExtension, Row, ToSql};
+use crate::sqlite_order_service::migrate;
+use crate::table_service::{validate, Table, TableService, TableServiceError, TableStatus};
+
+// SqliteTableService keeps the tables in the SQLite database file the orders are persisted in,
+// so that orders and the tables they were placed for survive a restart together.
+// Like SqliteOrderService, it guards a single connection with a Mutex.
+pub struct SqliteTableService {
+    conn: Mutex<Connection>,
+}
+
+// Opens (or creates) the database at the given path and brings its schema up to date.
+pub fn new_sqlite(path: &str) -> Result<SqliteTableService, TableServiceError> {
+    let mut conn = Connection::open(path).map_err(storage_error)?;
+    migrate(&mut conn).map_err(|e| TableServiceError::Storage(e.to_string()))?;
+
+    Ok(SqliteTableService { conn: Mutex::new(conn) })
+}
+
+fn storage_error(e: rusqlite::Error) -> TableServiceError {
+    TableServiceError::Storage(e.to_string())
+}
+
+impl ToSql for TableStatus {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(match self {
+            TableStatus::Open => "open",
+            TableStatus::Closed => "closed",
+        }))
+    }
+}
+
+impl FromSql for TableStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "open" => Ok(TableStatus::Open),
+            "closed" => Ok(TableStatus::Closed),
+            s => Err(FromSqlError::Other(format!("unknown table status '{}'", s).into())),
+        }
+    }
+}
+
+fn table_from_row(row: &Row) -> rusqlite::Result<Table> {
+    Ok(Table {
+        table_id: row.get("table_id")?,
+        capacity: row.get("capacity")?,
+        section: row.get("section")?,
+        status: row.get("status")?,
+    })
+}
+
+impl SqliteTableService {
+    fn conn(&self) -> Result<MutexGuard<'_, Connection>, TableServiceError> {
+        self.conn.lock()
+            .map_err(|_| TableServiceError::MutexPoisoned("Failed to obtain connection mutex".into()))
+    }
+}
+
+impl TableService for SqliteTableService {
+    fn put_table(&self, table: Table) -> Result<Table, TableServiceError> {
+        validate(&table)?;
+
+        self.conn()?
+            .execute(
+                "INSERT OR REPLACE INTO tables (table_id, capacity, section, status) VALUES (?1, ?2, ?3, ?4)",
+                params![table.table_id, table.capacity, table.section, table.status],
+            )
+            .map_err(storage_error)?;
+
+        Ok(table)
+    }
+
+    fn get_table(&self, table_id: &str) -> Result<Table, TableServiceError> {
+        self.conn()?
+            .query_row("SELECT * FROM tables WHERE table_id = ?1", params![table_id], table_from_row)
+            .optional()
+            .map_err(storage_error)?
+            .ok_or_else(|| TableServiceError::TableNotFound(table_id.to_string()))
+    }
+
+    fn get_tables(&self, section: Option<String>) -> Result<Vec<Table>, TableServiceError> {
+        let conn = self.conn()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT * FROM tables WHERE ?1 IS NULL OR section = ?1
+                 ORDER BY section, table_id",
+            )
+            .map_err(storage_error)?;
+        let tables = stmt
+            .query_map(params![section], table_from_row)
+            .map_err(storage_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(storage_error)?;
+
+        Ok(tables)
+    }
+
+    fn set_status(&self, table_id: &str, status: TableStatus) -> Result<Table, TableServiceError> {
+        self.conn()?
+            .query_row(
+                "UPDATE tables SET status = ?2 WHERE table_id = ?1 RETURNING *",
+                params![table_id, status],
+                table_from_row,
+            )
+            .optional()
+            .map_err(storage_error)?
+            .ok_or_else(|| TableServiceError::TableNotFound(table_id.to_string()))
+    }
+
+    fn delete_table(&self, table_id: String) -> Result<Table, TableServiceError> {
+        self.conn()?
+            .query_row("DELETE FROM tables WHERE table_id = ?1 RETURNING *", params![table_id], table_from_row)
+            .optional()
+            .map_err(storage_error)?
+            .ok_or(TableServiceError::TableNotFound(table_id))
+    }
+}
@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+    use crate::sqlite_table_service::{new_sqlite, SqliteTableService};
+    use crate::table_service::{Table, TableService, TableServiceError, TableStatus};
+
+    fn setup_service() -> SqliteTableService {
+        new_sqlite(":memory:").unwrap()
+    }
+
+    fn table(table_id: &str, section: &str) -> Table {
+        Table {
+            table_id: table_id.to_string(),
+            capacity: 4,
+            section: section.to_string(),
+            status: TableStatus::Open,
+        }
+    }
+
+    #[test]
+    fn test_put_get_and_delete_table() {
+        let service = setup_service();
+        assert_eq!(service.put_table(table("1", "main")).unwrap(), table("1", "main"));
+        assert_eq!(service.get_table("1").unwrap(), table("1", "main"));
+
+        let closed = service.set_status("1", TableStatus::Closed).unwrap();
+        assert_eq!(closed.status, TableStatus::Closed);
+        assert_eq!(service.get_table("1").unwrap(), closed);
+        assert!(matches!(service.set_status("2", TableStatus::Open), Err(TableServiceError::TableNotFound(_))));
+
+        assert_eq!(service.delete_table("1".to_string()).unwrap(), closed);
+        assert!(matches!(service.get_table("1"), Err(TableServiceError::TableNotFound(_))));
+        assert!(matches!(service.delete_table("1".to_string()), Err(TableServiceError::TableNotFound(_))));
+    }
+
+    #[test]
+    fn test_get_tables_by_section() {
+        let service = setup_service();
+        service.put_table(table("3", "terrace")).unwrap();
+        service.put_table(table("2", "main")).unwrap();
+        service.put_table(table("1", "main")).unwrap();
+
+        let ids = |tables: Vec<Table>| tables.into_iter().map(|table| table.table_id).collect::<Vec<_>>();
+        assert_eq!(ids(service.get_tables(None).unwrap()), vec!["1", "2", "3"]);
+        assert_eq!(ids(service.get_tables(Some("terrace".to_string())).unwrap()), vec!["3"]);
+    }
+
+    #[test]
+    fn test_tables_survive_restart() {
+        let path = std::env::temp_dir()
+            .join(format!("restaurant_api_test_{}.db", Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned();
+        new_sqlite(&path).unwrap().put_table(table("1", "main")).unwrap();
+        new_sqlite(&path).unwrap().set_status("1", TableStatus::Closed).unwrap();
+
+        assert_eq!(new_sqlite(&path).unwrap().get_table("1").unwrap().status, TableStatus::Closed);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use rocket::serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::journal;
+
+// The file the tables are kept in within the journal directory.
+const TABLES_FILE: &str = "tables.json";
+
+#[derive(Serialize, Clone, Copy, Deserialize, Debug, PartialEq, ToSchema)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum TableStatus {
+    Open,
+    Closed,
+}
+
+// Table is a table of the restaurant orders can be placed for.
+// section groups tables served by the same waitstaff (e.g. "terrace").
+// Orders are only accepted for open tables.
+#[derive(Serialize, Clone, Deserialize, Debug, PartialEq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct Table {
+    pub table_id: String,
+    pub capacity: u32,
+    pub section: String,
+    pub status: TableStatus,
+}
+
+#[derive(Debug)]
+pub enum TableServiceError {
+    TableNotFound(String),
+    InvalidTable(String),
+    MutexPoisoned(String),
+    Storage(String),
+    TaskFailed(String),
+}
+
+impl fmt::Display for TableServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableServiceError::TableNotFound(id) => write!(f, "Table with id '{}' not found.", id),
+            TableServiceError::InvalidTable(msg) => write!(f, "Invalid table: {}", msg),
+            TableServiceError::MutexPoisoned(msg) => write!(f, "Mutex poisoned: {}", msg),
+            TableServiceError::Storage(msg) => write!(f, "Storage error: {}", msg),
+            TableServiceError::TaskFailed(msg) => write!(f, "Background task failed: {}", msg),
+        }
+    }
+}
+
+impl Error for TableServiceError {}
+
+// TableService is the registry of the restaurant's tables.
+// put_table registers a new table or replaces an existing one with the same id.
+pub trait TableService: Sync + Send {
+    fn put_table(&self, table: Table) -> Result<Table, TableServiceError>;
+    fn get_table(&self, table_id: &str) -> Result<Table, TableServiceError>;
+    fn get_tables(&self, section: Option<String>) -> Result<Vec<Table>, TableServiceError>;
+    fn set_status(&self, table_id: &str, status: TableStatus) -> Result<Table, TableServiceError>;
+    fn delete_table(&self, table_id: String) -> Result<Table, TableServiceError>;
+
+    // Whether calls may block the current thread, see OrderService::is_blocking.
+    fn is_blocking(&self) -> bool {
+        true
+    }
+}
+
+// InMemoryTableService keeps the tables in a HashMap wrapped in RwLock for thread safety.
+// With a journal directory, every change rewrites the tables file there before it is applied, so
+// that the tables survive a restart along with the journaled orders.
+pub struct InMemoryTableService {
+    tables: RwLock<HashMap<String, Table>>,
+    dir: Option<PathBuf>,
+}
+
+pub fn new_in_memory() -> InMemoryTableService {
+    InMemoryTableService {
+        tables: RwLock::new(HashMap::new()),
+        dir: None,
+    }
+}
+
+// Creates an InMemoryTableService keeping the tables in `dir`, with the tables recovered from it.
+pub fn new_in_memory_with_journal(dir: impl AsRef<Path>) -> Result<InMemoryTableService, TableServiceError> {
+    let dir = dir.as_ref().to_path_buf();
+    std::fs::create_dir_all(&dir).map_err(storage_error)?;
+    let tables: Vec<Table> = journal::read_registry(&dir, TABLES_FILE).map_err(storage_error)?;
+
+    Ok(InMemoryTableService {
+        tables: RwLock::new(tables.into_iter().map(|table| (table.table_id.clone(), table)).collect()),
+        dir: Some(dir),
+    })
+}
+
+fn storage_error(e: std::io::Error) -> TableServiceError {
+    TableServiceError::Storage(format!("tables I/O failed: {}", e))
+}
+
+impl InMemoryTableService {
+    // Writes the tables as they are going to be after a change, before the change is applied.
+    fn persist(&self, tables: &HashMap<String, Table>) -> Result<(), TableServiceError> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        let mut tables: Vec<&Table> = tables.values().collect();
+        tables.sort_by(|a, b| a.table_id.cmp(&b.table_id));
+        journal::write_registry(dir, TABLES_FILE, &tables).map_err(storage_error)
+    }
+}
+
+pub fn validate(table: &Table) -> Result<(), TableServiceError> {
+    if table.table_id.trim().is_empty() {
+        return Err(TableServiceError::InvalidTable("table_id must not be empty".into()));
+    }
+    if table.section.trim().is_empty() {
+        return Err(TableServiceError::InvalidTable("section must not be empty".into()));
+    }
+    if table.capacity == 0 {
+        return Err(TableServiceError::InvalidTable("capacity must be positive".into()));
+    }
+    Ok(())
+}
+
+impl TableService for InMemoryTableService {
+    fn put_table(&self, table: Table) -> Result<Table, TableServiceError> {
+        validate(&table)?;
+
+        let mut tables = self.tables.write()
+            .map_err(|_| TableServiceError::MutexPoisoned("Failed to obtain write mutex".into()))?;
+        let mut updated = tables.clone();
+        updated.insert(table.table_id.clone(), table.clone());
+        self.persist(&updated)?;
+        *tables = updated;
+
+        Ok(table)
+    }
+
+    fn get_table(&self, table_id: &str) -> Result<Table, TableServiceError> {
+        let tables = self.tables.read()
+            .map_err(|_| TableServiceError::MutexPoisoned("Failed to obtain read mutex".into()))?;
+
+        tables.get(table_id)
+            .cloned()
+            .ok_or_else(|| TableServiceError::TableNotFound(table_id.to_string()))
+    }
+
+    fn get_tables(&self, section: Option<String>) -> Result<Vec<Table>, TableServiceError> {
+        let tables = self.tables.read()
+            .map_err(|_| TableServiceError::MutexPoisoned("Failed to obtain read mutex".into()))?;
+
+        let mut result: Vec<Table> = tables
+            .values()
+            .filter(|table| section.as_ref().is_none_or(|section| &table.section == section))
+            .cloned()
+            .collect();
+        result.sort_by(|a, b| a.section.cmp(&b.section).then_with(|| a.table_id.cmp(&b.table_id)));
+
+        Ok(result)
+    }
+
+    fn set_status(&self, table_id: &str, status: TableStatus) -> Result<Table, TableServiceError> {
+        let mut tables = self.tables.write()
+            .map_err(|_| TableServiceError::MutexPoisoned("Failed to obtain write mutex".into()))?;
+
+        let mut updated = tables.clone();
+        let table = updated.get_mut(table_id)
+            .ok_or_else(|| TableServiceError::TableNotFound(table_id.to_string()))?;
+        table.status = status;
+        let table = table.clone();
+        self.persist(&updated)?;
+        *tables = updated;
+
+        Ok(table)
+    }
+
+    fn delete_table(&self, table_id: String) -> Result<Table, TableServiceError> {
+        let mut tables = self.tables.write()
+            .map_err(|_| TableServiceError::MutexPoisoned("Failed to obtain write mutex".into()))?;
+
+        let mut updated = tables.clone();
+        let table = updated.remove(&table_id)
+            .ok_or(TableServiceError::TableNotFound(table_id))?;
+        self.persist(&updated)?;
+        *tables = updated;
+
+        Ok(table)
+    }
+    // Only changes to tables kept in a journal directory write to disk.
+    fn is_blocking(&self) -> bool {
+        self.dir.is_some()
+    }
+}
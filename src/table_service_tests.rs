@@ -0,0 +1,95 @@
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+    use crate::table_service::{new_in_memory, new_in_memory_with_journal, InMemoryTableService, Table, TableService, TableServiceError, TableStatus};
+
+    fn setup_service() -> InMemoryTableService {
+        new_in_memory()
+    }
+
+    fn table(table_id: &str, section: &str) -> Table {
+        Table {
+            table_id: table_id.to_string(),
+            capacity: 4,
+            section: section.to_string(),
+            status: TableStatus::Open,
+        }
+    }
+
+    #[test]
+    fn test_put_and_get_table() {
+        let service = setup_service();
+        let table = table("table1", "terrace");
+
+        assert_eq!(service.put_table(table.clone()).unwrap(), table);
+        assert_eq!(service.get_table("table1").unwrap(), table);
+    }
+
+    #[test]
+    fn test_put_invalid_table_fails() {
+        let service = setup_service();
+
+        let mut table = table("table1", "terrace");
+        table.capacity = 0;
+        assert!(matches!(service.put_table(table), Err(TableServiceError::InvalidTable(_))));
+        assert!(matches!(service.put_table(self::table("table1", " ")), Err(TableServiceError::InvalidTable(_))));
+        assert!(service.get_tables(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_get_tables_by_section() {
+        let service = setup_service();
+        service.put_table(table("table2", "terrace")).unwrap();
+        service.put_table(table("table1", "terrace")).unwrap();
+        service.put_table(table("table3", "bar")).unwrap();
+
+        let all: Vec<String> = service.get_tables(None).unwrap().into_iter().map(|t| t.table_id).collect();
+        assert_eq!(all, vec!["table3", "table1", "table2"]);
+
+        let terrace: Vec<String> = service.get_tables(Some("terrace".to_string())).unwrap().into_iter().map(|t| t.table_id).collect();
+        assert_eq!(terrace, vec!["table1", "table2"]);
+    }
+
+    #[test]
+    fn test_set_status() {
+        let service = setup_service();
+        service.put_table(table("table1", "terrace")).unwrap();
+
+        let closed = service.set_status("table1", TableStatus::Closed).unwrap();
+        assert_eq!(closed.status, TableStatus::Closed);
+        assert_eq!(service.get_table("table1").unwrap().status, TableStatus::Closed);
+
+        assert!(matches!(service.set_status("table2", TableStatus::Open), Err(TableServiceError::TableNotFound(_))));
+    }
+
+    #[test]
+    fn test_delete_table() {
+        let service = setup_service();
+        service.put_table(table("table1", "terrace")).unwrap();
+
+        assert_eq!(service.delete_table("table1".to_string()).unwrap().table_id, "table1");
+        if let Err(TableServiceError::TableNotFound(table_id)) = service.get_table("table1") {
+            assert_eq!(table_id, "table1");
+        } else {
+            panic!("Expected TableNotFound error.");
+        }
+    }
+
+    #[test]
+    fn test_tables_are_recovered_from_journal_dir() {
+        let dir = std::env::temp_dir().join(format!("restaurant_api_tables_{}", Uuid::new_v4()));
+        {
+            let service = new_in_memory_with_journal(&dir).unwrap();
+            service.put_table(table("1", "main")).unwrap();
+            service.put_table(table("2", "terrace")).unwrap();
+            service.set_status("1", TableStatus::Closed).unwrap();
+            service.delete_table("2".to_string()).unwrap();
+        }
+
+        let service = new_in_memory_with_journal(&dir).unwrap();
+        assert_eq!(service.get_table("1").unwrap().status, TableStatus::Closed);
+        assert!(matches!(service.get_table("2"), Err(TableServiceError::TableNotFound(_))));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,13 +1,22 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
+    use std::sync::Arc;
+    use chrono::{DateTime, Duration, SecondsFormat, Utc};
     use tokio::task;
-    use reqwest;
-    use rocket::http::{ContentType, Status};
+    use rocket::http::{ContentType, Header, Status};
     use rocket::local::blocking::Client;
-    use rocket::serde::json::json;
+    use rocket::serde::json::{json, serde_json};
     use serde::{Deserialize, Serialize};
     use uuid::Uuid;
-    use crate::{create_rocket, rocket, ErrorResponse};
+    use crate::{create_rocket, rocket, write_enrollment_keys, ErrorResponse, OrderDetails};
+    use crate::api_error::{ErrorCodeInfo, CORRELATION_HEADER};
+    use crate::audit_log::REASON_HEADER;
+    use crate::auth::Auth;
+    use crate::menu_service::MenuItem;
+    use crate::order_events::{EventId, OrderEvents};
+    use crate::policy::{Role, POLICY};
+    use crate::table_service::{Table, TableStatus};
 
     #[derive(Serialize, Deserialize, Debug)]
     struct Order {
@@ -22,6 +31,60 @@ mod tests {
         item_id: String,
         table_id: String,
         cooking_time: i32,
+        status: String,
+        created_at: DateTime<Utc>,
+        updated_at: DateTime<Utc>,
+        estimated_ready_at: DateTime<Utc>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[serde(crate = "rocket::serde")]
+    struct OrderList {
+        orders: Vec<OrderResult>,
+        next_cursor: Option<String>,
+    }
+
+    // The Authorization header of an enrolled manager's device, which may call every route.
+    fn bearer(client: &Client) -> Header<'static> {
+        bearer_as(client, "test-device", Role::Manager, None)
+    }
+
+    fn bearer_as(client: &Client, device_id: &str, role: Role, section: Option<&str>) -> Header<'static> {
+        let auth = client.rocket().state::<Auth>().unwrap();
+        let token = auth.issue(device_id, role, section.map(str::to_string)).unwrap().token;
+        Header::new("Authorization", format!("Bearer {}", token))
+    }
+
+    fn reqwest_client(token: &str) -> reqwest::Client {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(reqwest::header::AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+        reqwest::Client::builder().default_headers(headers).build().unwrap()
+    }
+
+    fn put_menu_item(client: &Client, item_id: &str) {
+        let res = client
+            .put(format!("/menu/{}", item_id))
+            .header(bearer(client))
+            .json(&json!({
+                "name": format!("Item {}", item_id),
+                "price_cents": 1000,
+                "category": "mains",
+                "preparation_time": 10,
+            }))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+    }
+
+    fn put_table(client: &Client, table_id: &str) {
+        let res = client
+            .put(format!("/tables/{}", table_id))
+            .header(bearer(client))
+            .json(&json!({
+                "capacity": 4,
+                "section": "main",
+            }))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
     }
 
     #[test]
@@ -30,6 +93,7 @@ mod tests {
         let client = Client::tracked(rocket).expect("valid rocket instance");
 
         let response = client.put("/order/123")
+            .header(bearer(&client))
             .header(ContentType::JSON)
             .dispatch();
 
@@ -45,11 +109,14 @@ mod tests {
         let client = Client::tracked(rocket).expect("valid rocket instance");
 
         let response = client.put("/order/123")
+            .header(bearer(&client))
             .header(ContentType::Plain)
             .body("item_id=1&table_id=2")
             .dispatch();
 
-        assert_eq!(response.status(), Status::NotFound);
+        assert_eq!(response.status(), Status::UnsupportedMediaType);
+        let error_response: ErrorResponse = response.into_json().unwrap();
+        assert_eq!(error_response.error_code, "UNSUPPORTED_MEDIA_TYPE");
     }
 
     #[test]
@@ -62,7 +129,50 @@ mod tests {
             .body(json!({"item_id": "1", "table_id": "2"}).to_string())
             .dispatch();
 
-        assert_eq!(response.status(), Status::NotFound);
+        assert_eq!(response.status(), Status::MethodNotAllowed);
+        assert_eq!(response.headers().get_one("Allow"), Some("DELETE, GET, PUT"));
+        let error_response: ErrorResponse = response.into_json().unwrap();
+        assert_eq!(error_response.error_code, "METHOD_NOT_ALLOWED");
+    }
+
+    #[test]
+    fn unknown_path() {
+        let client = Client::tracked(rocket()).unwrap();
+
+        for path in ["/orderz", "/order/123/unknown", "/order"] {
+            let response = client.get(path).dispatch();
+            assert_eq!(response.status(), Status::NotFound);
+            assert_eq!(response.content_type(), Some(ContentType::JSON));
+            let error_response: ErrorResponse = response.into_json().unwrap();
+            assert_eq!(error_response.error_code, "NOT_FOUND");
+        }
+    }
+
+    #[rocket::get("/status/<code>")]
+    fn respond_with_status(code: u16) -> Status {
+        Status::new(code)
+    }
+
+    #[test]
+    fn errors_without_handler_response_are_json() {
+        let rocket = rocket::build()
+            .register("/", rocket::catchers![crate::not_found, crate::internal_error, crate::default_catcher])
+            .mount("/", rocket::routes![respond_with_status]);
+        let client = Client::tracked(rocket).unwrap();
+
+        for (code, error_code) in [
+            (400, "BAD_REQUEST"),
+            (405, "METHOD_NOT_ALLOWED"),
+            (415, "UNSUPPORTED_MEDIA_TYPE"),
+            (418, "IM_A_TEAPOT"),
+            (422, "UNPROCESSABLE_ENTITY"),
+            (500, "INTERNAL_ERROR"),
+        ] {
+            let response = client.get(format!("/status/{}", code)).dispatch();
+            assert_eq!(response.status().code, code);
+            let error_response: ErrorResponse = response.into_json().unwrap();
+            assert_eq!(error_response.error_code, error_code);
+        }
     }
 
     #[test]
@@ -71,6 +181,7 @@ mod tests {
         let client = Client::tracked(rocket).expect("valid rocket instance");
 
         let response = client.put("/order/123")
+            .header(bearer(&client))
             .header(ContentType::JSON)
             .body(json!({"table_id": "2"}).to_string())
             .dispatch();
@@ -83,10 +194,13 @@ mod tests {
 
     #[test]
     fn put_order_happy_path() {
-        let client = Client::new(rocket()).unwrap();
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
         let uuid = Uuid::new_v4();
         let res = client
             .put(format!("/order/{}", uuid))
+            .header(bearer(&client))
             .json(&Order {
                 item_id: String::from("123"),
                 table_id: String::from("1"),
@@ -100,12 +214,14 @@ mod tests {
         assert_eq!(order_result.order_id, uuid.to_string());
         assert_eq!(order_result.item_id, "123");
         assert_eq!(order_result.table_id, "1");
-        assert!((5..=15).contains(&order_result.cooking_time));
+        assert_eq!(order_result.cooking_time, 10);
     }
 
     #[test]
     fn put_duplicate_order() {
-        let client = Client::new(rocket()).unwrap();
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
         let uuid = Uuid::new_v4().to_string();
         let order = Order {
             item_id: "123".to_string(),
@@ -114,31 +230,113 @@ mod tests {
 
         let res = client
             .put(format!("/order/{}", uuid))
+            .header(bearer(&client))
             .json(&order)
             .dispatch();
         assert_eq!(res.status(), Status::Ok);
+        let created: OrderResult = res.into_json().unwrap();
 
-        // Second PUT request with the same ID
+        // Second PUT request with the same ID and body is a retry
         let res = client
             .put(format!("/order/{}", uuid))
+            .header(bearer(&client))
             .header(ContentType::JSON)
             .body(json!(order).to_string())
             .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let retried: OrderResult = res.into_json().unwrap();
+        assert_eq!(retried, created);
+
+        // Third PUT request with the same ID but a different body
+        let res = client
+            .put(format!("/order/{}", uuid))
+            .header(bearer(&client))
+            .json(&json!({"item_id": "123", "table_id": "1", "quantity": 2}))
+            .dispatch();
 
         assert_eq!(res.status(), Status::Conflict);
 
         let error_response: ErrorResponse = res.into_json().unwrap();
         assert_eq!(error_response.error_code, "DUPLICATE_ORDER");
+        assert!(error_response.error.contains("quantity"));
+        assert_eq!(error_response.fields.len(), 1);
+        assert_eq!(error_response.fields[0].field, "quantity");
+        assert_eq!(error_response.fields[0].code, "CONFLICT");
+    }
+
+    #[test]
+    fn put_order_malformed_ids() {
+        let client = Client::tracked(rocket()).unwrap();
+
+        let res = client
+            .put("/order/order%201")
+            .header(bearer(&client))
+            .json(&json!({"item_id": "", "table_id": "x".repeat(65)}))
+            .dispatch();
+
+        assert_eq!(res.status(), Status::UnprocessableEntity);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "VALIDATION_FAILED");
+        let fields: Vec<(&str, &str)> = error_response.fields
+            .iter()
+            .map(|error| (error.field.as_str(), error.code.as_str()))
+            .collect();
+        assert_eq!(fields, vec![("id", "INVALID_CHARS"), ("item_id", "REQUIRED"), ("table_id", "TOO_LONG")]);
+        assert!(error_response.fields.iter().all(|error| !error.message.is_empty()));
+    }
+
+    #[test]
+    fn malformed_path_ids_are_rejected() {
+        let client = Client::tracked(rocket()).unwrap();
+
+        for (method, path) in [
+            ("GET", "/order/a%20b"),
+            ("POST", "/order/a%20b/start"),
+            ("DELETE", "/order/a%20b"),
+            ("GET", "/menu/a%20b"),
+            ("DELETE", "/tables/a%20b"),
+            ("POST", "/tables/a%20b/open"),
+            ("GET", "/webhooks/a%20b"),
+        ] {
+            let res = match method {
+                "GET" => client.get(path).header(bearer(&client)).dispatch(),
+                "POST" => client.post(path).header(bearer(&client)).dispatch(),
+                _ => client.delete(path).header(bearer(&client)).dispatch(),
+            };
+            assert_eq!(res.status(), Status::UnprocessableEntity, "{} {}", method, path);
+            let error_response: ErrorResponse = res.into_json().unwrap();
+            assert_eq!(error_response.error_code, "VALIDATION_FAILED");
+            assert_eq!(error_response.fields[0].field, "id");
+        }
+
+        let res = client
+            .put("/menu/burger")
+            .header(bearer(&client))
+            .json(&json!({
+                "name": "Burger\u{7}",
+                "price_cents": 1000,
+                "category": "",
+                "preparation_time": 10,
+            }))
+            .dispatch();
+        assert_eq!(res.status(), Status::UnprocessableEntity);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "VALIDATION_FAILED");
+        let fields: Vec<&str> = error_response.fields.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(fields, vec!["name", "category"]);
     }
 
     #[test]
     fn get_orders_by_table() {
-        let client = Client::new(rocket()).unwrap();
+        let client = Client::tracked(rocket()).unwrap();
 
+        put_table(&client, "3");
         for i in 301..304 {
+            put_menu_item(&client, &i.to_string());
             let uuid = Uuid::new_v4();
             client
                 .put(format!("/order/{}", uuid))
+                .header(bearer(&client))
                 .json(&Order {
                     item_id: i.to_string(),
                     table_id: String::from("3"),
@@ -146,9 +344,9 @@ mod tests {
                 .dispatch();
         }
 
-        let res = client.get("/orders?table_id=3").dispatch();
+        let res = client.get("/orders?table_id=3").header(bearer(&client)).dispatch();
         assert_eq!(res.status(), Status::Ok);
-        let orders: Vec<OrderResult> = res.into_json().unwrap();
+        let orders = res.into_json::<OrderList>().unwrap().orders;
 
         assert_eq!(orders.len(), 3);
         let item_ids: Vec<String> = orders.iter().map(|o| o.item_id.clone()).collect();
@@ -159,11 +357,14 @@ mod tests {
 
     #[test]
     fn get_orders_by_table_and_item() {
-        let client = Client::new(rocket()).unwrap();
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "401");
+        put_table(&client, "4");
         let uuid = Uuid::new_v4();
 
         let res = client
             .put(format!("/order/{}", uuid))
+            .header(bearer(&client))
             .json(&Order {
                 item_id: String::from("401"),
                 table_id: String::from("4"),
@@ -174,10 +375,11 @@ mod tests {
 
         let res = client
             .get("/orders?table_id=4&item_id=401")
+            .header(bearer(&client))
             .dispatch();
 
         assert_eq!(res.status(), Status::Ok);
-        let orders: Vec<OrderResult> = res.into_json().unwrap();
+        let orders = res.into_json::<OrderList>().unwrap().orders;
 
         assert!(!orders.is_empty());
 
@@ -186,16 +388,20 @@ mod tests {
             .find(|o| o.item_id == "401" && o.table_id == "4")
             .expect("Order not found");
 
-        assert!((5..=15).contains(&order.cooking_time));
+        // The item's preparation time, with an idle kitchen and no customizations.
+        assert_eq!(order.cooking_time, 10);
     }
 
     #[test]
     fn delete_item_from_table() {
-        let client = Client::new(rocket()).unwrap();
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "201");
+        put_table(&client, "2");
         let uuid = Uuid::new_v4();
 
         let res = client
             .put(format!("/order/{}", uuid))
+            .header(bearer(&client))
             .json(&Order {
                 item_id: String::from("201"),
                 table_id: String::from("2"),
@@ -206,23 +412,26 @@ mod tests {
 
         let res = client
             .delete(format!("/order/{}", uuid))
+            .header(bearer(&client))
             .dispatch();
 
         assert_eq!(res.status(), Status::Ok);
 
-        let res = client.get("/orders?table_id=2").dispatch();
+        let res = client.get("/orders?table_id=2").header(bearer(&client)).dispatch();
         assert_eq!(res.status(), Status::Ok);
-        let orders: Vec<OrderResult> = res.into_json().unwrap();
+        let orders = res.into_json::<OrderList>().unwrap().orders;
 
         assert!(orders.is_empty());
     }
 
     #[test]
     fn delete_nonexistent_order() {
-        let client = Client::new(rocket()).unwrap();        let uuid = Uuid::new_v4();
+        let client = Client::tracked(rocket()).unwrap();
+        let uuid = Uuid::new_v4();
 
         let res = client
             .delete(format!("/order/{}", uuid))
+            .header(bearer(&client))
             .dispatch();
 
         assert_eq!(res.status(), Status::NotFound);
@@ -237,6 +446,7 @@ mod tests {
 
         let server = rocket.ignite().await.unwrap();
         let shutdown_handle = server.shutdown();
+        let token = server.state::<Auth>().unwrap().issue("test-device", Role::Manager, None).unwrap().token;
 
         let server_handle = tokio::spawn(async move {
             server.launch().await.unwrap();
@@ -245,10 +455,38 @@ mod tests {
         // Give server time to start
         tokio::time::sleep(std::time::Duration::from_secs(1)).await;
 
+        let client = reqwest_client(&token);
+        for thread_num in 0..10 {
+            let res = client
+                .put(format!("http://localhost:8000/menu/item{}", thread_num))
+                .json(&json!({
+                    "name": format!("Item {}", thread_num),
+                    "price_cents": 1000,
+                    "category": "mains",
+                    "preparation_time": 10,
+                }))
+                .send()
+                .await
+                .expect("Failed to send PUT request");
+            assert!(res.status().is_success());
+        }
+        for table_num in 0..3 {
+            let res = client
+                .put(format!("http://localhost:8000/tables/table{}", table_num))
+                .json(&json!({
+                    "capacity": 4,
+                    "section": "main",
+                }))
+                .send()
+                .await
+                .expect("Failed to send PUT request");
+            assert!(res.status().is_success());
+        }
+
         let mut handles = vec![];
         for thread_num in 0..10 {
+            let client = reqwest_client(&token);
             let handle = task::spawn(async move {
-                let client = reqwest::Client::new();
                 let start_time = std::time::Instant::now();
                 let mut iteration_num = 0;
                 while start_time.elapsed() < std::time::Duration::from_secs(10) {
@@ -262,7 +500,7 @@ mod tests {
                             table_id: format!("table{}", thread_num % 3),
                         };
                         let res = client
-                            .put(&format!("http://localhost:8000/order/{}", order_id))
+                            .put(format!("http://localhost:8000/order/{}", order_id))
                             .json(&order)
                             .send()
                             .await
@@ -273,28 +511,29 @@ mod tests {
                     // Query based on item
                     let item_id = format!("item{}", thread_num);
                     let res = client
-                        .get(&format!("http://localhost:8000/orders?item_id={}", item_id))
+                        .get(format!("http://localhost:8000/orders?item_id={}", item_id))
                         .send()
                         .await
                         .expect("Failed to send GET request");
                     assert!(res.status().is_success());
-                    let orders: Vec<OrderResult> = res.json().await.expect("Failed to parse response");
+                    let orders = res.json::<OrderList>().await.expect("Failed to parse response").orders;
                     assert!(orders.len() >= 3); // At least the ones we just inserted
 
                     // Query based on table
                     let table_id = format!("table{}", thread_num % 3);
                     let res = client
-                        .get(&format!("http://localhost:8000/orders?table_id={}", table_id))
+                        .get(format!("http://localhost:8000/orders?table_id={}", table_id))
                         .send()
                         .await
                         .expect("Failed to send GET request");
                     assert!(res.status().is_success());
-                    let orders_by_table: Vec<OrderResult> = res.json().await.expect("Failed to parse response");
+                    let orders_by_table = res.json::<OrderList>().await.expect("Failed to parse response").orders;
+                    assert!(orders_by_table.len() >= 3);
 
                     // Delete all three items
                     for order_id in &order_ids {
                         let res = client
-                            .delete(&format!("http://localhost:8000/order/{}", order_id))
+                            .delete(format!("http://localhost:8000/order/{}", order_id))
                             .send()
                             .await
                             .expect("Failed to send DELETE request");
@@ -315,4 +554,1261 @@ mod tests {
         shutdown_handle.notify();
         server_handle.await.unwrap();
     }
+
+    #[test]
+    fn put_order_unknown_item() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_table(&client, "1");
+
+        let res = client
+            .put(format!("/order/{}", Uuid::new_v4()))
+            .header(bearer(&client))
+            .json(&Order {
+                item_id: String::from("no-such-item"),
+                table_id: String::from("1"),
+            })
+            .dispatch();
+
+        assert_eq!(res.status(), Status::UnprocessableEntity);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "UNKNOWN_ITEM");
+
+        let res = client.get("/orders").header(bearer(&client)).dispatch();
+        let orders = res.into_json::<OrderList>().unwrap().orders;
+        assert!(orders.is_empty());
+    }
+
+    #[test]
+    fn put_order_unavailable_item() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_table(&client, "1");
+        let res = client
+            .put("/menu/soup")
+            .header(bearer(&client))
+            .json(&json!({
+                "name": "Soup of the day",
+                "price_cents": 650,
+                "category": "starters",
+                "preparation_time": 5,
+                "available": false,
+            }))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+
+        let res = client
+            .put(format!("/order/{}", Uuid::new_v4()))
+            .header(bearer(&client))
+            .json(&Order {
+                item_id: String::from("soup"),
+                table_id: String::from("1"),
+            })
+            .dispatch();
+
+        assert_eq!(res.status(), Status::UnprocessableEntity);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "ITEM_UNAVAILABLE");
+    }
+
+    #[test]
+    fn menu_crud() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "burger");
+        put_menu_item(&client, "steak");
+
+        let res = client.get("/menu/burger").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let item: MenuItem = res.into_json().unwrap();
+        assert_eq!(item.item_id, "burger");
+        assert_eq!(item.preparation_time, 10);
+        assert!(item.available);
+
+        let res = client.get("/menu?category=mains").dispatch();
+        let items: Vec<MenuItem> = res.into_json().unwrap();
+        assert_eq!(items.len(), 2);
+
+        let res = client.delete("/menu/burger").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+
+        let res = client.get("/menu/burger").dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "ITEM_NOT_FOUND");
+    }
+
+    #[test]
+    fn put_invalid_menu_item() {
+        let client = Client::tracked(rocket()).unwrap();
+
+        let res = client
+            .put("/menu/burger")
+            .header(bearer(&client))
+            .json(&json!({
+                "name": "Burger",
+                "price_cents": 1000,
+                "category": "mains",
+                "preparation_time": -1,
+            }))
+            .dispatch();
+
+        assert_eq!(res.status(), Status::UnprocessableEntity);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "INVALID_MENU_ITEM");
+    }
+
+    #[test]
+    fn put_order_unknown_table() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+
+        let res = client
+            .put(format!("/order/{}", Uuid::new_v4()))
+            .header(bearer(&client))
+            .json(&Order {
+                item_id: String::from("123"),
+                table_id: String::from("no-such-table"),
+            })
+            .dispatch();
+
+        assert_eq!(res.status(), Status::UnprocessableEntity);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "UNKNOWN_TABLE");
+    }
+
+    #[test]
+    fn put_order_closed_table() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
+
+        let res = client.post("/tables/1/close").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let table: Table = res.into_json().unwrap();
+        assert_eq!(table.status, TableStatus::Closed);
+
+        let order = Order {
+            item_id: String::from("123"),
+            table_id: String::from("1"),
+        };
+        let res = client.put(format!("/order/{}", Uuid::new_v4())).header(bearer(&client)).json(&order).dispatch();
+        assert_eq!(res.status(), Status::Conflict);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "TABLE_CLOSED");
+
+        let res = client.post("/tables/1/open").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+
+        let res = client.put(format!("/order/{}", Uuid::new_v4())).header(bearer(&client)).json(&order).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+    }
+
+    #[test]
+    fn tables_crud() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_table(&client, "1");
+
+        let res = client
+            .put("/tables/2")
+            .header(bearer(&client))
+            .json(&json!({
+                "capacity": 2,
+                "section": "terrace",
+                "status": "closed",
+            }))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+
+        let res = client.get("/tables/2").dispatch();
+        let table: Table = res.into_json().unwrap();
+        assert_eq!(table.capacity, 2);
+        assert_eq!(table.section, "terrace");
+        assert_eq!(table.status, TableStatus::Closed);
+
+        let res = client.get("/tables?section=main").dispatch();
+        let tables: Vec<Table> = res.into_json().unwrap();
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].table_id, "1");
+
+        let res = client.delete("/tables/1").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+
+        let res = client.post("/tables/1/open").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "TABLE_NOT_FOUND");
+
+        let res = client.put("/tables/3").header(bearer(&client)).json(&json!({"capacity": 0, "section": "bar"})).dispatch();
+        assert_eq!(res.status(), Status::UnprocessableEntity);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "INVALID_TABLE");
+    }
+
+    #[test]
+    fn cooking_time_grows_with_kitchen_load() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
+
+        // The kitchen handles 10 orders in parallel, each further batch adds 5 minutes.
+        let cooking_times: Vec<i32> = (0..11)
+            .map(|_| {
+                let res = client
+                    .put(format!("/order/{}", Uuid::new_v4()))
+                    .header(bearer(&client))
+                    .json(&Order {
+                        item_id: String::from("123"),
+                        table_id: String::from("1"),
+                    })
+                    .dispatch();
+                res.into_json::<OrderResult>().unwrap().cooking_time
+            })
+            .collect();
+
+        assert!(cooking_times[..10].iter().all(|&t| t == 10));
+        assert_eq!(cooking_times[10], 15);
+    }
+
+    #[test]
+    fn order_lifecycle() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
+        let uuid = Uuid::new_v4();
+
+        let res = client
+            .put(format!("/order/{}", uuid))
+            .header(bearer(&client))
+            .json(&Order {
+                item_id: String::from("123"),
+                table_id: String::from("1"),
+            })
+            .dispatch();
+        let order: OrderResult = res.into_json().unwrap();
+        assert_eq!(order.status, "placed");
+
+        let res = client.post(format!("/order/{}/served", uuid)).header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Conflict);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "INVALID_TRANSITION");
+
+        for (action, status) in [("start", "cooking"), ("ready", "ready")] {
+            let res = client.post(format!("/order/{}/{}", uuid, action)).header(bearer(&client)).dispatch();
+            assert_eq!(res.status(), Status::Ok);
+            let order: OrderResult = res.into_json().unwrap();
+            assert_eq!(order.status, status);
+        }
+
+        let res = client.get("/orders?status=ready").header(bearer(&client)).dispatch();
+        let orders = res.into_json::<OrderList>().unwrap().orders;
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].order_id, uuid.to_string());
+
+        let res = client.get("/orders?table_id=1&status=cooking").header(bearer(&client)).dispatch();
+        let orders = res.into_json::<OrderList>().unwrap().orders;
+        assert!(orders.is_empty());
+
+        let res = client.post(format!("/order/{}/served", uuid)).header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+
+        let res = client.post(format!("/order/{}/cancel", uuid)).header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Conflict);
+
+        let res = client.post(format!("/order/{}/start", Uuid::new_v4())).header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "ORDER_NOT_FOUND");
+    }
+
+    #[test]
+    fn get_orders_unknown_status() {
+        let client = Client::tracked(rocket()).unwrap();
+
+        let res = client.get("/orders?status=eaten").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "INVALID_STATUS");
+    }
+
+    #[test]
+    fn order_timestamps() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
+        let uuid = Uuid::new_v4();
+
+        let res = client
+            .put(format!("/order/{}", uuid))
+            .header(bearer(&client))
+            .json(&Order {
+                item_id: String::from("123"),
+                table_id: String::from("1"),
+            })
+            .dispatch();
+        let placed: OrderResult = res.into_json().unwrap();
+        assert_eq!(placed.created_at, placed.updated_at);
+        assert_eq!(placed.estimated_ready_at, placed.created_at + Duration::minutes(placed.cooking_time as i64));
+
+        let res = client.post(format!("/order/{}/start", uuid)).header(bearer(&client)).dispatch();
+        let started: OrderResult = res.into_json().unwrap();
+        assert_eq!(started.created_at, placed.created_at);
+        assert!(started.updated_at >= placed.updated_at);
+
+        let since = placed.created_at.to_rfc3339_opts(SecondsFormat::Micros, true);
+        let res = client.get(format!("/orders?table_id=1&since={}", since)).header(bearer(&client)).dispatch();
+        let orders = res.into_json::<OrderList>().unwrap().orders;
+        assert_eq!(orders.len(), 1);
+
+        let res = client.get(format!("/orders?table_id=1&until={}", since)).header(bearer(&client)).dispatch();
+        let orders = res.into_json::<OrderList>().unwrap().orders;
+        assert!(orders.is_empty());
+
+        let res = client.get("/orders?since=yesterday").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "INVALID_TIMESTAMP");
+    }
+
+    #[test]
+    fn put_order_with_modifiers() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
+
+        let res = client
+            .put(format!("/order/{}", Uuid::new_v4()))
+            .header(bearer(&client))
+            .json(&json!({
+                "item_id": "123",
+                "table_id": "1",
+                "quantity": 2,
+                "modifiers": [
+                    {"type": "remove", "ingredient": "onion"},
+                    {"type": "doneness", "level": "medium_rare"},
+                ],
+                "notes": "sauce on the side",
+            }))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let order: OrderDetails = res.into_json().unwrap();
+        let order = json!(order);
+        assert_eq!(order["quantity"], 2);
+        assert_eq!(order["modifiers"][0], json!({"type": "remove", "ingredient": "onion"}));
+        assert_eq!(order["notes"], "sauce on the side");
+        // 10 minutes + 1 extra portion * 2 + 1 removed ingredient * 1.
+        assert_eq!(order["cooking_time"], 13);
+
+        let res = client
+            .put(format!("/order/{}", Uuid::new_v4()))
+            .header(bearer(&client))
+            .json(&json!({"item_id": "123", "table_id": "1", "quantity": 0}))
+            .dispatch();
+        assert_eq!(res.status(), Status::UnprocessableEntity);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "INVALID_ORDER");
+
+        let res = client
+            .put(format!("/order/{}", Uuid::new_v4()))
+            .header(bearer(&client))
+            .json(&json!({
+                "item_id": "123",
+                "table_id": "1",
+                "modifiers": [{"type": "doneness", "level": "burnt"}],
+            }))
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "INVALID_BODY");
+    }
+
+    #[test]
+    fn get_single_order() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
+        let uuid = Uuid::new_v4();
+
+        let res = client
+            .put(format!("/order/{}", uuid))
+            .header(bearer(&client))
+            .json(&Order {
+                item_id: String::from("123"),
+                table_id: String::from("1"),
+            })
+            .dispatch();
+        let created: OrderResult = res.into_json().unwrap();
+
+        client.post(format!("/order/{}/start", uuid)).header(bearer(&client)).dispatch();
+
+        let res = client.get(format!("/order/{}", uuid)).header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let order: OrderResult = res.into_json().unwrap();
+        assert_eq!(order.order_id, uuid.to_string());
+        assert_eq!(order.cooking_time, created.cooking_time);
+        assert_eq!(order.status, "cooking");
+
+        let res = client.get(format!("/order/{}", Uuid::new_v4())).header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "ORDER_NOT_FOUND");
+    }
+
+    #[test]
+    fn get_orders_paginated() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
+        let mut created = Vec::new();
+        for _ in 0..5 {
+            let uuid = Uuid::new_v4().to_string();
+            let res = client
+                .put(format!("/order/{}", uuid))
+                .header(bearer(&client))
+                .json(&Order {
+                    item_id: String::from("123"),
+                    table_id: String::from("1"),
+                })
+                .dispatch();
+            assert_eq!(res.status(), Status::Ok);
+            created.push(uuid);
+        }
+        created.sort();
+
+        let mut seen = Vec::new();
+        let mut url = "/orders?sort=table&limit=2".to_string();
+        loop {
+            let res = client.get(&url).header(bearer(&client)).dispatch();
+            assert_eq!(res.status(), Status::Ok);
+            let page = res.into_json::<OrderList>().unwrap();
+            assert!(page.orders.len() <= 2);
+            seen.extend(page.orders.into_iter().map(|order| order.order_id));
+            match page.next_cursor {
+                Some(cursor) => url = format!("/orders?limit=2&cursor={}", cursor),
+                None => break,
+            }
+        }
+        assert_eq!(seen, created);
+
+        for (query, error_code) in [
+            ("limit=0", "INVALID_LIMIT"),
+            ("limit=1001", "INVALID_LIMIT"),
+            ("sort=price", "INVALID_SORT"),
+            ("direction=up", "INVALID_SORT"),
+            ("cursor=garbage", "INVALID_CURSOR"),
+        ] {
+            let res = client.get(format!("/orders?{}", query)).header(bearer(&client)).dispatch();
+            assert_eq!(res.status(), Status::BadRequest, "{}", query);
+            let error_response: ErrorResponse = res.into_json().unwrap();
+            assert_eq!(error_response.error_code, error_code);
+        }
+
+        let res = client.get("/orders?sort=table&limit=2").header(bearer(&client)).dispatch();
+        let cursor = res.into_json::<OrderList>().unwrap().next_cursor.unwrap();
+        let res = client.get(format!("/orders?sort=item&cursor={}", cursor)).header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "INVALID_CURSOR");
+    }
+
+    #[test]
+    fn get_orders_by_multiple_values() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        let mut order_ids = Vec::new();
+        for table_id in ["1", "2", "3"] {
+            put_table(&client, table_id);
+            let uuid = Uuid::new_v4().to_string();
+            let res = client
+                .put(format!("/order/{}", uuid))
+                .header(bearer(&client))
+                .json(&Order {
+                    item_id: String::from("123"),
+                    table_id: String::from(table_id),
+                })
+                .dispatch();
+            assert_eq!(res.status(), Status::Ok);
+            order_ids.push(uuid);
+        }
+        let res = client.post(format!("/order/{}/start", order_ids[1])).header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+
+        let res = client.get("/orders?table_id=1&table_id=2&status=placed&status=cooking").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let orders = res.into_json::<OrderList>().unwrap().orders;
+        let mut found: Vec<String> = orders.into_iter().map(|order| order.order_id).collect();
+        found.sort();
+        let mut expected = order_ids[..2].to_vec();
+        expected.sort();
+        assert_eq!(found, expected);
+
+        let res = client.get("/orders?table_id=1&table_id=3&status=cooking").header(bearer(&client)).dispatch();
+        assert!(res.into_json::<OrderList>().unwrap().orders.is_empty());
+
+        let res = client.get("/orders?table_id=1&table_id=2&since=2100-01-01T00:00:00Z").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert!(res.into_json::<OrderList>().unwrap().orders.is_empty());
+    }
+
+    // Splits a Server-Sent Events body into (event type, id, order id) triples.
+    fn parse_events(body: &str) -> Vec<(String, String, String)> {
+        body.split("\n\n")
+            .filter(|event| !event.is_empty() && !event.starts_with(':'))
+            .map(|event| {
+                let (mut kind, mut id, mut data) = (String::new(), String::new(), String::new());
+                for line in event.lines() {
+                    if let Some(value) = line.strip_prefix("event:") {
+                        kind = value.trim().to_string();
+                    } else if let Some(value) = line.strip_prefix("id:") {
+                        id = value.trim().to_string();
+                    } else if let Some(value) = line.strip_prefix("data:") {
+                        data.push_str(value);
+                    }
+                }
+                let order_id = rocket::serde::json::from_str::<OrderResult>(&data)
+                    .map(|order| order.order_id)
+                    .unwrap_or_default();
+                (kind, id, order_id)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn order_events() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
+        put_table(&client, "2");
+        let put = |table_id: &str| {
+            let uuid = Uuid::new_v4().to_string();
+            let res = client
+                .put(format!("/order/{}", uuid))
+                .header(bearer(&client))
+                .json(&Order {
+                    item_id: String::from("123"),
+                    table_id: String::from(table_id),
+                })
+                .dispatch();
+            assert_eq!(res.status(), Status::Ok);
+            uuid
+        };
+        let epoch = client.rocket().state::<Arc<OrderEvents>>().unwrap().epoch();
+        let event_id = |id: u64| EventId { epoch, id }.to_string();
+        let event = |kind: &str, id: u64, order_id: &str| (kind.to_string(), event_id(id), order_id.to_string());
+
+        let order1 = put("1");
+        let all_events = client.get("/orders/events")
+            .header(bearer(&client))
+            .header(Header::new("Last-Event-ID", event_id(1)))
+            .dispatch();
+        assert_eq!(all_events.status(), Status::Ok);
+        assert_eq!(all_events.content_type(), Some(ContentType::EventStream));
+        let table2_events = client.get("/orders/events?table_id=2").header(bearer(&client)).dispatch();
+
+        let order2 = put("2");
+        let res = client.post(format!("/order/{}/start", order2)).header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let res = client.delete(format!("/order/{}", order1)).header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+
+        // Streams end when the server shuts down, after the events sent so far.
+        client.rocket().shutdown().notify();
+        assert_eq!(parse_events(&all_events.into_string().unwrap()), vec![
+            event("created", 2, &order2),
+            event("updated", 3, &order2),
+            event("deleted", 4, &order1),
+        ]);
+        assert_eq!(parse_events(&table2_events.into_string().unwrap()), vec![
+            event("created", 2, &order2),
+            event("updated", 3, &order2),
+        ]);
+
+        // A reconnecting client gets the events it missed.
+        let res = client.get("/orders/events?table_id=1")
+            .header(bearer(&client))
+            .header(Header::new("Last-Event-ID", event_id(0)))
+            .dispatch();
+        assert_eq!(parse_events(&res.into_string().unwrap()), vec![
+            event("created", 1, &order1),
+            event("deleted", 4, &order1),
+        ]);
+
+        let res = client.get("/orders/events")
+            .header(bearer(&client))
+            .header(Header::new("Last-Event-ID", event_id(100)))
+            .dispatch();
+        assert_eq!(parse_events(&res.into_string().unwrap()), vec![event("resync", 4, "")]);
+
+        // Ids from before a restart have another epoch, or none at all.
+        for last_event_id in [EventId { epoch: epoch.wrapping_add(1), id: 1 }.to_string(), "1".to_string()] {
+            let res = client.get("/orders/events")
+                .header(bearer(&client))
+                .header(Header::new("Last-Event-ID", last_event_id))
+                .dispatch();
+            assert_eq!(parse_events(&res.into_string().unwrap()), vec![event("resync", 4, "")]);
+        }
+
+        let res = client.get("/orders/events")
+            .header(bearer(&client))
+            .header(Header::new("Last-Event-ID", "latest"))
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "INVALID_EVENT_ID");
+    }
+
+    #[test]
+    fn get_order_changes() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
+        let mut order_ids = Vec::new();
+        for _ in 0..2 {
+            let uuid = Uuid::new_v4().to_string();
+            let res = client
+                .put(format!("/order/{}", uuid))
+                .header(bearer(&client))
+                .json(&Order {
+                    item_id: String::from("123"),
+                    table_id: String::from("1"),
+                })
+                .dispatch();
+            assert_eq!(res.status(), Status::Ok);
+            order_ids.push(uuid);
+        }
+
+        let res = client.get("/orders/changes").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let changes: serde_json::Value = res.into_json().unwrap();
+        assert_eq!(changes["last_seq"], 2);
+        assert_eq!(changes["changes"].as_array().unwrap().len(), 2);
+
+        let res = client.post(format!("/order/{}/start", order_ids[0])).header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let res = client.delete(format!("/order/{}", order_ids[1])).header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+
+        let res = client.get("/orders/changes?since=2").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let changes: serde_json::Value = res.into_json().unwrap();
+        assert_eq!(changes, json!({
+            "changes": [
+                {
+                    "seq": 3,
+                    "order_id": order_ids[0],
+                    "deleted": false,
+                    "order": changes["changes"][0]["order"],
+                },
+                {"seq": 4, "order_id": order_ids[1], "deleted": true, "order": null},
+            ],
+            "last_seq": 4,
+        }));
+        assert_eq!(changes["changes"][0]["order"]["status"], "cooking");
+        assert_eq!(changes["changes"][0]["order"]["seq"], 3);
+
+        let res = client.get("/orders/changes?since=5").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Gone);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "RESYNC_REQUIRED");
+
+        let res = client.get("/orders/changes?since=-1").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "INVALID_SEQ");
+    }
+
+    #[test]
+    fn webhooks() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
+
+        // Nothing listens on the port once the listener is dropped, so deliveries fail.
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let res = client
+            .put("/webhooks/pos")
+            .header(bearer(&client))
+            .json(&json!({
+                "url": format!("http://127.0.0.1:{}/hooks", port),
+                "events": ["order_created", "order_deleted"],
+                "secret": "secret",
+            }))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let webhook: serde_json::Value = res.into_json().unwrap();
+        assert_eq!(webhook, json!({
+            "webhook_id": "pos",
+            "url": format!("http://127.0.0.1:{}/hooks", port),
+            "events": ["order_created", "order_deleted"],
+        }));
+
+        let res = client.get("/webhooks?event=order_created").header(bearer(&client)).dispatch();
+        assert_eq!(res.into_json::<serde_json::Value>().unwrap(), json!([webhook]));
+        let res = client.get("/webhooks?event=order_ready").header(bearer(&client)).dispatch();
+        assert_eq!(res.into_json::<serde_json::Value>().unwrap(), json!([]));
+
+        let uuid = Uuid::new_v4().to_string();
+        let res = client
+            .put(format!("/order/{}", uuid))
+            .header(bearer(&client))
+            .json(&Order {
+                item_id: String::from("123"),
+                table_id: String::from("1"),
+            })
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+
+        // The first attempt is made right away, the next one after a backoff.
+        let mut deliveries = serde_json::Value::Null;
+        for _ in 0..500 {
+            let res = client.get("/webhooks/deliveries?webhook_id=pos").header(bearer(&client)).dispatch();
+            assert_eq!(res.status(), Status::Ok);
+            deliveries = res.into_json().unwrap();
+            if deliveries[0]["attempts"] == 1 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(deliveries.as_array().unwrap().len(), 1);
+        assert_eq!(deliveries[0]["event"], "order_created");
+        assert_eq!(deliveries[0]["order_id"], uuid);
+        assert_eq!(deliveries[0]["status"], "pending");
+        assert_eq!(deliveries[0]["attempts"], 1);
+        assert!(deliveries[0]["error"].is_string());
+        assert!(deliveries[0]["next_attempt_at"].is_string());
+
+        let res = client.get("/webhooks/deliveries?status=delivered").header(bearer(&client)).dispatch();
+        assert_eq!(res.into_json::<serde_json::Value>().unwrap(), json!([]));
+        let res = client.get("/webhooks/deliveries?status=lost").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "INVALID_STATUS");
+
+        let res = client.delete("/webhooks/pos").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let res = client.get("/webhooks/pos").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "WEBHOOK_NOT_FOUND");
+    }
+
+    #[test]
+    fn put_invalid_webhook() {
+        let client = Client::tracked(rocket()).unwrap();
+
+        let res = client
+            .put("/webhooks/pos")
+            .header(bearer(&client))
+            .json(&json!({"url": "pos.local", "events": ["order_created"], "secret": "secret"}))
+            .dispatch();
+        assert_eq!(res.status(), Status::UnprocessableEntity);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "INVALID_WEBHOOK");
+
+        let res = client
+            .put("/webhooks/pos")
+            .header(bearer(&client))
+            .json(&json!({"url": "http://pos.local", "events": ["order_cooked"], "secret": "secret"}))
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "INVALID_BODY");
+
+        let res = client.get("/webhooks?event=order_cooked").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "INVALID_EVENT");
+    }
+
+    // Rocket's /order/<id> is /order/{id} in OpenAPI.
+    fn openapi_path(path: &str) -> String {
+        path.split('/')
+            .map(|segment| match segment.strip_prefix('<') {
+                Some(param) => format!("{{{}}}", param.trim_end_matches('>').trim_end_matches("..")),
+                None => segment.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    #[test]
+    fn openapi_describes_every_route() {
+        let client = Client::tracked(rocket()).unwrap();
+        let res = client.get("/openapi.json").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let spec: serde_json::Value = res.into_json().unwrap();
+        assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+        let catalog: Vec<ErrorCodeInfo> = client.get("/errors").dispatch().into_json().unwrap();
+
+        for route in client.rocket().routes() {
+            let path = openapi_path(route.uri.path());
+            let method = route.method.as_str().to_lowercase();
+            let operation = &spec["paths"][&path][&method];
+            assert!(operation.is_object(), "{} {} is not described in the OpenAPI document", method, path);
+
+            // Every error response names the error codes it is returned with.
+            for (status, response) in operation["responses"].as_object().unwrap() {
+                if status.as_str() >= "400" {
+                    let code = response["description"].as_str().unwrap().split(':').next().unwrap();
+                    assert!(
+                        !code.is_empty() && code.chars().all(|c| c.is_ascii_uppercase() || c == '_'),
+                        "{} {} does not name the error code of {}", method, path, status
+                    );
+                    // ...and those codes are in the catalog, with the same status.
+                    for code in response["description"].as_str().unwrap().split("; ") {
+                        let code = code.split(':').next().unwrap();
+                        assert!(
+                            catalog.iter().any(|info| info.code == code && info.status.to_string() == *status),
+                            "{} {} responds with {} {}, which is not in the catalog", method, path, status, code
+                        );
+                    }
+                    assert_eq!(response["content"]["application/json"]["schema"]["$ref"], "#/components/schemas/ErrorResponse");
+                }
+            }
+
+            // ...and the routes POLICY restricts require a bearer token.
+            let secured = operation["security"].as_array().is_some_and(|security| !security.is_empty());
+            let mounted = route.uri.path().trim_end_matches('/').to_string();
+            let restricted = POLICY.iter().any(|(m, p, _)| *m == route.method && *p == mounted);
+            assert_eq!(secured, restricted, "{} {}", method, path);
+        }
+        assert_eq!(spec["components"]["securitySchemes"]["bearer"]["scheme"], "bearer");
+    }
+
+    #[test]
+    fn restricted_routes_require_a_token() {
+        let client = Client::tracked(rocket()).unwrap();
+        let other_auth = Auth::new(b"another secret", Default::default(), std::time::Duration::from_secs(60));
+        let foreign_token = other_auth.issue("test-device", Role::Manager, None).unwrap().token;
+
+        for authorization in [
+            None,
+            Some("Basic dXNlcjpwYXNz".to_string()),
+            Some("Bearer not-a-token".to_string()),
+            Some(format!("Bearer {}", foreign_token)),
+        ] {
+            for (method, path) in [
+                ("PUT", "/order/123"),
+                ("GET", "/order/123"),
+                ("DELETE", "/order/123"),
+                ("POST", "/order/123/start"),
+                ("GET", "/orders"),
+                ("GET", "/orders/events"),
+                ("GET", "/orders/changes"),
+                ("PUT", "/menu/123"),
+                ("DELETE", "/tables/123"),
+                ("POST", "/tables/123/close"),
+                ("GET", "/webhooks"),
+                ("GET", "/webhooks/deliveries"),
+            ] {
+                let mut req = match method {
+                    "GET" => client.get(path),
+                    "PUT" => client.put(path).json(&Order { item_id: "1".to_string(), table_id: "1".to_string() }),
+                    "POST" => client.post(path),
+                    _ => client.delete(path),
+                };
+                if let Some(authorization) = &authorization {
+                    req = req.header(Header::new("Authorization", authorization.clone()));
+                }
+                let res = req.dispatch();
+                assert_eq!(res.status(), Status::Unauthorized, "{} {} with {:?}", method, path, authorization);
+                assert_eq!(res.headers().get_one("WWW-Authenticate"), Some("Bearer"));
+                let error_response: ErrorResponse = res.into_json().unwrap();
+                assert_eq!(error_response.error_code, "UNAUTHENTICATED");
+            }
+        }
+
+        let res = client.get("/orders").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        // Reading the menu and the tables requires no token.
+        let res = client.get("/menu").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let res = client.get("/tables").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn generated_enrollment_keys_are_only_readable_by_the_owner() {
+        use std::os::unix::fs::PermissionsExt;
+        let path = std::env::temp_dir().join(format!("restaurant_api_keys_{}", Uuid::new_v4()));
+        std::fs::write(&path, "stale keys of another start").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        let keys = vec![(Role::Waiter, "abc".to_string()), (Role::Manager, "def".to_string())];
+        write_enrollment_keys(path.to_str().unwrap(), &keys).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "waiter abc\nmanager def\n");
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+        #[test]
+    fn enroll_device() {
+        let client = Client::tracked(rocket()).unwrap();
+        let auth = client.rocket().state::<Auth>().unwrap();
+        let enrollment_key = auth.enrollment_key(Role::Waiter).to_string();
+        let manager_key = auth.enrollment_key(Role::Manager).to_string();
+
+        let res = client
+            .post("/auth/token")
+            .json(&json!({"device_id": "watch-17", "role": "waiter", "enrollment_key": "guess"}))
+            .dispatch();
+        assert_eq!(res.status(), Status::Unauthorized);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "UNAUTHENTICATED");
+
+        // The key of one role does not enroll a device as another.
+        let res = client
+            .post("/auth/token")
+            .json(&json!({"device_id": "watch-17", "role": "manager", "enrollment_key": enrollment_key}))
+            .dispatch();
+        assert_eq!(res.status(), Status::Unauthorized);
+
+        let res = client
+            .post("/auth/token")
+            .json(&json!({"device_id": "watch 17", "role": "waiter", "enrollment_key": enrollment_key}))
+            .dispatch();
+        assert_eq!(res.status(), Status::UnprocessableEntity);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "VALIDATION_FAILED");
+
+        let res = client
+            .post("/auth/token")
+            .json(&json!({"device_id": "watch-17", "role": "waiter", "section": "terrace", "enrollment_key": enrollment_key}))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let issued: serde_json::Value = res.into_json().unwrap();
+        assert_eq!(issued["token_type"], "Bearer");
+        assert!(issued["expires_at"].as_str().unwrap().parse::<DateTime<Utc>>().unwrap() > Utc::now());
+
+        let token = issued["token"].as_str().unwrap();
+        let res = client.get("/orders").header(Header::new("Authorization", format!("bearer {}", token))).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+
+        let res = client
+            .post("/auth/token")
+            .json(&json!({"device_id": "tablet-1", "role": "manager", "enrollment_key": manager_key}))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+    }
+
+    fn put_order_as(client: &Client, order_id: &str, table_id: &str, device: Header<'static>) -> Status {
+        client
+            .put(format!("/order/{}", order_id))
+            .header(device)
+            .json(&Order { item_id: String::from("123"), table_id: String::from(table_id) })
+            .dispatch()
+            .status()
+    }
+
+    fn assert_forbidden(res: rocket::local::blocking::LocalResponse<'_>) {
+        assert_eq!(res.status(), Status::Forbidden);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "FORBIDDEN");
+    }
+
+    #[test]
+    fn put_order_by_role() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
+
+        let waiter = bearer_as(&client, "watch-17", Role::Waiter, None);
+        assert_eq!(put_order_as(&client, &Uuid::new_v4().to_string(), "1", waiter), Status::Ok);
+        assert_eq!(put_order_as(&client, &Uuid::new_v4().to_string(), "1", bearer(&client)), Status::Ok);
+
+        let res = client
+            .put(format!("/order/{}", Uuid::new_v4()))
+            .header(bearer_as(&client, "pass-1", Role::Kitchen, None))
+            .json(&Order { item_id: String::from("123"), table_id: String::from("1") })
+            .dispatch();
+        assert_forbidden(res);
+    }
+
+    #[test]
+    fn get_orders_by_placed_by() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
+        let own = Uuid::new_v4().to_string();
+        let waiter = bearer_as(&client, "watch-17", Role::Waiter, None);
+        assert_eq!(put_order_as(&client, &own, "1", waiter.clone()), Status::Ok);
+        let other = bearer_as(&client, "watch-18", Role::Waiter, None);
+        assert_eq!(put_order_as(&client, &Uuid::new_v4().to_string(), "1", other), Status::Ok);
+
+        let res = client.get("/orders?placed_by=watch-17").header(waiter).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let orders = res.into_json::<OrderList>().unwrap().orders;
+        let found: Vec<String> = orders.into_iter().map(|order| order.order_id).collect();
+        assert_eq!(found, vec![own]);
+
+        let res = client.get("/orders?placed_by=watch-17&placed_by=watch-18").header(bearer(&client)).dispatch();
+        assert_eq!(res.into_json::<OrderList>().unwrap().orders.len(), 2);
+    }
+
+    #[test]
+    fn get_order_by_role() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
+        let uuid = Uuid::new_v4();
+        assert_eq!(put_order_as(&client, &uuid.to_string(), "1", bearer(&client)), Status::Ok);
+
+        for role in Role::ALL {
+            let device = bearer_as(&client, "test-device", role, None);
+            let res = client.get(format!("/order/{}", uuid)).header(device.clone()).dispatch();
+            assert_eq!(res.status(), Status::Ok, "{:?}", role);
+            let res = client.get("/orders").header(device).dispatch();
+            assert_eq!(res.status(), Status::Ok, "{:?}", role);
+        }
+    }
+
+    #[test]
+    fn delete_order_by_role() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
+        let res = client
+            .put("/tables/2")
+            .header(bearer(&client))
+            .json(&json!({"capacity": 2, "section": "terrace"}))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+
+        let orders: Vec<String> = (0..4).map(|_| Uuid::new_v4().to_string()).collect();
+        let waiter = |device_id: &str, section: Option<&str>| bearer_as(&client, device_id, Role::Waiter, section);
+        assert_eq!(put_order_as(&client, &orders[0], "1", waiter("watch-17", None)), Status::Ok);
+        assert_eq!(put_order_as(&client, &orders[1], "2", waiter("watch-18", None)), Status::Ok);
+        assert_eq!(put_order_as(&client, &orders[2], "1", waiter("watch-18", None)), Status::Ok);
+        assert_eq!(put_order_as(&client, &orders[3], "1", waiter("watch-18", None)), Status::Ok);
+
+        // The kitchen deletes no orders.
+        let res = client.delete(format!("/order/{}", orders[0])).header(bearer_as(&client, "pass-1", Role::Kitchen, None)).dispatch();
+        assert_forbidden(res);
+
+        // A waiter deletes the orders they placed...
+        let res = client.delete(format!("/order/{}", orders[0])).header(waiter("watch-17", None)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        // ...and the orders of their section's tables, but not others.
+        let res = client.delete(format!("/order/{}", orders[1])).header(waiter("watch-17", Some("terrace"))).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let res = client.delete(format!("/order/{}", orders[2])).header(waiter("watch-17", Some("terrace"))).dispatch();
+        assert_forbidden(res);
+
+        // Managers delete every order.
+        let res = client.delete(format!("/order/{}", orders[2])).header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+
+        let res = client.get("/orders").header(bearer(&client)).dispatch();
+        let remaining: Vec<String> = res.into_json::<OrderList>().unwrap().orders.into_iter().map(|order| order.order_id).collect();
+        assert_eq!(remaining, vec![orders[3].clone()]);
+    }
+
+    #[test]
+    fn order_status_changes_by_role() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
+        let uuid = Uuid::new_v4();
+        assert_eq!(put_order_as(&client, &uuid.to_string(), "1", bearer(&client)), Status::Ok);
+
+        let res = client.post(format!("/order/{}/start", uuid)).header(bearer_as(&client, "watch-17", Role::Waiter, None)).dispatch();
+        assert_forbidden(res);
+        let res = client.post(format!("/order/{}/start", uuid)).header(bearer_as(&client, "pass-1", Role::Kitchen, None)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+    }
+
+    #[test]
+    fn admin_routes_are_for_managers() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
+
+        for role in [Role::Waiter, Role::Kitchen] {
+            let device = bearer_as(&client, "test-device", role, Some("main"));
+            let menu_item = json!({"name": "Item", "price_cents": 1000, "category": "mains", "preparation_time": 10});
+            assert_forbidden(client.put("/menu/123").header(device.clone()).json(&menu_item).dispatch());
+            assert_forbidden(client.delete("/menu/123").header(device.clone()).dispatch());
+            let table = json!({"capacity": 4, "section": "main"});
+            assert_forbidden(client.put("/tables/1").header(device.clone()).json(&table).dispatch());
+            assert_forbidden(client.post("/tables/1/close").header(device.clone()).dispatch());
+            assert_forbidden(client.delete("/tables/1").header(device.clone()).dispatch());
+            let webhook = json!({"url": "http://127.0.0.1/hooks", "events": ["order_created"], "secret": "secret"});
+            assert_forbidden(client.put("/webhooks/pos").header(device.clone()).json(&webhook).dispatch());
+            for path in ["/webhooks", "/webhooks/pos", "/webhooks/deliveries"] {
+                assert_forbidden(client.get(path).header(device.clone()).dispatch());
+            }
+            assert_forbidden(client.delete("/webhooks/pos").header(device.clone()).dispatch());
+
+            // Everyone may read the menu and the tables.
+            assert_eq!(client.get("/menu/123").header(device.clone()).dispatch().status(), Status::Ok);
+            assert_eq!(client.get("/tables/1").header(device).dispatch().status(), Status::Ok);
+        }
+    }
+
+    #[test]
+    fn audit_log_records_mutations() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
+        let uuid = Uuid::new_v4().to_string();
+        let waiter = bearer_as(&client, "watch-17", Role::Waiter, None);
+
+        assert_eq!(put_order_as(&client, &uuid, "1", waiter.clone()), Status::Ok);
+        // A retry changes nothing, so it is not recorded.
+        assert_eq!(put_order_as(&client, &uuid, "1", waiter.clone()), Status::Ok);
+        let res = client.post(format!("/order/{}/start", uuid)).header(bearer_as(&client, "pass-1", Role::Kitchen, None)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let res = client
+            .delete(format!("/order/{}", uuid))
+            .header(waiter)
+            .header(Header::new(REASON_HEADER, "guest left"))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+
+        let res = client.get(format!("/audit?order_id={}", uuid)).header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let records: Vec<serde_json::Value> = res.into_json().unwrap();
+        let operations: Vec<&str> = records.iter().map(|record| record["operation"].as_str().unwrap()).collect();
+        assert_eq!(operations, vec!["create_order", "start_order", "delete_order"]);
+
+        assert_eq!(records[0]["device"], "watch-17");
+        assert_eq!(records[0]["role"], "waiter");
+        assert!(records[0]["before"].is_null());
+        assert_eq!(records[0]["after"]["status"], "placed");
+        assert_eq!(records[1]["device"], "pass-1");
+        assert_eq!(records[1]["role"], "kitchen");
+        assert_eq!(records[1]["before"]["status"], "placed");
+        assert_eq!(records[1]["after"]["status"], "cooking");
+        assert_eq!(records[2]["before"]["status"], "cooking");
+        assert!(records[2]["after"].is_null());
+        assert_eq!(records[2]["reason"], "guest left");
+        assert!(records[0]["reason"].is_null());
+
+        let res = client.get("/audit?device=pass-1&operation=start_order").header(bearer(&client)).dispatch();
+        let records: Vec<serde_json::Value> = res.into_json().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["order_id"], uuid);
+
+        let since = (Utc::now() + Duration::hours(1)).to_rfc3339_opts(SecondsFormat::Secs, true);
+        let res = client.get(format!("/audit?since={}", since)).header(bearer(&client)).dispatch();
+        let records: Vec<serde_json::Value> = res.into_json().unwrap();
+        assert!(records.is_empty());
+
+        let res = client.get("/audit?operation=update_order").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "INVALID_OPERATION");
+
+        let res = client.get("/audit?until=yesterday").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "INVALID_TIMESTAMP");
+    }
+
+    #[test]
+    fn audit_log_export() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
+        let order_ids: Vec<String> = (0..2).map(|_| Uuid::new_v4().to_string()).collect();
+        for order_id in &order_ids {
+            assert_eq!(put_order_as(&client, order_id, "1", bearer(&client)), Status::Ok);
+        }
+
+        let res = client.get(format!("/audit/export?order_id={}", order_ids[1])).header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(res.content_type(), Some(ContentType::new("application", "x-ndjson")));
+        let body = res.into_string().unwrap();
+        let lines: Vec<serde_json::Value> = body.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["order_id"], order_ids[1]);
+        assert_eq!(lines[0]["operation"], "create_order");
+        assert!(body.ends_with('\n'));
+    }
+
+    #[test]
+    fn audit_log_is_for_managers() {
+        let client = Client::tracked(rocket()).unwrap();
+
+        let res = client.get("/audit").dispatch();
+        assert_eq!(res.status(), Status::Unauthorized);
+
+        for role in [Role::Waiter, Role::Kitchen] {
+            for path in ["/audit", "/audit/export"] {
+                let res = client.get(path).header(bearer_as(&client, "test-device", role, None)).dispatch();
+                assert_forbidden(res);
+            }
+        }
+    }
+
+    #[test]
+    fn audit_reason_is_validated() {
+        let client = Client::tracked(rocket()).unwrap();
+        put_menu_item(&client, "123");
+        put_table(&client, "1");
+        let uuid = Uuid::new_v4();
+
+        let res = client
+            .put(format!("/order/{}", uuid))
+            .header(bearer(&client))
+            .header(Header::new(REASON_HEADER, "x".repeat(201)))
+            .json(&Order { item_id: String::from("123"), table_id: String::from("1") })
+            .dispatch();
+        assert_eq!(res.status(), Status::UnprocessableEntity);
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.error_code, "VALIDATION_FAILED");
+        assert_eq!(error_response.fields[0].field, REASON_HEADER);
+
+        // The rejected request changed nothing.
+        let res = client.get(format!("/order/{}", uuid)).header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        let records: Vec<serde_json::Value> = client.get("/audit").header(bearer(&client)).dispatch().into_json().unwrap();
+        assert!(records.is_empty());
+    }
+
+    #[test]
+    fn errors_carry_correlation_id() {
+        let client = Client::tracked(rocket()).unwrap();
+
+        let res = client.get("/order/unknown").header(bearer(&client)).dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        let header = res.headers().get_one(CORRELATION_HEADER).unwrap().to_string();
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.correlation_id, header);
+        assert!(Uuid::parse_str(&error_response.correlation_id).is_ok());
+
+        // Every request gets its own.
+        let res = client.get("/order/unknown").header(bearer(&client)).dispatch();
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_ne!(error_response.correlation_id, header);
+
+        // A client's own correlation id is used, unless it is malformed.
+        let res = client.get("/order/unknown").header(bearer(&client)).header(Header::new(CORRELATION_HEADER, "device-7.42")).dispatch();
+        assert_eq!(res.headers().get_one(CORRELATION_HEADER), Some("device-7.42"));
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert_eq!(error_response.correlation_id, "device-7.42");
+
+        let res = client.get("/order/unknown").header(bearer(&client)).header(Header::new(CORRELATION_HEADER, "device 7")).dispatch();
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert!(Uuid::parse_str(&error_response.correlation_id).is_ok());
+
+        // Errors Rocket responds with on its own carry one too.
+        let res = client.get("/orderz").dispatch();
+        let error_response: ErrorResponse = res.into_json().unwrap();
+        assert!(Uuid::parse_str(&error_response.correlation_id).is_ok());
+    }
+
+    #[test]
+    fn error_catalog() {
+        let client = Client::tracked(rocket()).unwrap();
+
+        let res = client.get("/errors").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let catalog: Vec<ErrorCodeInfo> = res.into_json().unwrap();
+
+        let order_not_found = catalog.iter().find(|info| info.code == "ORDER_NOT_FOUND").unwrap();
+        assert_eq!(order_not_found.status, 404);
+        assert!(!order_not_found.description.is_empty());
+        assert!(catalog.iter().any(|info| info.code == "VALIDATION_FAILED" && info.status == 422));
+        assert!(catalog.iter().any(|info| info.code == "INTERNAL_ERROR" && info.status == 500));
+    }
 }
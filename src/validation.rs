@@ -0,0 +1,112 @@
+use rocket::serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::{Uuid, Version};
+
+// Bounds on the ids and free-text fields accepted by the API. Ids (of menu items, tables,
+// webhooks and, unless they have to be UUIDs, orders) are limited to ASCII letters, digits,
+// '-', '_' and '.', so that they can be used in paths and query strings as they are.
+pub const MAX_ID_LEN: usize = 64;
+pub const MAX_NAME_LEN: usize = 100;
+pub const MAX_URL_LEN: usize = 2048;
+pub const MAX_SECRET_LEN: usize = 256;
+
+// IdFormat is the format order ids are required to have, set with APP_ORDER_ID_FORMAT.
+// opaque (default): any id within the bounds above
+// uuid4: a hyphenated UUID version 4, as generated by the waitstaff's devices
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IdFormat {
+    #[default]
+    Opaque,
+    Uuid4,
+}
+
+impl IdFormat {
+    pub fn parse(s: &str) -> Option<IdFormat> {
+        match s {
+            "opaque" => Some(IdFormat::Opaque),
+            "uuid4" => Some(IdFormat::Uuid4),
+            _ => None,
+        }
+    }
+}
+
+// FieldError describes why a field of a request was rejected. code is one of
+// REQUIRED, TOO_LONG, INVALID_CHARS, INVALID_FORMAT or, for a retried order, CONFLICT.
+#[derive(Serialize, Clone, Deserialize, Debug, PartialEq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, code: &str, message: String) -> FieldError {
+        FieldError {
+            field: field.to_string(),
+            code: code.to_string(),
+            message,
+        }
+    }
+}
+
+// Validator checks the fields of a request and collects every violation, so that a client
+// learns about all of them at once.
+#[derive(Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Validator {
+        Validator::default()
+    }
+
+    pub fn order_id(&mut self, field: &str, value: &str, format: IdFormat) -> &mut Validator {
+        match format {
+            IdFormat::Opaque => self.id(field, value),
+            IdFormat::Uuid4 => {
+                let is_uuid4 = value.len() == 36
+                    && Uuid::parse_str(value).is_ok_and(|uuid| uuid.get_version() == Some(Version::Random));
+                if !is_uuid4 {
+                    self.reject(field, "INVALID_FORMAT", "must be a hyphenated UUID version 4".to_string());
+                }
+                self
+            }
+        }
+    }
+
+    pub fn id(&mut self, field: &str, value: &str) -> &mut Validator {
+        if value.is_empty() {
+            self.reject(field, "REQUIRED", "must not be empty".to_string());
+        } else if value.len() > MAX_ID_LEN {
+            self.reject(field, "TOO_LONG", format!("must be at most {} characters", MAX_ID_LEN));
+        } else if !value.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+            self.reject(field, "INVALID_CHARS", "may only contain ASCII letters, digits, '-', '_' and '.'".to_string());
+        }
+        self
+    }
+
+    pub fn text(&mut self, field: &str, value: &str, max_len: usize) -> &mut Validator {
+        if value.trim().is_empty() {
+            self.reject(field, "REQUIRED", "must not be empty".to_string());
+        } else if value.chars().count() > max_len {
+            self.reject(field, "TOO_LONG", format!("must be at most {} characters", max_len));
+        } else if value.chars().any(char::is_control) {
+            self.reject(field, "INVALID_CHARS", "must not contain control characters".to_string());
+        }
+        self
+    }
+
+    fn reject(&mut self, field: &str, code: &str, message: String) {
+        self.errors.push(FieldError::new(field, code, message));
+    }
+
+    pub fn finish(&mut self) -> Result<(), Vec<FieldError>> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+}
@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod tests {
+    use crate::validation::{FieldError, IdFormat, Validator, MAX_ID_LEN, MAX_NAME_LEN};
+
+    fn codes(result: Result<(), Vec<FieldError>>) -> Vec<(String, String)> {
+        result
+            .unwrap_err()
+            .into_iter()
+            .map(|error| (error.field, error.code))
+            .collect()
+    }
+
+    fn code(field: &str, code: &str) -> (String, String) {
+        (field.to_string(), code.to_string())
+    }
+
+    #[test]
+    fn test_parse_id_format() {
+        assert_eq!(IdFormat::parse("opaque"), Some(IdFormat::Opaque));
+        assert_eq!(IdFormat::parse("uuid4"), Some(IdFormat::Uuid4));
+        assert_eq!(IdFormat::parse("uuid"), None);
+        assert_eq!(IdFormat::default(), IdFormat::Opaque);
+    }
+
+    #[test]
+    fn test_id_accepts_bounded_ascii() {
+        assert!(Validator::new().id("id", "table-1_a.b").finish().is_ok());
+        assert!(Validator::new().id("id", &"a".repeat(MAX_ID_LEN)).finish().is_ok());
+    }
+
+    #[test]
+    fn test_id_rejects_empty_long_and_odd_chars() {
+        assert_eq!(codes(Validator::new().id("id", "").finish()), vec![code("id", "REQUIRED")]);
+        assert_eq!(codes(Validator::new().id("id", &"a".repeat(MAX_ID_LEN + 1)).finish()), vec![code("id", "TOO_LONG")]);
+        assert_eq!(codes(Validator::new().id("id", "table 1").finish()), vec![code("id", "INVALID_CHARS")]);
+        assert_eq!(codes(Validator::new().id("id", "tisch-ä").finish()), vec![code("id", "INVALID_CHARS")]);
+        assert_eq!(codes(Validator::new().id("id", "a/b").finish()), vec![code("id", "INVALID_CHARS")]);
+    }
+
+    #[test]
+    fn test_order_id_formats() {
+        let uuid4 = "4f2a6c1e-8b3d-4e5f-9a7b-1c2d3e4f5a6b";
+        let uuid1 = "c232ab00-9414-11ec-b3c8-9f6bdeced846";
+
+        assert!(Validator::new().order_id("id", "order1", IdFormat::Opaque).finish().is_ok());
+        assert!(Validator::new().order_id("id", uuid1, IdFormat::Opaque).finish().is_ok());
+        assert!(Validator::new().order_id("id", uuid4, IdFormat::Uuid4).finish().is_ok());
+
+        assert_eq!(codes(Validator::new().order_id("id", "order 1", IdFormat::Opaque).finish()), vec![code("id", "INVALID_CHARS")]);
+        assert_eq!(codes(Validator::new().order_id("id", "order1", IdFormat::Uuid4).finish()), vec![code("id", "INVALID_FORMAT")]);
+        assert_eq!(codes(Validator::new().order_id("id", uuid1, IdFormat::Uuid4).finish()), vec![code("id", "INVALID_FORMAT")]);
+        // Only the hyphenated form is accepted.
+        let simple = uuid4.replace('-', "");
+        assert_eq!(codes(Validator::new().order_id("id", &simple, IdFormat::Uuid4).finish()), vec![code("id", "INVALID_FORMAT")]);
+    }
+
+    #[test]
+    fn test_text_bounds() {
+        assert!(Validator::new().text("name", "Crème brûlée", MAX_NAME_LEN).finish().is_ok());
+        // Length is counted in characters, not bytes.
+        assert!(Validator::new().text("name", &"é".repeat(MAX_NAME_LEN), MAX_NAME_LEN).finish().is_ok());
+
+        assert_eq!(codes(Validator::new().text("name", "  ", MAX_NAME_LEN).finish()), vec![code("name", "REQUIRED")]);
+        assert_eq!(codes(Validator::new().text("name", &"é".repeat(MAX_NAME_LEN + 1), MAX_NAME_LEN).finish()), vec![code("name", "TOO_LONG")]);
+        assert_eq!(codes(Validator::new().text("name", "Burger\n", MAX_NAME_LEN).finish()), vec![code("name", "INVALID_CHARS")]);
+    }
+
+    #[test]
+    fn test_every_violation_is_collected() {
+        let mut validator = Validator::new();
+        validator
+            .order_id("id", "order1", IdFormat::Uuid4)
+            .id("item_id", "burger")
+            .id("table_id", "")
+            .text("name", "\u{7}", MAX_NAME_LEN);
+
+        let errors = validator.finish().unwrap_err();
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0], FieldError::new("id", "INVALID_FORMAT", "must be a hyphenated UUID version 4".to_string()));
+        assert_eq!((errors[1].field.as_str(), errors[1].code.as_str()), ("table_id", "REQUIRED"));
+        assert_eq!((errors[2].field.as_str(), errors[2].code.as_str()), ("name", "INVALID_CHARS"));
+
+        // The validator is empty once finished.
+        assert!(validator.finish().is_ok());
+    }
+}
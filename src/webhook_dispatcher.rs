@@ -0,0 +1,223 @@
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::header::CONTENT_TYPE;
+use rocket::Shutdown;
+use rocket::serde::Serialize;
+use rocket::serde::json::serde_json;
+use rocket::tokio::{self, select};
+use rocket::tokio::sync::broadcast::error::RecvError;
+use sha2::Sha256;
+use uuid::Uuid;
+use crate::order_events::{OrderEvent, OrderEvents, Subscription};
+use crate::order_service::OrderResult;
+use crate::webhook_service::{Delivery, DeliveryStatus, Webhook, WebhookEvent, WebhookService, WebhookServiceError};
+
+// Every delivery is a POST of a JSON payload with these headers. The signature is
+// "sha256=" followed by the hex encoded HMAC-SHA256 of the body, keyed with the webhook's secret.
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+pub const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+// How long a single attempt may take, including reading the response.
+const ATTEMPT_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+// RetryPolicy allows max_attempts attempts per delivery. The wait after a failed attempt starts
+// at initial_backoff and doubles with every further attempt, up to an hour.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy { max_attempts: 5, initial_backoff: Duration::from_secs(1) }
+    }
+}
+
+impl RetryPolicy {
+    // The wait after the given number of failed attempts.
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(MAX_BACKOFF)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Payload<'a> {
+    delivery_id: &'a str,
+    webhook_id: &'a str,
+    event: WebhookEvent,
+    order: &'a OrderResult,
+}
+
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+// WebhookDispatcher delivers order events to the webhooks subscribed to them.
+// Deliveries are made in the background and recorded in the WebhookService as they progress.
+// Deliveries that are still pending on shutdown are not resumed.
+pub struct WebhookDispatcher {
+    service: Arc<dyn WebhookService>,
+    client: reqwest::Client,
+    retry: RetryPolicy,
+}
+
+impl WebhookDispatcher {
+    pub fn new(service: Arc<dyn WebhookService>, retry: RetryPolicy) -> WebhookDispatcher {
+        let client = reqwest::Client::builder()
+            .timeout(ATTEMPT_TIMEOUT)
+            .build()
+            .expect("Failed to build HTTP client");
+        WebhookDispatcher { service, client, retry }
+    }
+
+    // Dispatches the events published since subscribing, until shutdown. The caller subscribes
+    // before anything may publish an event, since the task may only start running later on.
+    pub async fn run(self: Arc<Self>, events: Arc<OrderEvents>, mut subscription: Subscription, mut shutdown: Shutdown) {
+        loop {
+            let mut last_id = subscription.last_id;
+            match subscription.missed.take() {
+                Some(missed) => for event in missed {
+                    self.dispatch(&event);
+                },
+                None => warn!("Webhook dispatcher fell behind, events after {} are not delivered", last_id),
+            }
+
+            loop {
+                let event = select! {
+                    biased;
+                    event = subscription.receiver.recv() => event,
+                    _ = &mut shutdown => return,
+                };
+                match event {
+                    Ok(event) => {
+                        last_id = event.id;
+                        self.dispatch(&event);
+                    }
+                    Err(RecvError::Lagged(_)) => break,
+                    Err(RecvError::Closed) => return,
+                }
+            }
+            subscription = events.subscribe(Some(last_id));
+        }
+    }
+
+    // Records a pending delivery of the event to every webhook subscribed to it and starts
+    // making them. Deliveries are independent of each other, so a retried delivery may arrive
+    // after the deliveries of later events.
+    pub fn dispatch(self: &Arc<Self>, event: &OrderEvent) -> Vec<Delivery> {
+        let Some(webhook_event) = WebhookEvent::of(event) else {
+            return Vec::new();
+        };
+        let webhooks = match self.service.get_webhooks(Some(webhook_event)) {
+            Ok(webhooks) => webhooks,
+            Err(e) => {
+                error!("Failed to dispatch event {}: {}", event.id, e);
+                return Vec::new();
+            }
+        };
+
+        let mut deliveries = Vec::new();
+        for webhook in webhooks {
+            let now = Utc::now();
+            let delivery = Delivery {
+                delivery_id: Uuid::new_v4().to_string(),
+                webhook_id: webhook.webhook_id.clone(),
+                event: webhook_event,
+                order_id: event.order.order_id.clone(),
+                status: DeliveryStatus::Pending,
+                attempts: 0,
+                response_status: None,
+                error: None,
+                created_at: now,
+                updated_at: now,
+                next_attempt_at: Some(now),
+            };
+            let body = serde_json::to_vec(&Payload {
+                delivery_id: &delivery.delivery_id,
+                webhook_id: &delivery.webhook_id,
+                event: webhook_event,
+                order: &event.order,
+            }).expect("Failed to serialize webhook payload");
+
+            self.record(&delivery);
+            tokio::spawn(self.clone().deliver(webhook, delivery.clone(), body));
+            deliveries.push(delivery);
+        }
+        deliveries
+    }
+
+    async fn deliver(self: Arc<Self>, mut webhook: Webhook, mut delivery: Delivery, body: Vec<u8>) {
+        loop {
+            delivery.attempts += 1;
+            let result = self.client.post(&webhook.url)
+                .header(CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, delivery.event.as_str())
+                .header(DELIVERY_HEADER, &delivery.delivery_id)
+                .header(SIGNATURE_HEADER, sign(&webhook.secret, &body))
+                .body(body.clone())
+                .send()
+                .await;
+
+            delivery.updated_at = Utc::now();
+            delivery.next_attempt_at = None;
+            match result {
+                Ok(response) if response.status().is_success() => {
+                    delivery.status = DeliveryStatus::Delivered;
+                    delivery.response_status = Some(response.status().as_u16());
+                    delivery.error = None;
+                    self.record(&delivery);
+                    return;
+                }
+                Ok(response) => {
+                    delivery.response_status = Some(response.status().as_u16());
+                    delivery.error = Some(format!("Unexpected response status {}", response.status()));
+                }
+                Err(e) => {
+                    delivery.response_status = None;
+                    delivery.error = Some(e.to_string());
+                }
+            }
+
+            if delivery.attempts >= self.retry.max_attempts {
+                warn!("Giving up on webhook delivery '{}': {}", delivery.delivery_id, delivery.error.as_deref().unwrap_or_default());
+                delivery.status = DeliveryStatus::Failed;
+                self.record(&delivery);
+                return;
+            }
+            let backoff = self.retry.backoff(delivery.attempts);
+            delivery.next_attempt_at = chrono::Duration::from_std(backoff).ok().map(|backoff| delivery.updated_at + backoff);
+            self.record(&delivery);
+            tokio::time::sleep(backoff).await;
+
+            // The webhook may have been changed or removed in the meantime.
+            match self.service.get_webhook(&webhook.webhook_id) {
+                Ok(current) => webhook = current,
+                Err(WebhookServiceError::WebhookNotFound(_)) => {
+                    delivery.status = DeliveryStatus::Failed;
+                    delivery.error = Some("Webhook was removed".to_string());
+                    delivery.updated_at = Utc::now();
+                    delivery.next_attempt_at = None;
+                    self.record(&delivery);
+                    return;
+                }
+                Err(e) => error!("Failed to refresh webhook '{}': {}", webhook.webhook_id, e),
+            }
+        }
+    }
+
+    fn record(&self, delivery: &Delivery) {
+        if let Err(e) = self.service.put_delivery(delivery.clone()) {
+            error!("Failed to record webhook delivery '{}': {}", delivery.delivery_id, e);
+        }
+    }
+}
@@ -0,0 +1,235 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use rocket::serde::json::{serde_json, Value};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use crate::order_events::{OrderEvent, OrderEventKind};
+    use crate::order_service::{Order, OrderResult, OrderStatus};
+    use crate::webhook_dispatcher::{sign, RetryPolicy, WebhookDispatcher, DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER};
+    use crate::webhook_service::{new_in_memory, Delivery, DeliveryStatus, Webhook, WebhookEvent, WebhookService};
+
+    // Request is what the stand-in received: lowercase header names and the body.
+    struct Request {
+        headers: HashMap<String, String>,
+        body: Vec<u8>,
+    }
+
+    // StandIn is a local HTTP server standing in for a webhook receiver. It answers the requests
+    // with the given statuses in turn, and with 200 once they run out.
+    struct StandIn {
+        url: String,
+        requests: Arc<Mutex<Vec<Request>>>,
+    }
+
+    impl StandIn {
+        async fn start(statuses: Vec<u16>) -> StandIn {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}/hooks", listener.local_addr().unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+
+            let received = requests.clone();
+            tokio::spawn(async move {
+                let mut statuses = statuses.into_iter();
+                loop {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    let status = statuses.next().unwrap_or(200);
+                    let request = respond(stream, status).await;
+                    received.lock().unwrap().push(request);
+                }
+            });
+
+            StandIn { url, requests }
+        }
+
+        fn requests(&self) -> usize {
+            self.requests.lock().unwrap().len()
+        }
+    }
+
+    async fn respond(mut stream: TcpStream, status: u16) -> Request {
+        let mut data = Vec::new();
+        let mut buf = [0u8; 4096];
+        let header_end = loop {
+            let n = stream.read(&mut buf).await.unwrap();
+            data.extend_from_slice(&buf[..n]);
+            if let Some(pos) = data.windows(4).position(|window| window == b"\r\n\r\n") {
+                break pos + 4;
+            }
+        };
+
+        let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+        let headers: HashMap<String, String> = head
+            .lines()
+            .skip(1)
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+            .collect();
+        let length: usize = headers.get("content-length").map_or(0, |length| length.parse().unwrap());
+        while data.len() < header_end + length {
+            let n = stream.read(&mut buf).await.unwrap();
+            data.extend_from_slice(&buf[..n]);
+        }
+
+        let response = format!("HTTP/1.1 {} Stand-In\r\ncontent-length: 0\r\nconnection: close\r\n\r\n", status);
+        stream.write_all(response.as_bytes()).await.unwrap();
+        stream.shutdown().await.unwrap();
+
+        Request { headers, body: data[header_end..header_end + length].to_vec() }
+    }
+
+    fn setup_dispatcher(max_attempts: u32, initial_backoff: Duration) -> (Arc<dyn WebhookService>, Arc<WebhookDispatcher>) {
+        let service: Arc<dyn WebhookService> = Arc::new(new_in_memory());
+        let retry = RetryPolicy { max_attempts, initial_backoff };
+        let dispatcher = Arc::new(WebhookDispatcher::new(service.clone(), retry));
+        (service, dispatcher)
+    }
+
+    fn webhook(url: &str, events: Vec<WebhookEvent>) -> Webhook {
+        Webhook {
+            webhook_id: "pos".to_string(),
+            url: url.to_string(),
+            events,
+            secret: "secret".to_string(),
+        }
+    }
+
+    fn event(kind: OrderEventKind, status: OrderStatus) -> OrderEvent {
+        let order = Order {
+            item_id: "item1".to_string(),
+            table_id: "table1".to_string(),
+            ..Default::default()
+        };
+        OrderEvent {
+            id: 1,
+            kind,
+            order: OrderResult {
+                status,
+                ..OrderResult::placed("order1".to_string(), order, 10)
+            },
+        }
+    }
+
+    // Waits for the delivery to be finished, one way or another.
+    async fn finished(service: &Arc<dyn WebhookService>, delivery_id: &str) -> Delivery {
+        for _ in 0..500 {
+            let delivery = service.get_deliveries(None, None).unwrap()
+                .into_iter()
+                .find(|delivery| delivery.delivery_id == delivery_id)
+                .unwrap();
+            if delivery.status != DeliveryStatus::Pending {
+                return delivery;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("Delivery '{}' is still pending", delivery_id);
+    }
+
+    #[test]
+    fn test_sign() {
+        // RFC 4231, test case 2.
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_backoff_doubles() {
+        let retry = RetryPolicy { max_attempts: 5, initial_backoff: Duration::from_millis(100) };
+
+        assert_eq!(retry.backoff(1), Duration::from_millis(100));
+        assert_eq!(retry.backoff(2), Duration::from_millis(200));
+        assert_eq!(retry.backoff(4), Duration::from_millis(800));
+        assert_eq!(retry.backoff(100), Duration::from_secs(60 * 60));
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_delivers_signed_event() {
+        let stand_in = StandIn::start(vec![]).await;
+        let (service, dispatcher) = setup_dispatcher(3, Duration::from_millis(10));
+        service.put_webhook(webhook(&stand_in.url, vec![WebhookEvent::Ready])).unwrap();
+
+        // Nobody is subscribed to these.
+        assert!(dispatcher.dispatch(&event(OrderEventKind::Created, OrderStatus::Placed)).is_empty());
+        assert!(dispatcher.dispatch(&event(OrderEventKind::Updated, OrderStatus::Cooking)).is_empty());
+
+        let deliveries = dispatcher.dispatch(&event(OrderEventKind::Updated, OrderStatus::Ready));
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].status, DeliveryStatus::Pending);
+        assert_eq!(deliveries[0].event, WebhookEvent::Ready);
+
+        let delivery = finished(&service, &deliveries[0].delivery_id).await;
+        assert_eq!(delivery.status, DeliveryStatus::Delivered);
+        assert_eq!(delivery.attempts, 1);
+        assert_eq!(delivery.response_status, Some(200));
+        assert_eq!(delivery.next_attempt_at, None);
+
+        let requests = stand_in.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        let request = &requests[0];
+        assert_eq!(request.headers["content-type"], "application/json");
+        assert_eq!(request.headers[&EVENT_HEADER.to_lowercase()], "order_ready");
+        assert_eq!(request.headers[&DELIVERY_HEADER.to_lowercase()], delivery.delivery_id);
+        assert_eq!(request.headers[&SIGNATURE_HEADER.to_lowercase()], sign("secret", &request.body));
+
+        let payload: Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(payload["delivery_id"], delivery.delivery_id);
+        assert_eq!(payload["webhook_id"], "pos");
+        assert_eq!(payload["event"], "order_ready");
+        assert_eq!(payload["order"]["order_id"], "order1");
+        assert_eq!(payload["order"]["status"], "ready");
+    }
+
+    #[tokio::test]
+    async fn test_failed_delivery_is_retried() {
+        let stand_in = StandIn::start(vec![500, 503]).await;
+        let (service, dispatcher) = setup_dispatcher(3, Duration::from_millis(10));
+        service.put_webhook(webhook(&stand_in.url, vec![WebhookEvent::Created])).unwrap();
+
+        let deliveries = dispatcher.dispatch(&event(OrderEventKind::Created, OrderStatus::Placed));
+        let delivery = finished(&service, &deliveries[0].delivery_id).await;
+
+        assert_eq!(delivery.status, DeliveryStatus::Delivered);
+        assert_eq!(delivery.attempts, 3);
+        assert_eq!(delivery.error, None);
+        assert_eq!(stand_in.requests(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_delivery_fails_after_max_attempts() {
+        let stand_in = StandIn::start(vec![500, 500, 500]).await;
+        let (service, dispatcher) = setup_dispatcher(2, Duration::from_millis(10));
+        service.put_webhook(webhook(&stand_in.url, vec![WebhookEvent::Deleted])).unwrap();
+
+        let deliveries = dispatcher.dispatch(&event(OrderEventKind::Deleted, OrderStatus::Placed));
+        let delivery = finished(&service, &deliveries[0].delivery_id).await;
+
+        assert_eq!(delivery.status, DeliveryStatus::Failed);
+        assert_eq!(delivery.attempts, 2);
+        assert_eq!(delivery.response_status, Some(500));
+        assert!(delivery.error.is_some());
+        assert_eq!(stand_in.requests(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_delivery_to_removed_webhook_fails() {
+        let stand_in = StandIn::start(vec![500]).await;
+        let (service, dispatcher) = setup_dispatcher(3, Duration::from_millis(200));
+        service.put_webhook(webhook(&stand_in.url, vec![WebhookEvent::Created])).unwrap();
+
+        let deliveries = dispatcher.dispatch(&event(OrderEventKind::Created, OrderStatus::Placed));
+        while stand_in.requests() == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        service.delete_webhook("pos".to_string()).unwrap();
+        let delivery = finished(&service, &deliveries[0].delivery_id).await;
+
+        assert_eq!(delivery.status, DeliveryStatus::Failed);
+        assert_eq!(delivery.attempts, 1);
+        assert_eq!(delivery.error.as_deref(), Some("Webhook was removed"));
+        assert_eq!(stand_in.requests(), 1);
+    }
+}
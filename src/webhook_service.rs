@@ -0,0 +1,255 @@
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::sync::RwLock;
+use chrono::{DateTime, Utc};
+use rocket::serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::order_events::{OrderEvent, OrderEventKind};
+use crate::order_service::OrderStatus;
+
+// Deliveries kept for the admin listing. The oldest ones are dropped first.
+pub const MAX_DELIVERIES: usize = 10_000;
+
+#[derive(Serialize, Clone, Copy, Deserialize, Debug, PartialEq, Eq, Hash, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub enum WebhookEvent {
+    #[serde(rename = "order_created")]
+    Created,
+    #[serde(rename = "order_ready")]
+    Ready,
+    #[serde(rename = "order_deleted")]
+    Deleted,
+}
+
+impl WebhookEvent {
+    pub const ALL: [WebhookEvent; 3] = [WebhookEvent::Created, WebhookEvent::Ready, WebhookEvent::Deleted];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::Created => "order_created",
+            WebhookEvent::Ready => "order_ready",
+            WebhookEvent::Deleted => "order_deleted",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<WebhookEvent> {
+        WebhookEvent::ALL.into_iter().find(|event| event.as_str() == s)
+    }
+
+    // The webhook event an order event amounts to, if any. Only the transition to ready is of
+    // interest among the status changes.
+    pub fn of(event: &OrderEvent) -> Option<WebhookEvent> {
+        match event.kind {
+            OrderEventKind::Created => Some(WebhookEvent::Created),
+            OrderEventKind::Updated if event.order.status == OrderStatus::Ready => Some(WebhookEvent::Ready),
+            OrderEventKind::Updated => None,
+            OrderEventKind::Deleted => Some(WebhookEvent::Deleted),
+        }
+    }
+}
+
+// Webhook subscribes an external system (e.g. the POS) to order events. Every delivery is
+// signed with the shared secret, which is never sent back by the API.
+#[derive(Serialize, Clone, Deserialize, Debug, PartialEq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct Webhook {
+    pub webhook_id: String,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    #[serde(skip_serializing)]
+    pub secret: String,
+}
+
+#[derive(Serialize, Clone, Copy, Deserialize, Debug, PartialEq, Eq, ToSchema)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    // Not delivered yet, more attempts are going to be made.
+    Pending,
+    Delivered,
+    // Every attempt failed, or the webhook was removed in the meantime.
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub const ALL: [DeliveryStatus; 3] = [DeliveryStatus::Pending, DeliveryStatus::Delivered, DeliveryStatus::Failed];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<DeliveryStatus> {
+        DeliveryStatus::ALL.into_iter().find(|status| status.as_str() == s)
+    }
+}
+
+// Delivery tracks sending a single event to a single webhook.
+// response_status and error describe the outcome of the last attempt, if any was made.
+#[derive(Serialize, Clone, Deserialize, Debug, PartialEq, ToSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct Delivery {
+    pub delivery_id: String,
+    pub webhook_id: String,
+    pub event: WebhookEvent,
+    pub order_id: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub response_status: Option<u16>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+pub enum WebhookServiceError {
+    WebhookNotFound(String),
+    InvalidWebhook(String),
+    MutexPoisoned(String),
+}
+
+impl fmt::Display for WebhookServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebhookServiceError::WebhookNotFound(id) => write!(f, "Webhook with id '{}' not found.", id),
+            WebhookServiceError::InvalidWebhook(msg) => write!(f, "Invalid webhook: {}", msg),
+            WebhookServiceError::MutexPoisoned(msg) => write!(f, "Mutex poisoned: {}", msg),
+        }
+    }
+}
+
+impl Error for WebhookServiceError {}
+
+// WebhookService is the registry of webhooks and the record of their deliveries.
+// put_webhook registers a new webhook or replaces an existing one with the same id.
+// put_delivery records a new delivery, one without attempts yet, or the progress of a recorded
+// one. Progress of a delivery that is no longer kept is ignored.
+pub trait WebhookService: Sync + Send {
+    fn put_webhook(&self, webhook: Webhook) -> Result<Webhook, WebhookServiceError>;
+    fn get_webhook(&self, webhook_id: &str) -> Result<Webhook, WebhookServiceError>;
+    fn get_webhooks(&self, event: Option<WebhookEvent>) -> Result<Vec<Webhook>, WebhookServiceError>;
+    fn delete_webhook(&self, webhook_id: String) -> Result<Webhook, WebhookServiceError>;
+    fn put_delivery(&self, delivery: Delivery) -> Result<Delivery, WebhookServiceError>;
+    fn get_deliveries(&self, webhook_id: Option<String>, status: Option<DeliveryStatus>) -> Result<Vec<Delivery>, WebhookServiceError>;
+}
+
+// DeliveryLog keeps the latest MAX_DELIVERIES deliveries by id, along with their ids in the
+// order they were recorded in.
+#[derive(Default)]
+struct DeliveryLog {
+    deliveries: HashMap<String, Delivery>,
+    ids: VecDeque<String>,
+}
+
+// InMemoryWebhookService keeps the webhooks in a HashMap and the deliveries in a DeliveryLog,
+// each wrapped in RwLock for thread safety.
+pub struct InMemoryWebhookService {
+    webhooks: RwLock<HashMap<String, Webhook>>,
+    deliveries: RwLock<DeliveryLog>,
+}
+
+pub fn new_in_memory() -> InMemoryWebhookService {
+    InMemoryWebhookService {
+        webhooks: RwLock::new(HashMap::new()),
+        deliveries: RwLock::new(DeliveryLog::default()),
+    }
+}
+
+fn validate(webhook: &Webhook) -> Result<(), WebhookServiceError> {
+    if webhook.webhook_id.trim().is_empty() {
+        return Err(WebhookServiceError::InvalidWebhook("webhook_id must not be empty".into()));
+    }
+    match reqwest::Url::parse(&webhook.url) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+        _ => return Err(WebhookServiceError::InvalidWebhook("url must be an absolute http(s) URL".into())),
+    }
+    if webhook.events.is_empty() {
+        return Err(WebhookServiceError::InvalidWebhook("events must not be empty".into()));
+    }
+    if webhook.secret.is_empty() {
+        return Err(WebhookServiceError::InvalidWebhook("secret must not be empty".into()));
+    }
+    Ok(())
+}
+
+impl WebhookService for InMemoryWebhookService {
+    fn put_webhook(&self, mut webhook: Webhook) -> Result<Webhook, WebhookServiceError> {
+        validate(&webhook)?;
+        webhook.events.sort_by_key(|event| event.as_str());
+        webhook.events.dedup();
+
+        let mut webhooks = self.webhooks.write()
+            .map_err(|_| WebhookServiceError::MutexPoisoned("Failed to obtain write mutex".into()))?;
+        webhooks.insert(webhook.webhook_id.clone(), webhook.clone());
+
+        Ok(webhook)
+    }
+
+    fn get_webhook(&self, webhook_id: &str) -> Result<Webhook, WebhookServiceError> {
+        let webhooks = self.webhooks.read()
+            .map_err(|_| WebhookServiceError::MutexPoisoned("Failed to obtain read mutex".into()))?;
+
+        webhooks.get(webhook_id)
+            .cloned()
+            .ok_or_else(|| WebhookServiceError::WebhookNotFound(webhook_id.to_string()))
+    }
+
+    fn get_webhooks(&self, event: Option<WebhookEvent>) -> Result<Vec<Webhook>, WebhookServiceError> {
+        let webhooks = self.webhooks.read()
+            .map_err(|_| WebhookServiceError::MutexPoisoned("Failed to obtain read mutex".into()))?;
+
+        let mut result: Vec<Webhook> = webhooks
+            .values()
+            .filter(|webhook| event.is_none_or(|event| webhook.events.contains(&event)))
+            .cloned()
+            .collect();
+        result.sort_by(|a, b| a.webhook_id.cmp(&b.webhook_id));
+
+        Ok(result)
+    }
+
+    fn delete_webhook(&self, webhook_id: String) -> Result<Webhook, WebhookServiceError> {
+        let mut webhooks = self.webhooks.write()
+            .map_err(|_| WebhookServiceError::MutexPoisoned("Failed to obtain write mutex".into()))?;
+
+        webhooks.remove(&webhook_id)
+            .ok_or(WebhookServiceError::WebhookNotFound(webhook_id))
+    }
+
+    fn put_delivery(&self, delivery: Delivery) -> Result<Delivery, WebhookServiceError> {
+        let mut log = self.deliveries.write()
+            .map_err(|_| WebhookServiceError::MutexPoisoned("Failed to obtain write mutex".into()))?;
+
+        if let Some(recorded) = log.deliveries.get_mut(&delivery.delivery_id) {
+            *recorded = delivery.clone();
+        } else if delivery.attempts == 0 {
+            log.deliveries.insert(delivery.delivery_id.clone(), delivery.clone());
+            log.ids.push_back(delivery.delivery_id.clone());
+            if log.ids.len() > MAX_DELIVERIES {
+                if let Some(oldest) = log.ids.pop_front() {
+                    log.deliveries.remove(&oldest);
+                }
+            }
+        }
+
+        Ok(delivery)
+    }
+
+    fn get_deliveries(&self, webhook_id: Option<String>, status: Option<DeliveryStatus>) -> Result<Vec<Delivery>, WebhookServiceError> {
+        let log = self.deliveries.read()
+            .map_err(|_| WebhookServiceError::MutexPoisoned("Failed to obtain read mutex".into()))?;
+
+        Ok(log.ids
+            .iter()
+            .rev()
+            .filter_map(|id| log.deliveries.get(id))
+            .filter(|delivery| webhook_id.as_ref().is_none_or(|id| &delivery.webhook_id == id))
+            .filter(|delivery| status.is_none_or(|status| delivery.status == status))
+            .cloned()
+            .collect())
+    }
+}
@@ -0,0 +1,166 @@
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use crate::order_events::{OrderEvent, OrderEventKind};
+    use crate::order_service::{Order, OrderResult, OrderStatus};
+    use crate::webhook_service::{new_in_memory, Delivery, DeliveryStatus, InMemoryWebhookService, Webhook, WebhookEvent, WebhookService, WebhookServiceError, MAX_DELIVERIES};
+
+    fn setup_service() -> InMemoryWebhookService {
+        new_in_memory()
+    }
+
+    fn webhook(webhook_id: &str, events: Vec<WebhookEvent>) -> Webhook {
+        Webhook {
+            webhook_id: webhook_id.to_string(),
+            url: "http://pos.local/hooks".to_string(),
+            events,
+            secret: "secret".to_string(),
+        }
+    }
+
+    fn delivery(delivery_id: &str, webhook_id: &str, status: DeliveryStatus) -> Delivery {
+        Delivery {
+            delivery_id: delivery_id.to_string(),
+            webhook_id: webhook_id.to_string(),
+            event: WebhookEvent::Created,
+            order_id: "order1".to_string(),
+            status,
+            attempts: 0,
+            response_status: None,
+            error: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            next_attempt_at: None,
+        }
+    }
+
+    fn ids(deliveries: Vec<Delivery>) -> Vec<String> {
+        deliveries.into_iter().map(|delivery| delivery.delivery_id).collect()
+    }
+
+    #[test]
+    fn test_put_and_get_webhook() {
+        let service = setup_service();
+        let stored = service.put_webhook(webhook("pos", vec![
+            WebhookEvent::Ready,
+            WebhookEvent::Created,
+            WebhookEvent::Ready,
+        ])).unwrap();
+
+        assert_eq!(stored.events, vec![WebhookEvent::Created, WebhookEvent::Ready]);
+        assert_eq!(service.get_webhook("pos").unwrap(), stored);
+        assert!(matches!(service.get_webhook("pager"), Err(WebhookServiceError::WebhookNotFound(_))));
+    }
+
+    #[test]
+    fn test_put_invalid_webhook_fails() {
+        let service = setup_service();
+
+        let mut invalid = webhook("pos", vec![WebhookEvent::Created]);
+        invalid.url = "ftp://pos.local/hooks".to_string();
+        assert!(matches!(service.put_webhook(invalid), Err(WebhookServiceError::InvalidWebhook(_))));
+
+        let mut invalid = webhook("pos", vec![WebhookEvent::Created]);
+        invalid.url = "/hooks".to_string();
+        assert!(matches!(service.put_webhook(invalid), Err(WebhookServiceError::InvalidWebhook(_))));
+
+        let mut invalid = webhook("pos", vec![WebhookEvent::Created]);
+        invalid.secret = String::new();
+        assert!(matches!(service.put_webhook(invalid), Err(WebhookServiceError::InvalidWebhook(_))));
+
+        assert!(matches!(service.put_webhook(webhook("pos", vec![])), Err(WebhookServiceError::InvalidWebhook(_))));
+        assert!(matches!(service.put_webhook(webhook(" ", vec![WebhookEvent::Created])), Err(WebhookServiceError::InvalidWebhook(_))));
+        assert!(service.get_webhooks(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_get_webhooks_by_event() {
+        let service = setup_service();
+        service.put_webhook(webhook("pos", vec![WebhookEvent::Created, WebhookEvent::Deleted])).unwrap();
+        service.put_webhook(webhook("pager", vec![WebhookEvent::Ready])).unwrap();
+
+        let ids = |event| -> Vec<String> {
+            service.get_webhooks(event).unwrap().into_iter().map(|webhook| webhook.webhook_id).collect()
+        };
+        assert_eq!(ids(None), vec!["pager", "pos"]);
+        assert_eq!(ids(Some(WebhookEvent::Created)), vec!["pos"]);
+        assert_eq!(ids(Some(WebhookEvent::Ready)), vec!["pager"]);
+    }
+
+    #[test]
+    fn test_delete_webhook() {
+        let service = setup_service();
+        service.put_webhook(webhook("pos", vec![WebhookEvent::Created])).unwrap();
+
+        assert_eq!(service.delete_webhook("pos".to_string()).unwrap().webhook_id, "pos");
+        assert!(matches!(service.delete_webhook("pos".to_string()), Err(WebhookServiceError::WebhookNotFound(_))));
+        assert!(service.get_webhooks(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_webhook_event_of_order_event() {
+        let event = |kind, status| OrderEvent {
+            id: 1,
+            kind,
+            order: OrderResult {
+                status,
+                ..OrderResult::placed("order1".to_string(), Order::default(), 10)
+            },
+        };
+
+        assert_eq!(WebhookEvent::of(&event(OrderEventKind::Created, OrderStatus::Placed)), Some(WebhookEvent::Created));
+        assert_eq!(WebhookEvent::of(&event(OrderEventKind::Updated, OrderStatus::Cooking)), None);
+        assert_eq!(WebhookEvent::of(&event(OrderEventKind::Updated, OrderStatus::Ready)), Some(WebhookEvent::Ready));
+        assert_eq!(WebhookEvent::of(&event(OrderEventKind::Deleted, OrderStatus::Ready)), Some(WebhookEvent::Deleted));
+    }
+
+    #[test]
+    fn test_put_and_get_deliveries() {
+        let service = setup_service();
+        service.put_delivery(delivery("d1", "pos", DeliveryStatus::Pending)).unwrap();
+        service.put_delivery(delivery("d2", "pager", DeliveryStatus::Pending)).unwrap();
+        service.put_delivery(delivery("d3", "pos", DeliveryStatus::Pending)).unwrap();
+
+        let mut delivered = delivery("d1", "pos", DeliveryStatus::Delivered);
+        delivered.attempts = 2;
+        delivered.response_status = Some(200);
+        service.put_delivery(delivered.clone()).unwrap();
+
+        // Newest first; recording progress does not reorder deliveries.
+        assert_eq!(ids(service.get_deliveries(None, None).unwrap()), vec!["d3", "d2", "d1"]);
+        assert_eq!(ids(service.get_deliveries(Some("pos".to_string()), None).unwrap()), vec!["d3", "d1"]);
+        assert_eq!(service.get_deliveries(None, Some(DeliveryStatus::Delivered)).unwrap(), vec![delivered]);
+        assert_eq!(ids(service.get_deliveries(Some("pager".to_string()), Some(DeliveryStatus::Pending)).unwrap()), vec!["d2"]);
+        assert!(service.get_deliveries(None, Some(DeliveryStatus::Failed)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_oldest_deliveries_are_dropped() {
+        let service = setup_service();
+        for i in 0..=MAX_DELIVERIES {
+            service.put_delivery(delivery(&format!("d{}", i), "pos", DeliveryStatus::Delivered)).unwrap();
+        }
+
+        let deliveries = ids(service.get_deliveries(None, None).unwrap());
+        assert_eq!(deliveries.len(), MAX_DELIVERIES);
+        assert_eq!(deliveries.first().unwrap(), &format!("d{}", MAX_DELIVERIES));
+        assert_eq!(deliveries.last().unwrap(), "d1");
+    }
+
+    #[test]
+    fn test_progress_of_dropped_deliveries_is_ignored() {
+        let service = setup_service();
+        for i in 0..=MAX_DELIVERIES {
+            service.put_delivery(delivery(&format!("d{}", i), "pos", DeliveryStatus::Pending)).unwrap();
+        }
+
+        let mut delivered = delivery("d0", "pos", DeliveryStatus::Delivered);
+        delivered.attempts = 1;
+        service.put_delivery(delivered).unwrap();
+
+        let deliveries = ids(service.get_deliveries(None, None).unwrap());
+        assert_eq!(deliveries.len(), MAX_DELIVERIES);
+        assert_eq!(deliveries.first().unwrap(), &format!("d{}", MAX_DELIVERIES));
+        assert_eq!(deliveries.last().unwrap(), "d1");
+    }
+}